@@ -0,0 +1,116 @@
+//! A fixed logical arena shown whole in any window: the camera scales it to
+//! fit and black bars hide what is around it. Shared by the Bevy levels, which
+//! insert a `GameArea` and add the `LetterboxPlugin`.
+
+use bevy::{prelude::*, render::camera::Camera};
+
+/// Size of the playfield in world units, centered on the origin. It does not
+/// depend on the window, the game gives it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GameArea {
+    pub width: f32,
+    pub height: f32
+}
+
+impl GameArea {
+    pub fn new(width: f32, height: f32) -> GameArea {
+        GameArea { width, height }
+    }
+
+    pub fn demi_width(&self) -> f32 {
+        self.width * 0.5
+    }
+
+    pub fn demi_height(&self) -> f32 {
+        self.height * 0.5
+    }
+}
+
+/// Camera showing the `GameArea`, `zoom` above 1 shows only a part of it.
+pub struct LetterboxCamera {
+    pub zoom: f32
+}
+
+/// Hides one side around the area.
+struct Bar;
+
+/// Bars cover this much around the area, more than any window shows.
+const BAR_LENGTH: f32 = 100_000.0;
+/// In front of everything, the 2D camera sees up to 1000.
+const BAR_DEPTH: f32 = 900.0;
+
+/// Spawns the camera and the bars, and fits them to the window and the area.
+pub struct LetterboxPlugin;
+
+impl Plugin for LetterboxPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_startup_system(setup_letterbox.system())
+            .add_system(fit_camera.system())
+            .add_system(place_bars.system());
+    }
+}
+
+fn setup_letterbox(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    commands.spawn_bundle(OrthographicCameraBundle::new_2d())
+        .insert(LetterboxCamera { zoom: 1.0 });
+
+    let material = materials.add(Color::BLACK.into());
+    for _ in 0..4 {
+        commands
+            .spawn_bundle(SpriteBundle {
+                material: material.clone(),
+                sprite: Sprite::new(Vec2::ZERO),
+                ..Default::default()
+            })
+            .insert(Bar);
+    }
+}
+
+/// World units per window pixel for the whole area to fit in the window.
+pub fn fit_scale(area: &GameArea, window_width: f32, window_height: f32, zoom: f32) -> f32 {
+    if window_width <= 0.0 || window_height <= 0.0 || zoom <= 0.0 {
+        return 1.0;
+    }
+    (area.width / window_width).max(area.height / window_height) / zoom
+}
+
+/// Where the cursor of the window points in the world.
+pub fn window_to_world(window: &Window, camera: &Transform, position: Vec2) -> Vec2 {
+    let center = Vec2::new(window.width(), window.height()) * 0.5;
+    let offset = (position - center) * Vec2::new(camera.scale.x, camera.scale.y);
+    Vec2::new(camera.translation.x, camera.translation.y) + offset
+}
+
+fn fit_camera(
+    area: Res<GameArea>,
+    windows: Res<Windows>,
+    mut cameras: Query<(&mut Transform, &LetterboxCamera), With<Camera>>
+) {
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return
+    };
+    for (mut transform, camera) in cameras.iter_mut() {
+        let scale = fit_scale(&area, window.width(), window.height(), camera.zoom);
+        if transform.scale.x != scale || transform.scale.y != scale {
+            transform.scale = Vec3::new(scale, scale, 1.0);
+        }
+    }
+}
+
+/// Left, right, bottom then top, the side bars go over the corners.
+fn place_bars(area: Res<GameArea>, mut bars: Query<(&mut Transform, &mut Sprite), With<Bar>>) {
+    let offset_x = area.demi_width() + BAR_LENGTH * 0.5;
+    let offset_y = area.demi_height() + BAR_LENGTH * 0.5;
+    let places = [
+        (Vec2::new(-offset_x, 0.0), Vec2::new(BAR_LENGTH, BAR_LENGTH * 3.0)),
+        (Vec2::new(offset_x, 0.0), Vec2::new(BAR_LENGTH, BAR_LENGTH * 3.0)),
+        (Vec2::new(0.0, -offset_y), Vec2::new(area.width, BAR_LENGTH)),
+        (Vec2::new(0.0, offset_y), Vec2::new(area.width, BAR_LENGTH))
+    ];
+
+    for ((mut transform, mut sprite), (position, size)) in bars.iter_mut().zip(places.iter()) {
+        transform.translation = Vec3::new(position.x, position.y, BAR_DEPTH);
+        sprite.size = *size;
+    }
+}
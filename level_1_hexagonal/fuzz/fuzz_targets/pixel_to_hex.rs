@@ -0,0 +1,49 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use tar_engine_rs::hexagonal::Coord;
+use tar_engine_rs::hexagonal::Layout;
+use tar_engine_rs::hexagonal::Orientation;
+use tar_engine_rs::hexagonal::Point;
+
+// Distance in hex size units, where the cells of any layout are regular
+// hexagons again and the nearest center is the containing cell.
+fn distance_sq(layout: &Layout, a: Point, b: Point) -> f32 {
+    let size = layout.size();
+    let (x, y) = ((a.0 - b.0) / size.0, (a.1 - b.1) / size.1);
+    x * x + y * y
+}
+
+// Points right next to a hex corner, where three cells meet: the rounded hex
+// must be the one with the nearest center, whatever the layout.
+fuzz_target!(|data: (i16, i16, u8, u8, i16, i16)| {
+    let (q, r, corner, shape, dx, dy) = data;
+    // f32 pixels are too coarse to tell cells apart near the corners far away from the origin
+    let (q, r) = (q as i32 % 500, r as i32 % 500);
+    let c = Coord::new(q, r, -q - r).unwrap();
+
+    let layout = match shape % 4 {
+        0 => Layout::new(Orientation::POINTY, Point(1.0, 1.0), Point(0.0, 0.0)),
+        1 => Layout::new(Orientation::FLAT, Point(30.0, -30.0), Point(0.0, 0.0)),
+        2 => Layout::builder().with_rotation(shape as f32 / 40.0).with_squash(0.5).with_size(Point(10.0, 10.0)).build(),
+        _ => Layout::builder().with_orientation(Orientation::FLAT).with_tile_size(64.0, 56.0).build()
+    };
+
+    // offset of at most a hundredth of the hex size from the corner
+    let size = layout.size();
+    let corner = layout.polygon_corners(&c)[corner as usize % 6];
+    let point = Point(
+        corner.0 + dx as f32 / i16::MAX as f32 * 0.01 * size.0,
+        corner.1 + dy as f32 / i16::MAX as f32 * 0.01 * size.1
+    );
+
+    let rounded = Coord::from(layout.pixel_to_hex(&point));
+    let nearest = distance_sq(&layout, point, layout.hex_to_pixel(&rounded));
+    for candidate in (0..6).map(|direction| c.neighbour(direction)).chain(std::iter::once(c)) {
+        let other = distance_sq(&layout, point, layout.hex_to_pixel(&candidate));
+        assert!(
+            nearest <= other + 1e-3,
+            "{:?} rounded to {} but {} is closer", point, rounded, candidate
+        );
+    }
+});
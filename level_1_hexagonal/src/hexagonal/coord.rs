@@ -73,6 +73,16 @@ impl FCoord {
             Ok(c)
         }
     }
+
+    pub fn x(&self) -> f32 {
+        self.0
+    }
+    pub fn y(&self) -> f32 {
+        self.1
+    }
+    pub fn z(&self) -> f32 {
+        self.2
+    }
 }
 
 impl From<FCoord> for Coord {
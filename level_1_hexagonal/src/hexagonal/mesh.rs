@@ -0,0 +1,336 @@
+use std::io;
+use std::io::Write;
+
+use super::Coord;
+use super::Layout;
+use super::Map;
+
+/// Triangle soup with flat normals, y is up and the layout plane is x/z.
+pub struct Mesh {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub indices: Vec<u32>
+}
+
+#[allow(dead_code)]
+impl Mesh {
+    /// Extrude every hex of `map` into a prism going from 0 to `height`, up
+    /// or down, so that the mesh is closed whatever the sign of the heights.
+    ///
+    /// Each hex gets its top and bottom faces, and side walls for each edge
+    /// where its prism goes further than the one of the neighbour, missing
+    /// neighbours being flat at 0. Walls between two hexes of the same height
+    /// are skipped, the wall between two different heights is only emitted
+    /// once, by the hex going further from 0.
+    pub fn from_heightmap<T, H>(map: &Map<T>, layout: &Layout, height: H) -> Mesh
+    where
+        H: Fn(Coord, &T) -> f32
+    {
+        let mut mesh = Mesh { positions: Vec::new(), normals: Vec::new(), indices: Vec::new() };
+
+        for (&hexa, value) in map.ground.iter() {
+            let h = height(hexa, value);
+            let (low, high) = (h.min(0.0), h.max(0.0));
+            let center = layout.hex_to_pixel(&hexa);
+            let corners = layout.polygon_corners(&hexa);
+
+            // top and bottom faces, as fans around the first corner
+            for (y, facing) in [(high, [0.0, 1.0, 0.0]), (low, [0.0, -1.0, 0.0])].iter() {
+                let face: Vec<[f32; 3]> = corners.iter().map(|p| [p.0, *y, p.1]).collect();
+                for i in 1..5 {
+                    mesh.add_triangle([face[0], face[i], face[i + 1]], *facing);
+                }
+            }
+
+            for direction in 0..6 {
+                let neighbour = hexa.neighbour(direction);
+                let other_h = match map.ground.get(&neighbour) {
+                    Some(value) => height(neighbour, value),
+                    None => 0.0
+                };
+
+                let (a, b) = layout.edge_corners(direction);
+                let (a, b) = (&corners[a], &corners[b]);
+                let other = layout.hex_to_pixel(&neighbour);
+                let outward = [other.0 - center.0, 0.0, other.1 - center.1];
+                // both prisms contain 0, what sticks out is above or below the neighbour
+                for (from, to) in [(other_h.max(0.0), high), (low, other_h.min(0.0))].iter() {
+                    if from < to {
+                        mesh.add_quad(
+                            [[a.0, *from, a.1], [b.0, *from, b.1], [b.0, *to, b.1], [a.0, *to, a.1]],
+                            outward
+                        );
+                    }
+                }
+            }
+        }
+
+        mesh
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    // Add a triangle facing `facing`, the winding is fixed up so that the
+    // triangle is counter clockwise when seen from that side.
+    fn add_triangle(&mut self, mut vertices: [[f32; 3]; 3], facing: [f32; 3]) {
+        let mut normal = cross(sub(vertices[1], vertices[0]), sub(vertices[2], vertices[0]));
+        if dot(normal, facing) < 0.0 {
+            vertices.swap(1, 2);
+            normal = [-normal[0], -normal[1], -normal[2]];
+        }
+        let normal = normalize(normal);
+
+        for vertex in vertices.iter() {
+            self.indices.push(self.positions.len() as u32);
+            self.positions.push(*vertex);
+            self.normals.push(normal);
+        }
+    }
+
+    fn add_quad(&mut self, vertices: [[f32; 3]; 4], facing: [f32; 3]) {
+        self.add_triangle([vertices[0], vertices[1], vertices[2]], facing);
+        self.add_triangle([vertices[0], vertices[2], vertices[3]], facing);
+    }
+
+    /// Write the mesh as a Wavefront OBJ file.
+    pub fn write_obj<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "# hexagonal heightmap, {} triangles", self.triangle_count())?;
+        for p in self.positions.iter() {
+            writeln!(out, "v {} {} {}", p[0], p[1], p[2])?;
+        }
+        for n in self.normals.iter() {
+            writeln!(out, "vn {} {} {}", n[0], n[1], n[2])?;
+        }
+        for t in self.indices.chunks(3) {
+            // OBJ indices start at 1
+            writeln!(out, "f {0}//{0} {1}//{1} {2}//{2}", t[0] + 1, t[1] + 1, t[2] + 1)?;
+        }
+        Ok(())
+    }
+
+    /// Write the mesh as a self contained glTF 2.0 file, the binary buffer is
+    /// embedded as a base64 data uri.
+    pub fn write_gltf<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let mut buffer = Vec::<u8>::new();
+        for p in self.positions.iter() {
+            for v in p.iter() {
+                buffer.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+        for n in self.normals.iter() {
+            for v in n.iter() {
+                buffer.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+        for i in self.indices.iter() {
+            buffer.extend_from_slice(&i.to_le_bytes());
+        }
+
+        let vertex_bytes = self.positions.len() * 12;
+        let index_bytes = self.indices.len() * 4;
+        let mut min = [f32::INFINITY; 3];
+        let mut max = [f32::NEG_INFINITY; 3];
+        for p in self.positions.iter() {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(p[axis]);
+                max[axis] = max[axis].max(p[axis]);
+            }
+        }
+        if self.positions.is_empty() {
+            min = [0.0; 3];
+            max = [0.0; 3];
+        }
+
+        write!(out, r#"{{
+  "asset": {{ "version": "2.0", "generator": "tar_engine_rs" }},
+  "scene": 0,
+  "scenes": [ {{ "nodes": [0] }} ],
+  "nodes": [ {{ "mesh": 0, "name": "heightmap" }} ],
+  "meshes": [ {{ "primitives": [ {{ "attributes": {{ "POSITION": 0, "NORMAL": 1 }}, "indices": 2 }} ] }} ],
+  "accessors": [
+    {{ "bufferView": 0, "componentType": 5126, "count": {count}, "type": "VEC3", "min": [{min0}, {min1}, {min2}], "max": [{max0}, {max1}, {max2}] }},
+    {{ "bufferView": 1, "componentType": 5126, "count": {count}, "type": "VEC3" }},
+    {{ "bufferView": 2, "componentType": 5125, "count": {indices}, "type": "SCALAR" }}
+  ],
+  "bufferViews": [
+    {{ "buffer": 0, "byteOffset": 0, "byteLength": {vertex_bytes}, "target": 34962 }},
+    {{ "buffer": 0, "byteOffset": {vertex_bytes}, "byteLength": {vertex_bytes}, "target": 34962 }},
+    {{ "buffer": 0, "byteOffset": {normals_end}, "byteLength": {index_bytes}, "target": 34963 }}
+  ],
+  "buffers": [ {{ "byteLength": {buffer_len}, "uri": "data:application/octet-stream;base64,{data}" }} ]
+}}
+"#,
+            count = self.positions.len(),
+            indices = self.indices.len(),
+            min0 = min[0], min1 = min[1], min2 = min[2],
+            max0 = max[0], max1 = max[1], max2 = max[2],
+            vertex_bytes = vertex_bytes,
+            normals_end = vertex_bytes * 2,
+            index_bytes = index_bytes,
+            buffer_len = buffer.len(),
+            data = base64(&buffer)
+        )
+    }
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn normalize(a: [f32; 3]) -> [f32; 3] {
+    let length = dot(a, a).sqrt();
+    [a[0] / length, a[1] / length, a[2] / length]
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        encoded.push(ALPHABET[(n >> 18) as usize & 63] as char);
+        encoded.push(ALPHABET[(n >> 12) as usize & 63] as char);
+        encoded.push(if chunk.len() > 1 { ALPHABET[(n >> 6) as usize & 63] as char } else { '=' });
+        encoded.push(if chunk.len() > 2 { ALPHABET[n as usize & 63] as char } else { '=' });
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Coord;
+    use super::super::Layout;
+    use super::super::Map;
+    use super::super::MapShape;
+    use super::super::Orientation;
+    use super::super::Point;
+    use super::Mesh;
+
+    fn flat(_c: Coord) -> i32 {
+        1
+    }
+
+    fn layout() -> Layout {
+        Layout::new(Orientation::POINTY, Point(1.0, 1.0), Point(0.0, 0.0))
+    }
+
+    #[test]
+    fn single_hex_is_a_prism() {
+        let map = Map::<i32>::new(MapShape::Hexagon { radius: 0, generator: flat });
+        let mesh = Mesh::from_heightmap(&map, &layout(), |_, &h| h as f32);
+
+        // 4 triangles on top and bottom, 2 for each of the 6 walls
+        assert_eq!(mesh.triangle_count(), 2 * 4 + 6 * 2);
+    }
+
+    #[test]
+    fn walls_only_where_heights_differ() {
+        let map = Map::<i32>::new(MapShape::Hexagon { radius: 1, generator: flat });
+        let mesh = Mesh::from_heightmap(&map, &layout(), |_, &h| h as f32);
+        // only the outer border has walls: 3 edges for each of the 6 outer hexes
+        assert_eq!(mesh.triangle_count(), 7 * 8 + 18 * 2);
+
+        fn bump(c: Coord) -> i32 {
+            if c == Coord::ZERO { 2 } else { 1 }
+        }
+        let map = Map::<i32>::new(MapShape::Hexagon { radius: 1, generator: bump });
+        let mesh = Mesh::from_heightmap(&map, &layout(), |_, &h| h as f32);
+        // plus the 6 walls of the center hex standing above its neighbours
+        assert_eq!(mesh.triangle_count(), 7 * 8 + 18 * 2 + 6 * 2);
+        assert!(is_closed(&mesh));
+    }
+
+    #[test]
+    fn negative_heights_go_down() {
+        fn pit(c: Coord) -> i32 {
+            if c == Coord::ZERO { -2 } else { 1 }
+        }
+        let map = Map::<i32>::new(MapShape::Hexagon { radius: 1, generator: pit });
+        let mesh = Mesh::from_heightmap(&map, &layout(), |_, &h| h as f32);
+        // the walls around the pit above 0, and the ones of the pit below
+        assert_eq!(mesh.triangle_count(), 7 * 8 + 18 * 2 + 6 * 2 + 6 * 2);
+        assert!(mesh.positions.iter().all(|p| p[1] >= -2.0));
+        assert!(mesh.positions.iter().any(|p| p[1] == -2.0));
+        assert!(is_closed(&mesh));
+
+        fn sea(_c: Coord) -> i32 {
+            -3
+        }
+        let map = Map::<i32>::new(MapShape::Hexagon { radius: 1, generator: sea });
+        let mesh = Mesh::from_heightmap(&map, &layout(), |_, &h| h as f32);
+        // down from 0 to the bottom of the sea
+        assert_eq!(mesh.triangle_count(), 7 * 8 + 18 * 2);
+        assert!(is_closed(&mesh));
+    }
+
+    // No edge of a closed mesh is on a single triangle.
+    fn is_closed(mesh: &Mesh) -> bool {
+        let key = |p: [f32; 3]| [(p[0] * 1000.0).round() as i64, (p[1] * 1000.0).round() as i64, (p[2] * 1000.0).round() as i64];
+        let mut edges = std::collections::HashMap::new();
+        for t in mesh.indices.chunks(3) {
+            for i in 0..3 {
+                let (a, b) = (key(mesh.positions[t[i] as usize]), key(mesh.positions[t[(i + 1) % 3] as usize]));
+                *edges.entry(if a < b { (a, b) } else { (b, a) }).or_insert(0) += 1;
+            }
+        }
+        edges.values().all(|&count| count >= 2)
+    }
+
+    #[test]
+    fn normals_point_outward() {
+        let map = Map::<i32>::new(MapShape::Hexagon { radius: 0, generator: flat });
+        let mesh = Mesh::from_heightmap(&map, &layout(), |_, &h| h as f32);
+
+        for (p, n) in mesh.positions.iter().zip(mesh.normals.iter()) {
+            if n[1] > 0.5 {
+                assert_eq!(p[1], 1.0);
+            }
+            else if n[1] < -0.5 {
+                assert_eq!(p[1], 0.0);
+            }
+            else {
+                // walls face away from the center of the hex
+                assert!(p[0] * n[0] + p[2] * n[2] > 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn writes_obj_and_gltf() -> std::io::Result<()> {
+        let map = Map::<i32>::new(MapShape::Hexagon { radius: 1, generator: flat });
+        let mesh = Mesh::from_heightmap(&map, &layout(), |_, &h| h as f32);
+
+        let mut obj = Vec::<u8>::new();
+        mesh.write_obj(&mut obj)?;
+        let obj = String::from_utf8(obj).unwrap();
+        assert_eq!(obj.lines().filter(|l| l.starts_with("v ")).count(), mesh.positions.len());
+        assert_eq!(obj.lines().filter(|l| l.starts_with("f ")).count(), mesh.triangle_count());
+
+        let mut gltf = Vec::<u8>::new();
+        mesh.write_gltf(&mut gltf)?;
+        let gltf = String::from_utf8(gltf).unwrap();
+        assert!(gltf.contains(r#""version": "2.0""#));
+        assert!(gltf.contains(&format!(r#""byteLength": {}"#, mesh.positions.len() * 24 + mesh.indices.len() * 4)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn base64_padding() {
+        assert_eq!(super::base64(b""), "");
+        assert_eq!(super::base64(b"f"), "Zg==");
+        assert_eq!(super::base64(b"fo"), "Zm8=");
+        assert_eq!(super::base64(b"foo"), "Zm9v");
+        assert_eq!(super::base64(b"foobar"), "Zm9vYmFy");
+    }
+}
@@ -1,5 +1,7 @@
 pub mod coord;
 pub mod map;
+pub mod mesh;
+pub mod movement;
 
 mod layout {
     use std::ops;
@@ -7,6 +9,7 @@ mod layout {
     use super::Coord;
     use super::FCoord;
 
+    #[derive(Debug, Clone, Copy)]
     pub struct Orientation {
         f0: f32,
         f1: f32,
@@ -45,8 +48,53 @@ mod layout {
             b3: SQRT_3 / 3.0,
             start_angle: 0.0
         };
+
+        /// Build an orientation from its forward matrix (hex to pixel), the
+        /// backward matrix (pixel to hex) is its inverse. `start_angle` is the
+        /// angle of the first corner in sixth of a turn.
+        pub fn new(f0: f32, f1: f32, f2: f32, f3: f32, start_angle: f32) -> Orientation {
+            let det = f0 * f3 - f1 * f2;
+            Orientation {
+                f0,
+                f1,
+                f2,
+                f3,
+                b0: f3 / det,
+                b1: -f1 / det,
+                b2: -f2 / det,
+                b3: f0 / det,
+                start_angle
+            }
+        }
+
+        /// The same orientation rotated by `angle` radians, counter clockwise when
+        /// the y axis of the layout goes up.
+        pub fn rotated(&self, angle: f32) -> Orientation {
+            let (sin, cos) = angle.sin_cos();
+            Orientation::new(
+                cos * self.f0 - sin * self.f2,
+                cos * self.f1 - sin * self.f3,
+                sin * self.f0 + cos * self.f2,
+                sin * self.f1 + cos * self.f3,
+                self.start_angle + angle * 3.0 / std::f32::consts::PI
+            )
+        }
+
+        /// Width and height of the bounding box of a hex of size 1.
+        pub fn unit_bounds(&self) -> Point {
+            let mut min = Point(f32::INFINITY, f32::INFINITY);
+            let mut max = Point(f32::NEG_INFINITY, f32::NEG_INFINITY);
+            for corner in 0..6 {
+                let angle = 2.0 * std::f32::consts::PI * (self.start_angle + corner as f32) / 6.0;
+                let (sin, cos) = angle.sin_cos();
+                min = Point(min.0.min(cos), min.1.min(sin));
+                max = Point(max.0.max(cos), max.1.max(sin));
+            }
+            Point(max.0 - min.0, max.1 - min.1)
+        }
     }
 
+    #[derive(Debug, PartialEq)]
     pub struct Point (pub f32, pub f32);
 
     impl Point {
@@ -71,10 +119,11 @@ mod layout {
     impl Copy for Point {}
     impl Clone for Point {
         fn clone(&self) -> Self {
-            Self(self.0, self.1)
+            *self
         }
     }
 
+    #[derive(Debug)]
     pub struct Layout {
         orientation: Orientation,
         size: Point,
@@ -86,6 +135,23 @@ mod layout {
         pub fn new(orientation: Orientation, size: Point, origin: Point) -> Layout {
             Layout { orientation, size, origin }
         }
+
+        pub fn builder() -> LayoutBuilder {
+            LayoutBuilder::default()
+        }
+
+        pub fn orientation(&self) -> &Orientation {
+            &self.orientation
+        }
+
+        pub fn size(&self) -> Point {
+            self.size
+        }
+
+        pub fn origin(&self) -> Point {
+            self.origin
+        }
+
         pub fn hex_to_pixel(&self, h: &Coord) -> Point {
             let m = &self.orientation;
             Point(
@@ -111,23 +177,231 @@ mod layout {
             Point(size.0 * angle.cos(), size.1 * angle.sin())
         }
 
+        /// The two corners, as indices of `polygon_corners`, of the edge shared
+        /// with the neighbour in `direction`.
+        pub fn edge_corners(&self, direction: usize) -> (usize, usize) {
+            let m = &self.orientation;
+            let d = Coord::direction(direction);
+            let x = m.f0 * d.x() as f32 + m.f1 * d.y() as f32;
+            let y = m.f2 * d.x() as f32 + m.f3 * d.y() as f32;
+            // the middle of the edge is half way between the corners
+            let sixths = y.atan2(x) * 3.0 / std::f32::consts::PI;
+            let first = (sixths - m.start_angle - 0.5).round().rem_euclid(6.0) as usize % 6;
+            (first, (first + 1) % 6)
+        }
+
         pub fn polygon_corners(&self, c: &Coord) -> [Point; 6] {
             let mut corners:[Point; 6] = [Point(0.0, 0.0); 6];
             let center = self.hex_to_pixel(c);
-            for i in 0 as usize..6 {
-                let offset = self.hex_corner_offset(i);
-                corners[i] = center + offset
+            for (i, corner) in corners.iter_mut().enumerate() {
+                *corner = center + self.hex_corner_offset(i);
             }
 
             corners
         }
     }
+
+    /// Build a `Layout` from what the art looks like: orientation, rotation,
+    /// isometric squash and the pixel size of a tile.
+    ///
+    /// The size comes from `with_tile_size` when given, from `with_size`
+    /// otherwise, then the squash is applied on the y axis. A negative squash
+    /// flips the y axis.
+    pub struct LayoutBuilder {
+        orientation: Orientation,
+        rotation: f32,
+        squash: f32,
+        size: Point,
+        tile_size: Option<Point>,
+        origin: Point
+    }
+
+    impl Default for LayoutBuilder {
+        fn default() -> Self {
+            LayoutBuilder {
+                orientation: Orientation::POINTY,
+                rotation: 0.0,
+                squash: 1.0,
+                size: Point(1.0, 1.0),
+                tile_size: None,
+                origin: Point(0.0, 0.0)
+            }
+        }
+    }
+
+    #[allow(dead_code)]
+    impl LayoutBuilder {
+        pub fn with_orientation(mut self, orientation: Orientation) -> Self {
+            self.orientation = orientation;
+            self
+        }
+
+        /// Rotation in radians, applied before the squash.
+        pub fn with_rotation(mut self, angle: f32) -> Self {
+            self.rotation = angle;
+            self
+        }
+
+        /// Vertical scale applied after the rotation, 0.5 gives the classic 2:1 isometric view.
+        pub fn with_squash(mut self, squash: f32) -> Self {
+            self.squash = squash;
+            self
+        }
+
+        /// Distance from the center to the corners of a hex, on each axis.
+        pub fn with_size(mut self, size: Point) -> Self {
+            self.size = size;
+            self
+        }
+
+        /// Width and height in pixels of the bounding box of one tile.
+        pub fn with_tile_size(mut self, width: f32, height: f32) -> Self {
+            self.tile_size = Some(Point(width, height));
+            self
+        }
+
+        pub fn with_origin(mut self, origin: Point) -> Self {
+            self.origin = origin;
+            self
+        }
+
+        pub fn build(self) -> Layout {
+            let orientation = self.orientation.rotated(self.rotation);
+            let size = match self.tile_size {
+                Some(tile) => {
+                    let bounds = orientation.unit_bounds();
+                    Point(tile.0 / bounds.0, tile.1 / bounds.1)
+                }
+                None => self.size
+            };
+
+            Layout::new(orientation, Point(size.0, size.1 * self.squash), self.origin)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::super::Coord;
+        use super::super::Map;
+        use super::super::MapShape;
+        use super::Layout;
+        use super::Orientation;
+        use super::Point;
+
+        fn layouts() -> Vec<Layout> {
+            vec![
+                Layout::new(Orientation::POINTY, Point(1.0, 1.0), Point(0.0, 0.0)),
+                Layout::new(Orientation::FLAT, Point(1.0, 1.0), Point(0.0, 0.0)),
+                Layout::new(Orientation::POINTY, Point(30.0, -30.0), Point(0.0, 0.0)),
+                Layout::new(Orientation::FLAT, Point(12.0, 7.0), Point(-40.0, 25.0)),
+                Layout::builder().with_rotation(0.3).with_size(Point(20.0, 20.0)).build(),
+                Layout::builder()
+                    .with_orientation(Orientation::FLAT)
+                    .with_rotation(-1.9)
+                    .with_origin(Point(100.0, 50.0))
+                    .build(),
+                Layout::builder()
+                    .with_rotation(std::f32::consts::FRAC_PI_4)
+                    .with_squash(0.5)
+                    .with_size(Point(32.0, 32.0))
+                    .build(),
+                Layout::builder().with_squash(-0.6).with_size(Point(10.0, 10.0)).build(),
+                Layout::builder().with_orientation(Orientation::FLAT).with_tile_size(64.0, 56.0).build(),
+                Layout::builder().with_tile_size(64.0, 56.0).with_origin(Point(32.0, 28.0)).build(),
+            ]
+        }
+
+        fn coords() -> Vec<Coord> {
+            fn zero(_c: Coord) -> i32 {
+                0
+            }
+            Map::<i32>::new(MapShape::Hexagon { radius: 20, generator: zero }).ground.into_keys().collect()
+        }
+
+        fn assert_close(a: Point, b: Point) {
+            assert!((a.0 - b.0).abs() < 1e-3 && (a.1 - b.1).abs() < 1e-3, "{:?} != {:?}", a, b);
+        }
+
+        #[test]
+        fn hex_to_pixel_round_trip() {
+            for (i, layout) in layouts().iter().enumerate() {
+                for c in coords() {
+                    let back = Coord::from(layout.pixel_to_hex(&layout.hex_to_pixel(&c)));
+                    assert_eq!(back, c, "layout {}", i);
+                }
+            }
+        }
+
+        #[test]
+        fn corners_round_to_their_hex() {
+            // points pulled slightly from each corner toward the center stay in the hex
+            for (i, layout) in layouts().iter().enumerate() {
+                for c in coords() {
+                    let center = layout.hex_to_pixel(&c);
+                    for corner in layout.polygon_corners(&c).iter() {
+                        let inside = Point(
+                            center.0 + (corner.0 - center.0) * 0.95,
+                            center.1 + (corner.1 - center.1) * 0.95
+                        );
+                        assert_eq!(Coord::from(layout.pixel_to_hex(&inside)), c, "layout {}", i);
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn edge_corners_are_shared_with_the_neighbour() {
+            for (i, layout) in layouts().iter().enumerate() {
+                let corners = layout.polygon_corners(&Coord::ZERO);
+                for direction in 0..6 {
+                    let neighbour = layout.polygon_corners(&Coord::ZERO.neighbour(direction));
+                    let (a, b) = layout.edge_corners(direction);
+                    let (c, d) = layout.edge_corners(direction + 3);
+                    assert_close(Point(corners[a].0, corners[a].1), Point(neighbour[d].0, neighbour[d].1));
+                    assert_close(Point(corners[b].0, corners[b].1), Point(neighbour[c].0, neighbour[c].1));
+                    assert_eq!((a + 1) % 6, b, "layout {}", i);
+                }
+            }
+        }
+
+        #[test]
+        fn rotated_pointy_is_flat() {
+            let pointy = Layout::builder().with_rotation(std::f32::consts::FRAC_PI_6).build();
+            let flat = Layout::builder().with_orientation(Orientation::FLAT).build();
+            for c in coords() {
+                assert_close(pointy.hex_to_pixel(&c), flat.hex_to_pixel(&c));
+            }
+        }
+
+        #[test]
+        fn tile_size_fits_the_corners() {
+            for orientation in [Orientation::POINTY, Orientation::FLAT] {
+                for rotation in [0.0, 0.2, 1.0] {
+                    let layout = Layout::builder()
+                        .with_orientation(orientation)
+                        .with_rotation(rotation)
+                        .with_tile_size(64.0, 56.0)
+                        .build();
+                    let corners = layout.polygon_corners(&Coord::ZERO);
+                    let xs = corners.iter().map(|p| p.0);
+                    let ys = corners.iter().map(|p| p.1);
+                    let width = xs.clone().fold(f32::NEG_INFINITY, f32::max) - xs.fold(f32::INFINITY, f32::min);
+                    let height = ys.clone().fold(f32::NEG_INFINITY, f32::max) - ys.fold(f32::INFINITY, f32::min);
+                    assert_close(Point(width, height), Point(64.0, 56.0));
+                }
+            }
+        }
+    }
 }
 
 pub use self::coord::Coord;
 pub use self::coord::FCoord;
 pub use self::map::Map;
 pub use self::map::MapShape;
+pub use self::mesh::Mesh;
+pub use self::movement::Movement;
+pub use self::movement::Step;
 pub use self::layout::Orientation;
 pub use self::layout::Layout;
+pub use self::layout::LayoutBuilder;
 pub use self::layout::Point;
\ No newline at end of file
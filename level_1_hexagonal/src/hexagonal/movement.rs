@@ -0,0 +1,231 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::collections::HashMap;
+
+use super::Coord;
+use super::Map;
+
+/// How a hex was reached: the movement points left once standing on it and
+/// the hex the unit came from (`None` for the starting hex).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step {
+    pub remaining: i32,
+    pub from: Option<Coord>
+}
+
+/// Every hex a unit can reach this turn, with the cheapest way to get there.
+pub struct Movement {
+    start: Coord,
+    reached: HashMap<Coord, Step>
+}
+
+#[allow(dead_code)]
+impl Movement {
+    pub fn start(&self) -> Coord {
+        self.start
+    }
+
+    pub fn len(&self) -> usize {
+        self.reached.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reached.is_empty()
+    }
+
+    pub fn contains(&self, c: &Coord) -> bool {
+        self.reached.contains_key(c)
+    }
+
+    pub fn step(&self, c: &Coord) -> Option<&Step> {
+        self.reached.get(c)
+    }
+
+    pub fn remaining(&self, c: &Coord) -> Option<i32> {
+        self.reached.get(c).map(|step| step.remaining)
+    }
+
+    /// Path from the starting hex to `c`, both included.
+    pub fn path(&self, c: &Coord) -> Option<Vec<Coord>> {
+        let mut step = self.reached.get(c)?;
+        let mut path = vec![*c];
+        while let Some(from) = step.from {
+            path.push(from);
+            step = &self.reached[&from];
+        }
+        path.reverse();
+
+        Some(path)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Coord, &Step)> {
+        self.reached.iter()
+    }
+}
+
+// Entry of the frontier, ordered so that the heap pops the hex with the most
+// movement points left first.
+#[derive(PartialEq, Eq)]
+struct Frontier {
+    remaining: i32,
+    coord: Coord
+}
+
+impl Ord for Frontier {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.remaining.cmp(&other.remaining)
+    }
+}
+
+impl PartialOrd for Frontier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Map<T> {
+    /// Find every hex reachable from `start` with `points` movement points.
+    ///
+    /// `cost` gives the movement points needed to enter a hex, or `None` when the
+    /// hex can not be entered at all (water, enemy unit, ...). Hexes outside the
+    /// map are never reachable. Entering a hex for which `zone_of_control` returns
+    /// `true` ends the movement: the unit keeps no points and can not go further.
+    /// The starting hex is exempt, so a unit can always leave an enemy zone.
+    pub fn movement<C, Z>(&self, start: Coord, points: i32, cost: C, zone_of_control: Z) -> Movement
+    where
+        C: Fn(Coord, &T) -> Option<i32>,
+        Z: Fn(Coord) -> bool
+    {
+        let mut reached = HashMap::<Coord, Step>::new();
+        let mut frontier = BinaryHeap::<Frontier>::new();
+
+        reached.insert(start, Step { remaining: points, from: None });
+        frontier.push(Frontier { remaining: points, coord: start });
+
+        while let Some(Frontier { remaining, coord }) = frontier.pop() {
+            // an entry left behind after a better way to this hex was found
+            if reached[&coord].remaining > remaining {
+                continue;
+            }
+            if coord != start && zone_of_control(coord) {
+                continue;
+            }
+
+            for direction in 0..6 {
+                let next = coord.neighbour(direction);
+                let value = match self.ground.get(&next) {
+                    Some(value) => value,
+                    None => continue
+                };
+                let left = match cost(next, value) {
+                    Some(cost) if cost >= 0 && cost <= remaining => remaining - cost,
+                    _ => continue
+                };
+                let left = if zone_of_control(next) { 0 } else { left };
+
+                let better = match reached.get(&next) {
+                    Some(step) => step.remaining < left,
+                    None => true
+                };
+                if better {
+                    reached.insert(next, Step { remaining: left, from: Some(coord) });
+                    frontier.push(Frontier { remaining: left, coord: next });
+                }
+            }
+        }
+
+        Movement { start, reached }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Coord;
+    use super::super::Map;
+    use super::super::MapShape;
+
+    fn plain(_c: Coord) -> i32 {
+        1
+    }
+
+    #[test]
+    fn reach_on_plain_terrain() -> Result<(), String> {
+        let map = Map::<i32>::new(MapShape::Hexagon { radius: 5, generator: plain });
+        let movement = map.movement(Coord::ZERO, 2, |_, &cost| Some(cost), |_| false);
+
+        // a hexagon of radius 2 around the start
+        assert_eq!(movement.len(), 19);
+        assert_eq!(movement.remaining(&Coord::ZERO), Some(2));
+        assert_eq!(movement.remaining(&Coord::new(1, 0, -1)?), Some(1));
+        assert_eq!(movement.remaining(&Coord::new(2, -1, -1)?), Some(0));
+        assert!(!movement.contains(&Coord::new(3, 0, -3)?));
+
+        Ok(())
+    }
+
+    #[test]
+    fn path_follows_cheapest_route() -> Result<(), String> {
+        // a ridge of expensive hexes on x == 1, except for a pass at y == -1
+        fn ridge(c: Coord) -> i32 {
+            if c.x() == 1 && c.y() != -1 { 5 } else { 1 }
+        }
+        let map = Map::<i32>::new(MapShape::Hexagon { radius: 5, generator: ridge });
+        let movement = map.movement(Coord::ZERO, 3, |_, &cost| Some(cost), |_| false);
+
+        let target = Coord::new(2, 0, -2)?;
+        assert_eq!(movement.remaining(&target), Some(0));
+        assert_eq!(movement.path(&target), Some(vec![
+            Coord::ZERO,
+            Coord::new(1, -1, 0)?,
+            Coord::new(2, -1, -1)?,
+            target
+        ]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn impassable_and_outside_hexes_are_not_reached() -> Result<(), String> {
+        let map = Map::<i32>::new(MapShape::Hexagon { radius: 1, generator: plain });
+        let blocked = Coord::new(1, 0, -1)?;
+        let movement = map.movement(Coord::ZERO, 10, |c, &cost| {
+            if c == blocked { None } else { Some(cost) }
+        }, |_| false);
+
+        assert_eq!(movement.len(), 6);
+        assert!(!movement.contains(&blocked));
+        assert!(!movement.contains(&Coord::new(2, 0, -2)?));
+
+        Ok(())
+    }
+
+    #[test]
+    fn zone_of_control_stops_movement() -> Result<(), String> {
+        let map = Map::<i32>::new(MapShape::Hexagon { radius: 5, generator: plain });
+        let enemy = Coord::new(2, 0, -2)?;
+        let zone = |c: Coord| c.distance(enemy) == 1;
+
+        let movement = map.movement(Coord::ZERO, 3, |c, &cost| {
+            if c == enemy { None } else { Some(cost) }
+        }, zone);
+
+        // entering the zone costs every remaining point
+        let in_zone = Coord::new(1, 0, -1)?;
+        assert_eq!(movement.remaining(&in_zone), Some(0));
+        assert_eq!(movement.path(&in_zone), Some(vec![Coord::ZERO, in_zone]));
+
+        // the zone can not be crossed, going around it costs one point too many
+        let behind = Coord::new(3, -1, -2)?;
+        assert!(!movement.contains(&behind));
+        let free = map.movement(Coord::ZERO, 3, |c, &cost| {
+            if c == enemy { None } else { Some(cost) }
+        }, |_| false);
+        assert_eq!(free.remaining(&behind), Some(0));
+
+        // a unit starting inside a zone can still walk out of it
+        let movement = map.movement(in_zone, 1, |_, &cost| Some(cost), zone);
+        assert_eq!(movement.remaining(&Coord::ZERO), Some(0));
+
+        Ok(())
+    }
+}
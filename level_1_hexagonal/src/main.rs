@@ -1,52 +1,13 @@
-mod hexagonal;
+use std::fs::File;
+use std::io::BufWriter;
 
-use hexagonal::Coord;
-use hexagonal::Orientation;
-use hexagonal::Point;
-use hexagonal::Layout;
-use hexagonal::Map;
-use hexagonal::MapShape;
-
-pub struct App {
-    map: Map::<i32>
-}
-
-impl App {
-    fn render(&mut self, args: &RenderArgs) {
-        use graphics::*;
-
-        const GREEN: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
-        const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
-
-        let square = rectangle::square(0.0, 0.0, 50.0);
-        let rotation = self.rotation;
-        let (x, y) = (args.window_size[0] / 2.0, args.window_size[1] / 2.0);
-
-        self.gl.draw(args.viewport(), |c, gl| {
-            // Clear the screen.
-            clear(GREEN, gl);
-
-            let transform = c
-                .transform
-                .trans(x, y)
-                .rot_rad(rotation)
-                .trans(-25.0, -25.0);
-
-            // Draw a box rotating around the middle of the screen.
-            rectangle(RED, square, transform, gl);
-
-            for (hexa, val) in self.map.ground.iter() {
-                layout.polygon_corners(hexa).iter().map(|p| Point2::new(p.0, p.1)).collect(), Vector2::new(1.0, 1.0)
-                polygon(RED, polygon, transform, gl);
-            }
-        });
-    }
-
-    fn update(&mut self, args: &UpdateArgs) {
-        // Rotate 2 radians per second.
-        self.rotation += 2.0 * args.dt;
-    }
-}
+use tar_engine_rs::hexagonal::Coord;
+use tar_engine_rs::hexagonal::Orientation;
+use tar_engine_rs::hexagonal::Point;
+use tar_engine_rs::hexagonal::Layout;
+use tar_engine_rs::hexagonal::Map;
+use tar_engine_rs::hexagonal::MapShape;
+use tar_engine_rs::hexagonal::Mesh;
 
 fn main() -> Result<(), String> {
     let layout = Layout::new(
@@ -81,6 +42,13 @@ fn main() -> Result<(), String> {
     for (hexa, val) in map.ground.iter() {
     }
 
+    // extrude the red channel, the files can be opened in Blender
+    let mesh = Mesh::from_heightmap(&map, &layout, |_, &val| (val >> 16 & 0xff) as f32 / 255.0 * 60.0);
+    let mut obj = BufWriter::new(File::create("hexagon.obj").map_err(|e| e.to_string())?);
+    mesh.write_obj(&mut obj).map_err(|e| e.to_string())?;
+    let mut gltf = BufWriter::new(File::create("hexagon.gltf").map_err(|e| e.to_string())?);
+    mesh.write_gltf(&mut gltf).map_err(|e| e.to_string())?;
+    println!("Mesh exported: {} triangles", mesh.triangle_count());
 
     Ok(())
 }
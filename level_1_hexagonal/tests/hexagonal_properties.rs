@@ -0,0 +1,93 @@
+use proptest::prelude::*;
+
+use tar_engine_rs::hexagonal::Coord;
+use tar_engine_rs::hexagonal::FCoord;
+use tar_engine_rs::hexagonal::Layout;
+use tar_engine_rs::hexagonal::Map;
+use tar_engine_rs::hexagonal::MapShape;
+use tar_engine_rs::hexagonal::Orientation;
+use tar_engine_rs::hexagonal::Point;
+
+fn coord(range: i32) -> impl Strategy<Value = Coord> {
+    (-range..=range, -range..=range).prop_map(|(q, r)| Coord::new(q, r, -q - r).unwrap())
+}
+
+fn layout() -> impl Strategy<Value = Layout> {
+    (
+        prop_oneof![Just(Orientation::POINTY), Just(Orientation::FLAT)],
+        -std::f32::consts::PI..std::f32::consts::PI,
+        1.0f32..100.0,
+        prop_oneof![0.2f32..2.0, -2.0f32..-0.2],
+        (-500.0f32..500.0, -500.0f32..500.0)
+    ).prop_map(|(orientation, rotation, size, squash, origin)| {
+        Layout::builder()
+            .with_orientation(orientation)
+            .with_rotation(rotation)
+            .with_size(Point(size, size))
+            .with_squash(squash)
+            .with_origin(Point(origin.0, origin.1))
+            .build()
+    })
+}
+
+fn distance_sq(a: Point, b: Point) -> f32 {
+    (a.0 - b.0) * (a.0 - b.0) + (a.1 - b.1) * (a.1 - b.1)
+}
+
+fn one(_c: Coord) -> u8 {
+    1
+}
+
+proptest! {
+    #[test]
+    fn distance_triangle_inequality(a in coord(1000), b in coord(1000), c in coord(1000)) {
+        prop_assert!(a.distance(c) <= a.distance(b) + b.distance(c));
+        prop_assert_eq!(a.distance(b), b.distance(a));
+        prop_assert_eq!(a.distance(a), 0);
+    }
+
+    #[test]
+    fn neighbour_and_inverse_direction(c in coord(1000), direction in 0usize..12) {
+        let next = c.neighbour(direction);
+        prop_assert_eq!(c.distance(next), 1);
+        prop_assert_eq!(next.neighbour(direction + 3), c);
+        prop_assert_eq!(Coord::direction(direction) + Coord::direction(direction + 3), Coord::ZERO);
+    }
+
+    #[test]
+    fn pixel_to_hex_inverts_hex_to_pixel(layout in layout(), c in coord(1000)) {
+        let back = Coord::from(layout.pixel_to_hex(&layout.hex_to_pixel(&c)));
+        prop_assert_eq!(back, c);
+    }
+
+    #[test]
+    fn rounding_picks_the_nearest_hex(x in -100.0f32..100.0, y in -100.0f32..100.0) {
+        let layout = Layout::new(Orientation::POINTY, Point(1.0, 1.0), Point(0.0, 0.0));
+        let fractional = FCoord::new(x, y, -x - y).unwrap();
+        let rounded = Coord::from(fractional);
+
+        // in pixel space, the point is at least as close to the rounded hex as to any neighbour
+        let point = Point(
+            3f32.sqrt() * fractional.x() + 3f32.sqrt() / 2.0 * fractional.y(),
+            1.5 * fractional.y()
+        );
+        let nearest = distance_sq(point, layout.hex_to_pixel(&rounded));
+        for direction in 0..6 {
+            let other = distance_sq(point, layout.hex_to_pixel(&rounded.neighbour(direction)));
+            prop_assert!(nearest <= other + 1e-3, "{} is closer than {}", rounded.neighbour(direction), rounded);
+        }
+    }
+
+    #[test]
+    fn hexagon_tile_count(radius in 0i32..30) {
+        let map = Map::<u8>::new(MapShape::Hexagon { radius, generator: one });
+        prop_assert_eq!(map.ground.len() as i32, 3 * radius * (radius + 1) + 1);
+        prop_assert!(map.ground.keys().all(|c| c.length() <= radius));
+    }
+
+    #[test]
+    fn parallelogram_tile_count(q in 0usize..20, r in 0usize..20, direction in 0usize..3) {
+        let map = Map::<u8>::new(MapShape::Parallelogram { q, r, direction, f: one });
+        prop_assert_eq!(map.ground.len(), 4 * q * r);
+    }
+}
@@ -0,0 +1,185 @@
+use std::path::{Path, PathBuf};
+
+use amethyst::{
+    ecs::Entity,
+    input::{ControllerAxis, ControllerButton, InputEvent, StringBindings, VirtualKeyCode},
+    prelude::*,
+    ui::{Anchor, LineMode, UiText, UiTransform},
+};
+use log::warn;
+use pong_sim::{Action, BindingMenu, Bindings, Control, Side};
+
+use crate::pong::load_font;
+
+/// Axes a binding can name.
+pub const CONTROLLER_AXES: [ControllerAxis; 6] = [
+    ControllerAxis::LeftX,
+    ControllerAxis::LeftY,
+    ControllerAxis::RightX,
+    ControllerAxis::RightY,
+    ControllerAxis::LeftTrigger,
+    ControllerAxis::RightTrigger
+];
+
+/// How far a stick must be pushed to be bound.
+const CAPTURE_THRESHOLD: f32 = 0.5;
+
+/// Set while a menu is shown over the game, the simulation waits.
+#[derive(Default)]
+pub struct Paused(pub bool);
+
+/// The keyboard layout of the game, and the left stick of the first two
+/// controllers. SDL sticks go down towards positive values.
+pub fn default_bindings() -> Bindings {
+    let mut bindings = Bindings::default();
+    for &(gamepad, side) in [(0, Side::Left), (1, Side::Right)].iter() {
+        let stick = |positive| Control::Axis { gamepad, axis: "LeftY".to_string(), positive };
+        bindings.bind(side, Action::Up, stick(false));
+        bindings.bind(side, Action::Down, stick(true));
+    }
+    bindings
+}
+
+/// The saved bindings, or the default ones when there are none yet.
+pub fn load_bindings(path: &Path) -> Bindings {
+    if !path.exists() {
+        return default_bindings();
+    }
+    Bindings::load(path).unwrap_or_else(|error| {
+        warn!("{}, using the default bindings", error);
+        default_bindings()
+    })
+}
+
+/// The control an input event presses, to be bound.
+fn pressed_control(event: &InputEvent<StringBindings>) -> Option<Control> {
+    match event {
+        InputEvent::KeyPressed { key_code, .. } => Some(Control::Key(format!("{:?}", key_code))),
+        InputEvent::ControllerButtonPressed { which, button } => {
+            Some(Control::Button { gamepad: *which, button: format!("{:?}", button) })
+        }
+        InputEvent::ControllerAxisMoved { which, axis, value } if value.abs() > CAPTURE_THRESHOLD => {
+            Some(Control::Axis { gamepad: *which, axis: format!("{:?}", axis), positive: *value > 0.0 })
+        }
+        _ => None
+    }
+}
+
+fn key_pressed(event: &InputEvent<StringBindings>, key: VirtualKeyCode) -> bool {
+    matches!(event, InputEvent::KeyPressed { key_code, .. } if *key_code == key)
+}
+
+fn button_pressed(event: &InputEvent<StringBindings>, button: ControllerButton) -> bool {
+    matches!(event, InputEvent::ControllerButtonPressed { button: pressed, .. } if *pressed == button)
+}
+
+/// Opens the rebinding menu, F1 or the controller start button.
+pub fn opens_menu(event: &InputEvent<StringBindings>) -> bool {
+    key_pressed(event, VirtualKeyCode::F1) || button_pressed(event, ControllerButton::Start)
+}
+
+/// Pushed over the game to change the controls, saved to `config/bindings.ron`.
+pub struct BindingsMenu {
+    path: PathBuf,
+    menu: BindingMenu,
+    /// Title, hint and one text per menu entry.
+    texts: Vec<Entity>,
+    lines: Vec<Entity>
+}
+
+impl BindingsMenu {
+    pub fn new(path: PathBuf) -> BindingsMenu {
+        BindingsMenu { path, menu: BindingMenu::default(), texts: Vec::new(), lines: Vec::new() }
+    }
+
+    fn refresh(&self, world: &mut World) {
+        let lines = self.menu.lines(&world.read_resource::<Bindings>());
+        let mut texts = world.write_storage::<UiText>();
+        for (entity, line) in self.lines.iter().zip(lines) {
+            if let Some(text) = texts.get_mut(*entity) {
+                text.text = line;
+            }
+        }
+    }
+}
+
+impl SimpleState for BindingsMenu {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+        world.insert(Paused(true));
+        let font = load_font(world);
+
+        let mut texts = vec!["CONTROLS".to_string()];
+        texts.extend(self.menu.lines(&world.read_resource::<Bindings>()));
+        texts.push("enter to change, escape to go back".to_string());
+
+        let top = texts.len() as f32 * 20.0;
+        for (index, text) in texts.into_iter().enumerate() {
+            let transform = UiTransform::new(
+                format!("bindings_{}", index), Anchor::Middle, Anchor::Middle,
+                0.0, top - index as f32 * 40.0, 2.0, 800.0, 40.0
+            );
+            let entity = world.create_entity()
+                .with(transform)
+                .with(UiText::new(
+                    font.clone(),
+                    text,
+                    [1.0, 1.0, 1.0, 1.0],
+                    if index == 0 { 40.0 } else { 25.0 },
+                    LineMode::Single,
+                    Anchor::Middle
+                ))
+                .build();
+            self.texts.push(entity);
+        }
+        // the menu entries are between the title and the hint
+        self.lines = self.texts[1..self.texts.len() - 1].to_vec();
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+        world.insert(Paused(false));
+        self.lines.clear();
+        for entity in self.texts.drain(..) {
+            world.delete_entity(entity).expect("Can not delete the bindings menu");
+        }
+    }
+
+    fn handle_event(&mut self, data: StateData<'_, GameData<'_, '_>>, event: StateEvent) -> SimpleTrans {
+        let world = data.world;
+        let event = match &event {
+            StateEvent::Input(event) => event,
+            _ => return Trans::None
+        };
+
+        let mut changed = false;
+        if self.menu.is_waiting() {
+            if key_pressed(event, VirtualKeyCode::Escape) {
+                self.menu.cancel();
+            }
+            else if let Some(control) = pressed_control(event) {
+                changed = self.menu.capture(control, &mut world.write_resource::<Bindings>());
+            }
+        }
+        else if key_pressed(event, VirtualKeyCode::Up) || button_pressed(event, ControllerButton::DPadUp) {
+            self.menu.previous();
+        }
+        else if key_pressed(event, VirtualKeyCode::Down) || button_pressed(event, ControllerButton::DPadDown) {
+            self.menu.next();
+        }
+        else if key_pressed(event, VirtualKeyCode::Return) || button_pressed(event, ControllerButton::A) {
+            changed = self.menu.activate(&mut world.write_resource::<Bindings>(), &default_bindings());
+        }
+        else if key_pressed(event, VirtualKeyCode::Escape) || opens_menu(event) || button_pressed(event, ControllerButton::B) {
+            return Trans::Pop;
+        }
+
+        if changed {
+            if let Err(error) = world.read_resource::<Bindings>().save(&self.path) {
+                warn!("{}", error);
+            }
+        }
+        self.refresh(world);
+        Trans::None
+    }
+}
@@ -1,12 +1,16 @@
+mod controls;
 mod pong;
+mod stats;
 mod systems;
 
 use crate::pong::Pong;
+use pong_sim::ConfigFile;
 
 #[allow(unused)]
 use log::{debug, info, warn, error};
 
 use amethyst::{
+    audio::AudioBundle,
     core::TransformBundle, 
     input::{InputBundle, StringBindings}, 
     prelude::*, 
@@ -26,13 +30,17 @@ fn main() -> amethyst::Result<()> {
     let config_path = app_root.join("config");
     let display_config_path = config_path.join("display.ron");
     let binding_config_path = config_path.join("bindings.ron");
+    let audio_config_path = config_path.join("audio.ron");
     let assets_path = app_root.join("assets");
+    let tuning_path = assets_path.join("components").join("paddle.ron");
     info!("Game starting..."); 
     info!("  - App root:       {:?}", app_root);
     info!("  - Config path:    {:?}", config_path);
     info!("  - Display config: {:?}", display_config_path);
     info!("  - Binding config: {:?}", binding_config_path);
+    info!("  - Audio config:   {:?}", audio_config_path);
     info!("  - Asset path:     {:?}", assets_path);
+    info!("  - Tuning:         {:?}", tuning_path);
 
     let render_bundle = RenderingBundle::<DefaultBackend>::new()
         // The RenderToWindow plugin provides all the scaffolding for opening a window and drawing on it
@@ -45,8 +53,8 @@ fn main() -> amethyst::Result<()> {
         .with_plugin(RenderUi::default());
 
     let transform_bundle = TransformBundle::new();
-    let input_bundle = InputBundle::<StringBindings>::new()
-        .with_bindings_from_file(binding_config_path)?;
+    // the paddles read the keys and controllers of the `Bindings` resource
+    let input_bundle = InputBundle::<StringBindings>::new();
 
     let ui_bundle = UiBundle::<StringBindings>::new();
 
@@ -56,12 +64,20 @@ fn main() -> amethyst::Result<()> {
         .with_bundle(transform_bundle)?
         .with_bundle(input_bundle)?
         .with_bundle(ui_bundle)?
+        .with_bundle(AudioBundle::default())?
+        .with(systems::ConfigSystem::default(), "config_system", &[])
         .with(systems::PaddleSystem, "paddle_system", &["input_system"])
-        .with(systems::MoveBallsSystem, "ball_system", &[])
-        .with(systems::BounceSystem, "bounce_system", &["paddle_system", "ball_system"])
-        .with(systems::WinnerSystem, "winner_system", &["ball_system"]);
+        .with(systems::SimulationSystem::default(), "simulation_system", &["paddle_system", "config_system"])
+        .with(systems::WinnerSystem, "winner_system", &["simulation_system"])
+        .with(systems::PowerUpSystem, "power_up_system", &["simulation_system"])
+        .with(systems::AudioSystem::new(audio_config_path), "audio_system", &["simulation_system"])
+        .with(systems::StatsSystem::default(), "stats_system", &["simulation_system"])
+        .with(systems::EffectsSystem::default(), "effects_system", &["simulation_system"]);
 
-    let mut game = Application::new(assets_path, Pong::default(), game_data)?;
+    // the match history outlives the game, in the user data dir
+    let mut game = Application::build(assets_path, Pong::new(ConfigFile::new(tuning_path), binding_config_path))?
+        .with_resource(stats::load_history())
+        .build(game_data)?;
 
     // Running the game loop
     game.run();
@@ -1,179 +1,267 @@
-use amethyst::{core::Time, ui::{Anchor, FontAsset, LineMode, TtfFormat, UiText, UiTransform}};
+use std::path::PathBuf;
+
+use amethyst::core::math::Vector3;
+use amethyst::input::{is_key_down, VirtualKeyCode};
+use amethyst::ui::{Anchor, FontAsset, LineMode, TtfFormat, UiText, UiTransform};
 #[allow(unused)]
 use amethyst::{
     assets::{AssetStorage, Loader, Handle},
     core::transform::Transform,
-    ecs::{Component, DenseVecStorage, Entity},
+    ecs::{Component, DenseVecStorage, Entity, NullStorage},
     prelude::*,
     renderer::{Camera, ImageFormat, SpriteRender, SpriteSheet, SpriteSheetFormat, Texture},
 };
+use log::warn;
+use crate::controls::{load_bindings, opens_menu, BindingsMenu, Paused};
+use crate::stats::{opens_stats, StatsScreen};
+use pong_sim::{ConfigFile, Inputs, PongConfig, PongSim, Side, Vec2};
 
-pub const ARENA_HEIGHT: f32 = 100.0;
-pub const ARENA_WIDTH: f32 = 100.0;
-
-pub const PADDLE_HEIGHT: f32 = 16.0;
-pub const PADDLE_WIDTH: f32 = 4.0;
-pub const PADDLE_VELOCITY: f32 = 50.0;
-
-pub const BALL_VELOCITY_X: f32 = 75.0;
-pub const BALL_VELOCITY_Y: f32 = 50.0;
-pub const BALL_RADIUS: f32 = 2.0;
-
-#[derive(PartialEq, Eq)]
-pub enum Side {
-    Left,
-    Right
-}
+/// Size of the sprites in `pong_spritesheet.ron`, scaled to the tuning.
+pub const PADDLE_SPRITE_WIDTH: f32 = 4.0;
+pub const PADDLE_SPRITE_HEIGHT: f32 = 16.0;
+pub const BALL_SPRITE_SIZE: f32 = 4.0;
 
+/// The paddle of one side, its position comes from the `PongSim` resource.
 pub struct Paddle {
-    pub side: Side,
-    pub width: f32,
-    pub height: f32,
-    pub velocity: f32
-}
-
-impl Paddle {
-    fn new(side: Side, velocity: f32) -> Paddle {
-        Paddle { side: side, width: PADDLE_WIDTH, height: PADDLE_HEIGHT, velocity }
-    }
+    pub side: Side
 }
 
 impl Component for Paddle {
     type Storage = DenseVecStorage<Self>;
 }
 
-pub struct Ball {
-    pub velocity: [f32; 2],
-    pub radius: f32
-}
+/// The ball, its position comes from the `PongSim` resource.
+#[derive(Default)]
+pub struct Ball;
 
-impl Ball {
-    fn new(velocity: [f32; 2], radius: f32) -> Ball {
-        Ball {velocity, radius}
-    }
+impl Component for Ball {
+    type Storage = NullStorage<Self>;
 }
 
-impl Component for Ball {
-    type Storage = DenseVecStorage<Self>;
+/// One of the balls of a multi-ball, moved like the `Ball`.
+#[derive(Default)]
+pub struct ExtraBall;
+
+impl Component for ExtraBall {
+    type Storage = NullStorage<Self>;
 }
 
+/// A power-up waiting in the arena, tinted by kind.
 #[derive(Default)]
-pub struct ScoreBoard {
-    pub score_left: i32,
-    pub score_right: i32
+pub struct PowerUpSprite;
+
+impl Component for PowerUpSprite {
+    type Storage = NullStorage<Self>;
 }
 
 pub struct ScoreText {
     pub p1_score: Entity,
-    pub p2_score: Entity
+    pub p2_score: Entity,
+    pub countdown: Entity,
+    /// The power-ups caught by each player.
+    pub p1_effects: Entity,
+    pub p2_effects: Entity
 }
 
-#[derive(Default)]
 pub struct Pong {
-    ball_spawn_timer: Option<f32>,
+    /// Tuning of the match, reloaded by the `ConfigSystem` when it changes.
+    config_file: ConfigFile,
+    /// Keyboard and controller bindings, changed in the `BindingsMenu`.
+    bindings_path: PathBuf,
     sprite_sheet_handle: Option<Handle<SpriteSheet>>
 }
 
+impl Pong {
+    pub fn new(config_file: ConfigFile, bindings_path: PathBuf) -> Pong {
+        Pong { config_file, bindings_path, sprite_sheet_handle: None }
+    }
+}
+
 impl SimpleState for Pong {
     fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
         let world = data.world;
 
-        // wait one second before spawning the ball
-        self.ball_spawn_timer.replace(1.0);
+        let mut config_file = self.config_file.clone();
+        let (config, error) = config_file.load();
+        if let Some(error) = error {
+            warn!("{}, using the default tuning", error);
+        }
+        world.insert(PongSim::new(config));
+        world.insert(config_file);
+        world.insert(Inputs::default());
+        world.insert(load_bindings(&self.bindings_path));
+        world.insert(Paused(false));
         
         // Load the spritesheet necessary to render the graphics.
         self.sprite_sheet_handle.replace(load_sprite_sheet(world));
 
         initialise_paddles(world, self.sprite_sheet_handle.clone().unwrap());
+        initialise_ball(world, self.sprite_sheet_handle.clone().unwrap());
         initialise_camera(world);
         initialise_scoreboard(world);
     }
 
-    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
-        if let Some(mut timer) = self.ball_spawn_timer.take() {
-            {
-                let time = data.world.fetch::<Time>();
-                timer -= time.delta_seconds();
+    fn handle_event(&mut self, _: StateData<'_, GameData<'_, '_>>, event: StateEvent) -> SimpleTrans {
+        match &event {
+            StateEvent::Input(event) if opens_menu(event) => {
+                Trans::Push(Box::new(BindingsMenu::new(self.bindings_path.clone())))
             }
-            if timer <= 0.0 {
-                initialise_ball(data.world, self.sprite_sheet_handle.clone().unwrap());
+            StateEvent::Input(event) if opens_stats(event) => Trans::Push(Box::new(StatsScreen::default())),
+            _ => Trans::None
+        }
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        match data.world.read_resource::<PongSim>().winner() {
+            Some(winner) => Trans::Push(Box::new(GameOver { winner, text: None })),
+            None => Trans::None
+        }
+    }
+}
+
+/// Shown over the game once a player won, space starts a new match.
+pub struct GameOver {
+    winner: Side,
+    text: Option<Entity>
+}
+
+impl SimpleState for GameOver {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+        let font = load_font(world);
+        let message = match self.winner {
+            Side::Left => "Left player wins!",
+            Side::Right => "Right player wins!"
+        };
+
+        let transform = UiTransform::new(
+            "game_over".to_string(), Anchor::Middle, Anchor::Middle,
+            0.0, 0.0, 1.0, 800.0, 50.0
+        );
+        let text = world.create_entity()
+            .with(transform)
+            .with(UiText::new(
+                font,
+                format!("{} Press space to play again", message),
+                [1.0, 1.0, 1.0, 1.0],
+                30.0,
+                LineMode::Single,
+                Anchor::Middle
+            ))
+            .build();
+        self.text.replace(text);
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        if let Some(text) = self.text.take() {
+            data.world.delete_entity(text).expect("Can not delete the game over text");
+        }
+    }
+
+    fn handle_event(&mut self, data: StateData<'_, GameData<'_, '_>>, event: StateEvent) -> SimpleTrans {
+        if let StateEvent::Input(event) = &event {
+            if opens_stats(event) {
+                return Trans::Push(Box::new(StatsScreen::default()));
             }
-            else {
-                self.ball_spawn_timer.replace(timer);
+        }
+        if let StateEvent::Window(event) = &event {
+            if is_key_down(event, VirtualKeyCode::Space) {
+                data.world.write_resource::<PongSim>().restart();
+                return Trans::Pop;
             }
         }
         Trans::None
     }
 }
 
+/// The simulation is centered on the origin, the arena has (0, 0) in the bottom left.
+pub fn to_arena(config: &PongConfig, position: Vec2) -> (f32, f32) {
+    (position.x + config.arena_width * 0.5, position.y + config.arena_height * 0.5)
+}
+
+pub fn paddle_scale(config: &PongConfig) -> Vector3<f32> {
+    Vector3::new(config.paddle_width / PADDLE_SPRITE_WIDTH, config.paddle_height / PADDLE_SPRITE_HEIGHT, 1.0)
+}
+
+pub fn ball_scale(config: &PongConfig) -> Vector3<f32> {
+    Vector3::from_element(config.ball_radius * 2.0 / BALL_SPRITE_SIZE)
+}
+
 /// Setup camera in way that our screen covers whole arena and 
 /// (0, 0) is in the bottom left.
 fn initialise_camera(world: &mut World) {
+    let (width, height) = {
+        let sim = world.read_resource::<PongSim>();
+        (sim.config.arena_width, sim.config.arena_height)
+    };
     let mut transform = Transform::default();
-    transform.set_translation_xyz(ARENA_WIDTH * 0.5, ARENA_HEIGHT * 0.5, 1.0);
+    transform.set_translation_xyz(width * 0.5, height * 0.5, 1.0);
 
     world.create_entity()
-        .with(Camera::standard_2d(ARENA_WIDTH, ARENA_HEIGHT))
+        .with(Camera::standard_2d(width, height))
         .with(transform)
         .build();
 }
 
 /// Initialises one paddle on the left, and one paddle on the right.
 fn initialise_paddles(world: &mut World, sprite_sheet_handle: Handle<SpriteSheet>) {
-    let mut left_transform = Transform::default();
-    let mut right_transform = Transform::default();
-
-    // Correctly position the paddles.
-    let y = ARENA_HEIGHT * 0.5;
-    left_transform.set_translation_xyz(PADDLE_WIDTH * 0.5, y, 0.0);
-    right_transform.set_translation_xyz(ARENA_WIDTH - PADDLE_WIDTH * 0.5, y, 0.0);
+    let config = world.read_resource::<PongSim>().config.clone();
+    let paddles: Vec<(Side, Vec2)> = world.read_resource::<PongSim>().paddles.iter()
+        .map(|paddle| (paddle.side, paddle.position))
+        .collect();
 
     // Assign the sprites for the paddles
     let sprite_render = SpriteRender::new(sprite_sheet_handle, 0); 
 
-    world.create_entity()
-        .with(Paddle::new(Side::Left, PADDLE_VELOCITY))
-        .with(left_transform)
-        .with(sprite_render.clone())
-        .build();
-
-    world.create_entity()
-        .with(Paddle::new(Side::Right, PADDLE_VELOCITY))
-        .with(right_transform)
-        .with(sprite_render)
-        .build();
+    for (side, position) in paddles {
+        // Correctly position the paddles.
+        let (x, y) = to_arena(&config, position);
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(x, y, 0.0);
+        transform.set_scale(paddle_scale(&config));
+
+        world.create_entity()
+            .with(Paddle { side })
+            .with(transform)
+            .with(sprite_render.clone())
+            .build();
+    }
 }
 
 /// Initialises one ball
 fn initialise_ball(world: &mut World, sprite_sheet_handle: Handle<SpriteSheet>) {
+    let (config, position) = {
+        let sim = world.read_resource::<PongSim>();
+        (sim.config.clone(), sim.ball.position)
+    };
+    let (x, y) = to_arena(&config, position);
     let mut local_transform = Transform::default();
-    local_transform.set_translation_xyz(ARENA_WIDTH / 2.0, ARENA_HEIGHT / 2.0, 0.0);
-
-    world.register::<Ball>();
+    local_transform.set_translation_xyz(x, y, 0.0);
+    local_transform.set_scale(ball_scale(&config));
 
     // Assign the sprite for the ball
     let sprite_render = SpriteRender::new(sprite_sheet_handle, 1); 
 
     world.create_entity()
-        .with(Ball::new(
-            [BALL_VELOCITY_X, BALL_VELOCITY_Y], BALL_RADIUS
-        ))
+        .with(Ball)
         .with(local_transform)
         .with(sprite_render)
         .build();
 }
 
+pub fn load_font(world: &mut World) -> Handle<FontAsset> {
+    let loader = world.read_resource::<Loader>();
+    let font_storage = world.read_resource::<AssetStorage<FontAsset>>();
+    loader.load(
+        "fonts/square.ttf",
+        TtfFormat,
+        (),
+        &font_storage,
+    )
+}
+
 fn initialise_scoreboard(world: &mut World) {
-    let font = {
-        let loader = world.read_resource::<Loader>();
-        let font_storage = world.read_resource::<AssetStorage<FontAsset>>();
-        loader.load(
-            "fonts/square.ttf",
-            TtfFormat,
-            (),
-            &font_storage,
-        )
-    };
+    let font = load_font(world);
 
     let p1_transform = UiTransform::new(
         "P1".to_string(), Anchor::TopMiddle, Anchor::TopMiddle, 
@@ -199,7 +287,7 @@ fn initialise_scoreboard(world: &mut World) {
     let p2_score = world.create_entity()
         .with(p2_transform)
         .with(UiText::new(
-            font,
+            font.clone(),
             "0".to_string(),
             [1., 1., 1., 1.],
             50.,
@@ -208,7 +296,45 @@ fn initialise_scoreboard(world: &mut World) {
         ))
         .build();
 
-    world.insert(ScoreText { p1_score, p2_score});
+    // serve countdown
+    let countdown_transform = UiTransform::new(
+        "countdown".to_string(), Anchor::Middle, Anchor::Middle,
+        0.0, 0.0, 1.0, 200.0, 100.0
+    );
+    let countdown = world.create_entity()
+        .with(countdown_transform)
+        .with(UiText::new(
+            font.clone(),
+            String::new(),
+            [1.0, 1.0, 1.0, 1.0],
+            80.0,
+            LineMode::Single,
+            Anchor::Middle
+        ))
+        .build();
+
+    // power-ups caught, under the scores
+    let mut effects = Vec::new();
+    for (id, x) in [("P1_effects", -150.0), ("P2_effects", 150.0)].iter() {
+        let transform = UiTransform::new(
+            id.to_string(), Anchor::TopMiddle, Anchor::TopMiddle,
+            *x, -100.0, 1.0, 300.0, 30.0
+        );
+        let entity = world.create_entity()
+            .with(transform)
+            .with(UiText::new(
+                font.clone(),
+                String::new(),
+                [1.0, 1.0, 1.0, 1.0],
+                20.0,
+                LineMode::Single,
+                Anchor::Middle
+            ))
+            .build();
+        effects.push(entity);
+    }
+
+    world.insert(ScoreText { p1_score, p2_score, countdown, p1_effects: effects[0], p2_effects: effects[1] });
 }
 
 /// Load the sprite sheet necessary to render the graphics.
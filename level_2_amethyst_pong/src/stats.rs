@@ -0,0 +1,91 @@
+use std::path::PathBuf;
+
+use amethyst::{
+    ecs::Entity,
+    input::{InputEvent, StringBindings, VirtualKeyCode},
+    prelude::*,
+    ui::{Anchor, LineMode, UiText, UiTransform},
+};
+use log::warn;
+use pong_sim::{user_data_dir, History};
+
+use crate::controls::Paused;
+use crate::pong::load_font;
+
+const HISTORY_PATH: &str = "pong/history.ron";
+
+/// Where the history is saved, `None` when the system has no data dir.
+pub fn history_path() -> Option<PathBuf> {
+    user_data_dir().map(|directory| directory.join(HISTORY_PATH))
+}
+
+/// The saved history, or an empty one.
+pub fn load_history() -> History {
+    match history_path() {
+        Some(path) if path.exists() => History::load(&path).unwrap_or_else(|error| {
+            warn!("{}, starting a new history", error);
+            History::default()
+        }),
+        _ => History::default()
+    }
+}
+
+/// Opens the stats screen, F2.
+pub fn opens_stats(event: &InputEvent<StringBindings>) -> bool {
+    matches!(event, InputEvent::KeyPressed { key_code: VirtualKeyCode::F2, .. })
+}
+
+/// Pushed over the game to show the last matches and the high scores of the
+/// `History` resource, the game is paused.
+#[derive(Default)]
+pub struct StatsScreen {
+    texts: Vec<Entity>
+}
+
+impl SimpleState for StatsScreen {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+        world.insert(Paused(true));
+        let font = load_font(world);
+
+        let mut texts = vec!["STATS".to_string()];
+        texts.extend(world.read_resource::<History>().lines());
+        texts.push("escape to go back".to_string());
+
+        let top = texts.len() as f32 * 15.0;
+        for (index, text) in texts.into_iter().enumerate() {
+            let transform = UiTransform::new(
+                format!("stats_{}", index), Anchor::Middle, Anchor::Middle,
+                0.0, top - index as f32 * 30.0, 2.0, 800.0, 30.0
+            );
+            let entity = world.create_entity()
+                .with(transform)
+                .with(UiText::new(
+                    font.clone(),
+                    text,
+                    [1.0, 1.0, 1.0, 1.0],
+                    if index == 0 { 40.0 } else { 20.0 },
+                    LineMode::Single,
+                    Anchor::Middle
+                ))
+                .build();
+            self.texts.push(entity);
+        }
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+        world.insert(Paused(false));
+        for entity in self.texts.drain(..) {
+            world.delete_entity(entity).expect("Can not delete the stats screen");
+        }
+    }
+
+    fn handle_event(&mut self, _: StateData<'_, GameData<'_, '_>>, event: StateEvent) -> SimpleTrans {
+        match &event {
+            StateEvent::Input(InputEvent::KeyPressed { key_code: VirtualKeyCode::Escape, .. }) => Trans::Pop,
+            StateEvent::Input(event) if opens_stats(event) => Trans::Pop,
+            _ => Trans::None
+        }
+    }
+}
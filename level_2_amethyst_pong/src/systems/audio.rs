@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use amethyst::{
+    assets::{AssetStorage, Loader},
+    audio::{output::Output, AudioSink, Source, SourceHandle, WavFormat},
+    core::timing::Time,
+    ecs::{Read, ReadExpect, System, Write},
+    input::{InputEvent, StringBindings, VirtualKeyCode},
+    shrev::{EventChannel, ReaderId},
+};
+use log::warn;
+use pong_sim::{AudioPlayer, Event, Mixer, Music, NullBackend, PongSim, Sound};
+
+use crate::controls::Paused;
+
+struct Sources {
+    effects: HashMap<Sound, SourceHandle>,
+    music: [SourceHandle; 2]
+}
+
+/// Play a sound for each event of the `SimulationSystem`, and the menu music
+/// under the menus and the game over text. M mutes everything, F5 and F6
+/// change the volume of the effects, F7 and F8 the one of the music, saved to
+/// `config/audio.ron`.
+pub struct AudioSystem {
+    /// Drained every frame.
+    player: AudioPlayer<NullBackend>,
+    path: PathBuf,
+    sources: Option<Sources>,
+    /// One looping track each, their volumes make the cross-fade.
+    sinks: Option<[AudioSink; 2]>,
+    events: Option<ReaderId<Event>>,
+    inputs: Option<ReaderId<InputEvent<StringBindings>>>
+}
+
+impl AudioSystem {
+    pub fn new(path: PathBuf) -> AudioSystem {
+        let mut player = AudioPlayer::new(NullBackend::default());
+        player.mixer = Mixer::load_saved(&path).unwrap_or_else(|error| {
+            warn!("{}, using the default volumes", error);
+            Mixer::default()
+        });
+        AudioSystem { player, path, sources: None, sinks: None, events: None, inputs: None }
+    }
+
+    fn volume_key(&mut self, key: VirtualKeyCode) -> bool {
+        let mixer = &mut self.player.mixer;
+        let step = Mixer::VOLUME_STEP;
+        match key {
+            VirtualKeyCode::M => mixer.master.muted = !mixer.master.muted,
+            VirtualKeyCode::F5 => mixer.effects.adjust(-step),
+            VirtualKeyCode::F6 => mixer.effects.adjust(step),
+            VirtualKeyCode::F7 => mixer.music.adjust(-step),
+            VirtualKeyCode::F8 => mixer.music.adjust(step),
+            _ => return false
+        }
+        true
+    }
+}
+
+fn load_sources(loader: &Loader, storage: &AssetStorage<Source>) -> Sources {
+    let load = |name: &str| loader.load(format!("audio/{}.wav", name), WavFormat, (), storage);
+    Sources {
+        effects: Sound::ALL.iter().map(|sound| (*sound, load(sound.name()))).collect(),
+        music: [load(Music::ALL[0].name()), load(Music::ALL[1].name())]
+    }
+}
+
+impl<'s> System<'s> for AudioSystem {
+    type SystemData = (
+        Write<'s, EventChannel<Event>>,
+        Write<'s, EventChannel<InputEvent<StringBindings>>>,
+        ReadExpect<'s, PongSim>,
+        Read<'s, Paused>,
+        Read<'s, Time>,
+        ReadExpect<'s, Loader>,
+        Read<'s, AssetStorage<Source>>,
+        Option<Read<'s, Output>>
+    );
+
+    fn run(&mut self, (mut events, mut inputs, sim, paused, time, loader, storage, output): Self::SystemData) {
+        let events_reader = self.events.get_or_insert_with(|| events.register_reader());
+        // both players are on this computer
+        self.player.play_events(events.read(events_reader), None);
+
+        let inputs_reader = self.inputs.get_or_insert_with(|| inputs.register_reader());
+        let keys: Vec<VirtualKeyCode> = inputs.read(inputs_reader)
+            .filter_map(|event| match event {
+                InputEvent::KeyPressed { key_code, .. } => Some(*key_code),
+                _ => None
+            })
+            .collect();
+        if !paused.0 {
+            let mut changed = false;
+            for key in keys {
+                changed |= self.volume_key(key);
+            }
+            if changed {
+                if let Err(error) = self.player.mixer.save(&self.path) {
+                    warn!("{}", error);
+                }
+            }
+        }
+
+        let music = if paused.0 || sim.winner().is_some() { Music::Menu } else { Music::Game };
+        if self.player.music() != Some(music) {
+            self.player.set_music(Some(music));
+        }
+        self.player.update(time.delta_seconds());
+
+        let sources = self.sources.get_or_insert_with(|| load_sources(&loader, &storage));
+        let backend = &mut self.player.backend;
+        let output = match output {
+            Some(output) => output,
+            None => {
+                // no audio device
+                backend.played.clear();
+                return;
+            }
+        };
+        for (sound, volume) in backend.played.drain(..) {
+            if let Some(source) = sources.effects.get(&sound).and_then(|handle| storage.get(handle)) {
+                output.play_once(source, volume);
+            }
+        }
+
+        let sinks = self.sinks.get_or_insert_with(|| [AudioSink::new(&output), AudioSink::new(&output)]);
+        for ((sink, handle), volume) in sinks.iter().zip(sources.music.iter()).zip(backend.music.iter()) {
+            sink.set_volume(*volume);
+            // loops the track once it is loaded
+            if sink.empty() {
+                if let Some(source) = storage.get(handle) {
+                    if let Err(error) = sink.append(source) {
+                        warn!("{}", error);
+                    }
+                }
+            }
+        }
+    }
+}
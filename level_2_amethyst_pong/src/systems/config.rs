@@ -0,0 +1,40 @@
+use amethyst::{
+    core::timing::Time,
+    ecs::{Read, System, WriteExpect},
+};
+use log::{info, warn};
+use pong_sim::{ConfigFile, PongSim};
+
+/// Seconds between two checks of the tuning file.
+const POLL_INTERVAL: f32 = 1.0;
+
+/// Apply the tuning file to the running match when it is saved.
+#[derive(Default)]
+pub struct ConfigSystem {
+    elapsed: f32
+}
+
+impl<'s> System<'s> for ConfigSystem {
+    type SystemData = (
+        WriteExpect<'s, ConfigFile>,
+        WriteExpect<'s, PongSim>,
+        Read<'s, Time>
+    );
+
+    fn run(&mut self, (mut file, mut sim, time): Self::SystemData) {
+        self.elapsed += time.delta_seconds();
+        if self.elapsed < POLL_INTERVAL {
+            return;
+        }
+        self.elapsed = 0.0;
+
+        match file.reload() {
+            Some(Ok(config)) => {
+                info!("Reloaded {:?}", file.path());
+                sim.set_config(config);
+            }
+            Some(Err(error)) => warn!("{}", error),
+            None => {}
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use amethyst::{
+    core::{math::Vector3, timing::Time, transform::Transform, Hidden},
+    ecs::{Entities, Entity, Join, Read, ReadExpect, ReadStorage, System, Write, WriteStorage},
+    renderer::{palette::Srgba, resources::Tint, Camera, SpriteRender},
+    shrev::{EventChannel, ReaderId},
+};
+use pong_sim::{Event, MatchEffects, PongSim};
+
+use crate::pong::{to_arena, Ball, BALL_SPRITE_SIZE};
+
+/// Sparks over the ball, the trail under it.
+const SPARK_DEPTH: f32 = 0.5;
+const TRAIL_DEPTH: f32 = -0.5;
+
+/// Sparks on paddle hits and goals, a trail behind the ball and a shake of
+/// the camera. The sprites are pooled and hidden when unused.
+#[derive(Default)]
+pub struct EffectsSystem {
+    effects: MatchEffects,
+    sparks: Vec<Entity>,
+    trail: Vec<Entity>,
+    events: Option<ReaderId<Event>>
+}
+
+impl<'s> System<'s> for EffectsSystem {
+    type SystemData = (
+        Entities<'s>,
+        Write<'s, EventChannel<Event>>,
+        ReadExpect<'s, PongSim>,
+        Read<'s, Time>,
+        ReadStorage<'s, Ball>,
+        ReadStorage<'s, Camera>,
+        WriteStorage<'s, SpriteRender>,
+        WriteStorage<'s, Transform>,
+        WriteStorage<'s, Tint>,
+        WriteStorage<'s, Hidden>
+    );
+
+    fn run(&mut self, (
+        entities,
+        mut channel,
+        sim,
+        time,
+        balls,
+        cameras,
+        mut sprites,
+        mut transforms,
+        mut tints,
+        mut hidden
+    ): Self::SystemData) {
+        let reader = self.events.get_or_insert_with(|| channel.register_reader());
+        let events: Vec<Event> = channel.read(reader).copied().collect();
+        self.effects.handle(&sim, &events);
+        self.effects.update(&sim, time.delta_seconds());
+
+        // every sprite is the one of the ball
+        let sprite = match (&balls, &sprites).join().next() {
+            Some((_, sprite)) => sprite.clone(),
+            None => return
+        };
+
+        let sparks: Vec<_> = self.effects.sparks.particles().iter()
+            .map(|particle| {
+                let (x, y) = to_arena(&sim.config, particle.position);
+                let [r, g, b, a] = self.effects.sparks.color(particle);
+                (x, y, particle.size, Srgba::new(r, g, b, a))
+            })
+            .collect();
+        // smaller and fainter toward the end
+        let trail: Vec<_> = self.effects.trail.points()
+            .map(|(position, weight)| {
+                let (x, y) = to_arena(&sim.config, position);
+                (x, y, sim.config.ball_radius * 2.0 * weight, Srgba::new(1.0, 1.0, 1.0, weight * 0.4))
+            })
+            .collect();
+
+        let mut storages = (&entities, &mut sprites, &mut transforms, &mut tints, &mut hidden);
+        show(&mut self.sparks, &sparks, SPARK_DEPTH, &sprite, &mut storages);
+        show(&mut self.trail, &trail, TRAIL_DEPTH, &sprite, &mut storages);
+
+        // the camera looks at the center of the arena
+        let (offset, angle) = self.effects.shake.offset();
+        for (_, transform) in (&cameras, &mut transforms).join() {
+            transform.set_translation_xyz(
+                sim.config.arena_width * 0.5 + offset.x,
+                sim.config.arena_height * 0.5 + offset.y,
+                1.0
+            );
+            transform.set_rotation_2d(angle);
+        }
+    }
+}
+
+type Storages<'a, 's> = (
+    &'a Entities<'s>,
+    &'a mut WriteStorage<'s, SpriteRender>,
+    &'a mut WriteStorage<'s, Transform>,
+    &'a mut WriteStorage<'s, Tint>,
+    &'a mut WriteStorage<'s, Hidden>
+);
+
+/// Place a sprite of `pool` at each of `shown`, growing the pool as needed
+/// and hiding the sprites left.
+fn show(
+    pool: &mut Vec<Entity>,
+    shown: &[(f32, f32, f32, Srgba)],
+    depth: f32,
+    sprite: &SpriteRender,
+    (entities, sprites, transforms, tints, hidden): &mut Storages
+) {
+    while pool.len() < shown.len() {
+        let entity = entities.build_entity()
+            .with(Transform::default(), transforms)
+            .with(sprite.clone(), sprites)
+            .build();
+        pool.push(entity);
+    }
+    for (index, entity) in pool.iter().enumerate() {
+        let (x, y, size, color) = match shown.get(index) {
+            Some(shown) => *shown,
+            None => {
+                hidden.insert(*entity, Hidden).expect("Can not hide an effect");
+                continue;
+            }
+        };
+        hidden.remove(*entity);
+        if let Some(transform) = transforms.get_mut(*entity) {
+            transform.set_translation_xyz(x, y, depth);
+            transform.set_scale(Vector3::new(size / BALL_SPRITE_SIZE, size / BALL_SPRITE_SIZE, 1.0));
+        }
+        tints.insert(*entity, Tint(color)).expect("Can not tint an effect");
+    }
+}
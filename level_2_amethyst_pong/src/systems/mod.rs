@@ -1,9 +1,17 @@
+pub use self::audio::AudioSystem;
+pub use self::config::ConfigSystem;
+pub use self::effects::EffectsSystem;
 pub use self::paddle::PaddleSystem;
-pub use self::move_ball::MoveBallsSystem;
-pub use self::bounce::BounceSystem;
+pub use self::power_ups::PowerUpSystem;
+pub use self::simulation::SimulationSystem;
+pub use self::stats::StatsSystem;
 pub use self::winner::WinnerSystem;
 
+mod audio;
+mod config;
+mod effects;
 mod paddle;
-mod move_ball;
-mod bounce;
+mod power_ups;
+mod simulation;
+mod stats;
 mod winner;
\ No newline at end of file
@@ -1,36 +1,42 @@
-use amethyst::core::{Time, Transform};
 use amethyst::derive::SystemDesc;
-use amethyst::ecs::{Join, Read, ReadStorage, System, SystemData, WriteStorage};
-use amethyst::input::{InputHandler, StringBindings};
+use amethyst::ecs::{Read, ReadExpect, System, SystemData, Write};
+use amethyst::input::{Button, InputHandler, StringBindings};
+use pong_sim::{Bindings, Control, Inputs, Side};
 
-use crate::pong::{Paddle, Side, ARENA_HEIGHT, PADDLE_HEIGHT};
+use crate::controls::CONTROLLER_AXES;
 
+/// Read the paddle axes for the simulation, from the keys and controllers
+/// bound in the `Bindings` resource.
 #[derive(SystemDesc)]
 pub struct PaddleSystem;
 
 impl<'s> System<'s> for PaddleSystem {
     type SystemData = (
-        WriteStorage<'s, Transform>,
-        ReadStorage<'s, Paddle>,
-        Read<'s, InputHandler<StringBindings>>,
-        Read<'s, Time>
+        Write<'s, Inputs>,
+        ReadExpect<'s, Bindings>,
+        Read<'s, InputHandler<StringBindings>>
     );
 
-    fn run(&mut self, (mut transforms, paddles, input, time): Self::SystemData) {
-        for (paddle, transform) in (&paddles, &mut transforms).join() {
-            let movement = match paddle.side {
-                Side::Left => input.axis_value("left_paddle"),
-                Side::Right => input.axis_value("right_paddle")
-            };
-            if let Some(mv_amount) = movement {
-                if mv_amount != 0.0 {
-                    let paddle_y = transform.translation().y;
-                    transform.set_translation_y(
-                        (paddle_y + mv_amount * paddle.velocity * time.delta_seconds())
-                            .min(ARENA_HEIGHT - PADDLE_HEIGHT * 0.5)
-                            .max(PADDLE_HEIGHT * 0.5));
+    fn run(&mut self, (mut inputs, bindings, input): Self::SystemData) {
+        let pressed = |pressed: bool| if pressed { 1.0 } else { 0.0 };
+        let strength = |control: &Control| match control {
+            Control::Key(name) => pressed(input.keys_that_are_down().any(|key| format!("{:?}", key) == *name)),
+            Control::Button { gamepad, button } => pressed(input.buttons_that_are_down().any(|held| match held {
+                Button::Controller(id, held) => id == *gamepad && format!("{:?}", held) == *button,
+                _ => false
+            })),
+            Control::Axis { gamepad, axis, positive } => {
+                match CONTROLLER_AXES.iter().find(|known| format!("{:?}", known) == *axis) {
+                    Some(axis) => {
+                        let value = input.controller_axis_value(*gamepad, *axis).unwrap_or(0.0);
+                        if *positive { value } else { -value }
+                    }
+                    None => 0.0
                 }
             }
-        }
+        };
+
+        inputs.left = bindings.axis(Side::Left, &strength);
+        inputs.right = bindings.axis(Side::Right, &strength);
     }
-}
\ No newline at end of file
+}
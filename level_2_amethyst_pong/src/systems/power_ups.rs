@@ -0,0 +1,103 @@
+use amethyst::{
+    core::transform::Transform,
+    derive::SystemDesc,
+    ecs::{Entities, Join, ReadExpect, ReadStorage, System, SystemData, WriteStorage},
+    renderer::{palette::Srgba, resources::Tint, SpriteRender},
+    ui::UiText,
+};
+use pong_sim::{PongSim, PowerUpKind, Side};
+
+use crate::pong::{ball_scale, to_arena, Ball, ExtraBall, PowerUpSprite, ScoreText};
+
+/// Keep a sprite for each power-up in the arena and each extra ball of a
+/// multi-ball, the `SimulationSystem` moves the balls. The power-ups caught
+/// are listed under the scores.
+#[derive(SystemDesc)]
+pub struct PowerUpSystem;
+
+fn tint(kind: PowerUpKind) -> Tint {
+    let [r, g, b, a] = kind.color();
+    Tint(Srgba::new(r, g, b, a))
+}
+
+impl<'s> System<'s> for PowerUpSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadExpect<'s, PongSim>,
+        ReadExpect<'s, ScoreText>,
+        ReadStorage<'s, Ball>,
+        WriteStorage<'s, ExtraBall>,
+        WriteStorage<'s, PowerUpSprite>,
+        WriteStorage<'s, SpriteRender>,
+        WriteStorage<'s, Transform>,
+        WriteStorage<'s, Tint>,
+        WriteStorage<'s, UiText>
+    );
+
+    fn run(&mut self, (
+        entities,
+        sim,
+        score_text,
+        balls,
+        mut extra_balls,
+        mut power_ups,
+        mut sprites,
+        mut transforms,
+        mut tints,
+        mut ui_text
+    ): Self::SystemData) {
+        // every sprite is the one of the ball
+        let sprite = match (&balls, &sprites).join().next() {
+            Some((_, sprite)) => sprite.clone(),
+            None => return
+        };
+
+        let existing: Vec<_> = (&entities, &extra_balls).join().map(|(entity, _)| entity).collect();
+        for entity in existing.iter().skip(sim.extra_balls.len()) {
+            entities.delete(*entity).expect("Can not delete an extra ball");
+        }
+        for ball in sim.extra_balls.iter().skip(existing.len()) {
+            let (x, y) = to_arena(&sim.config, ball.position);
+            let mut transform = Transform::default();
+            transform.set_translation_xyz(x, y, 0.0);
+            transform.set_scale(ball_scale(&sim.config));
+            entities.build_entity()
+                .with(ExtraBall, &mut extra_balls)
+                .with(transform, &mut transforms)
+                .with(sprite.clone(), &mut sprites)
+                .build();
+        }
+
+        let mut existing: Vec<_> = (&entities, &power_ups).join().map(|(entity, _)| entity).collect();
+        for entity in existing.drain(sim.power_ups.len().min(existing.len())..) {
+            entities.delete(entity).expect("Can not delete a power-up");
+        }
+        while existing.len() < sim.power_ups.len() {
+            let entity = entities.build_entity()
+                .with(PowerUpSprite, &mut power_ups)
+                .with(Transform::default(), &mut transforms)
+                .with(sprite.clone(), &mut sprites)
+                .build();
+            existing.push(entity);
+        }
+        let scale = ball_scale(&sim.config) * (sim.config.power_up_radius / sim.config.ball_radius);
+        for (entity, power_up) in existing.iter().zip(sim.power_ups.iter()) {
+            if let Some(transform) = transforms.get_mut(*entity) {
+                let (x, y) = to_arena(&sim.config, power_up.position);
+                transform.set_translation_xyz(x, y, 0.0);
+                transform.set_scale(scale);
+            }
+            tints.insert(*entity, tint(power_up.kind)).expect("Can not tint a power-up");
+        }
+
+        for (entity, side) in [(score_text.p1_effects, Side::Left), (score_text.p2_effects, Side::Right)].iter() {
+            if let Some(text) = ui_text.get_mut(*entity) {
+                text.text = sim.effects.iter()
+                    .filter(|effect| effect.side == *side)
+                    .map(|effect| format!("{} {}", effect.kind.name(), effect.remaining.ceil()))
+                    .collect::<Vec<_>>()
+                    .join("  ");
+            }
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use amethyst::{
+    core::timing::Time,
+    core::transform::Transform,
+    ecs::{Join, Read, ReadStorage, System, Write, WriteExpect, WriteStorage},
+    shrev::EventChannel,
+};
+use pong_sim::{Event, FixedTimestep, Inputs, PongSim};
+
+use crate::controls::Paused;
+use crate::pong::{ball_scale, paddle_scale, to_arena, Ball, ExtraBall, Paddle};
+
+/// Advance the shared Pong simulation at a fixed rate and move the entities
+/// accordingly, interpolating between the last two steps. The events of the
+/// steps are written to the `EventChannel<Event>` resource.
+#[derive(Default)]
+pub struct SimulationSystem {
+    timestep: FixedTimestep,
+    previous: Option<PongSim>
+}
+
+impl<'s> System<'s> for SimulationSystem {
+    type SystemData = (
+        WriteExpect<'s, PongSim>,
+        Read<'s, Inputs>,
+        Read<'s, Paused>,
+        Read<'s, Time>,
+        Write<'s, EventChannel<Event>>,
+        ReadStorage<'s, Ball>,
+        ReadStorage<'s, ExtraBall>,
+        ReadStorage<'s, Paddle>,
+        WriteStorage<'s, Transform>
+    );
+
+    fn run(&mut self, (mut sim, inputs, paused, time, mut events, balls, extra_balls, paddles, mut transforms): Self::SystemData) {
+        // the time spent in a menu is not simulated afterwards
+        let steps = if paused.0 { 0 } else { self.timestep.advance(time.delta_seconds()) };
+        for _ in 0..steps {
+            self.previous = Some(sim.clone());
+            for event in sim.step(self.timestep.step(), *inputs) {
+                if let Event::Goal(_) = event {
+                    println!(
+                        "Score: | {:^3} | {:^3} |",
+                        sim.score.left, sim.score.right
+                    );
+                }
+                events.single_write(event);
+            }
+        }
+
+        let previous = self.previous.as_ref().unwrap_or(&*sim);
+        let frame = sim.frame(previous, self.timestep.alpha());
+
+        // the tuning can change while the game runs
+        let (x, y) = to_arena(&sim.config, frame.ball);
+        for (_, transform) in (&balls, &mut transforms).join() {
+            transform.set_translation_x(x);
+            transform.set_translation_y(y);
+            transform.set_scale(ball_scale(&sim.config));
+        }
+        // the `PowerUpSystem` keeps one entity per extra ball
+        for ((_, transform), ball) in (&extra_balls, &mut transforms).join().zip(frame.extra_balls.iter()) {
+            let (x, y) = to_arena(&sim.config, *ball);
+            transform.set_translation_x(x);
+            transform.set_translation_y(y);
+        }
+
+        for (paddle, transform) in (&paddles, &mut transforms).join() {
+            let (x, y) = to_arena(&sim.config, frame.paddle(paddle.side));
+            transform.set_translation_x(x);
+            transform.set_translation_y(y);
+            // grown or shrunk by the power-ups
+            let mut scale = paddle_scale(&sim.config);
+            scale.y *= sim.paddle_height(paddle.side) / sim.config.paddle_height;
+            transform.set_scale(scale);
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use amethyst::{
+    ecs::{ReadExpect, System, Write},
+    shrev::{EventChannel, ReaderId},
+};
+use log::{info, warn};
+use pong_sim::{Event, History, MatchRecord, MatchStats, PongSim};
+
+use crate::stats::history_path;
+
+/// Keep the statistics of the match being played, and add it to the
+/// `History` resource once won. The history is saved in the user data dir.
+#[derive(Default)]
+pub struct StatsSystem {
+    stats: MatchStats,
+    events: Option<ReaderId<Event>>
+}
+
+impl<'s> System<'s> for StatsSystem {
+    type SystemData = (
+        Write<'s, EventChannel<Event>>,
+        ReadExpect<'s, PongSim>,
+        Write<'s, History>
+    );
+
+    fn run(&mut self, (mut channel, sim, mut history): Self::SystemData) {
+        let reader = self.events.get_or_insert_with(|| channel.register_reader());
+        let events: Vec<Event> = channel.read(reader).copied().collect();
+        self.stats.record(&sim, &events);
+        if !events.iter().any(|event| matches!(event, Event::MatchOver(_))) {
+            return;
+        }
+
+        let stats = std::mem::take(&mut self.stats);
+        if let Some(record) = MatchRecord::new(&sim, stats) {
+            if let Some(rank) = history.add(record) {
+                info!("New high score, number {}", rank + 1);
+            }
+            if let Some(path) = history_path() {
+                if let Err(error) = history.save(&path) {
+                    warn!("{}", error);
+                }
+            }
+        }
+    }
+}
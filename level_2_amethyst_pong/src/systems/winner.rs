@@ -1,62 +1,38 @@
 use amethyst::{
-    core::transform::Transform, 
     derive::SystemDesc, 
-    ecs::{Join, ReadExpect, System, SystemData, Write, WriteStorage}, 
+    ecs::{ReadExpect, System, SystemData, WriteStorage}, 
     ui::UiText};
+use pong_sim::PongSim;
 
-use crate::pong::{Ball, ScoreBoard, ScoreText, ARENA_WIDTH, ARENA_HEIGHT};
+use crate::pong::ScoreText;
 
+/// Show the score of the simulation and the serve countdown.
 #[derive(SystemDesc)]
 pub struct WinnerSystem;
 
 impl<'s> System<'s> for WinnerSystem {
     type SystemData = (
-        WriteStorage<'s, Ball>,
-        WriteStorage<'s, Transform>,
         WriteStorage<'s, UiText>,
-        Write<'s, ScoreBoard>,
+        ReadExpect<'s, PongSim>,
         ReadExpect<'s, ScoreText>
     );
 
     fn run(&mut self, (
-        mut balls, 
-        mut locals,
         mut ui_text,
-        mut scores,
+        sim,
         score_text
     ): Self::SystemData) {
-        for (ball, transform) in (&mut balls, &mut locals).join() {
-            let ball_x = transform.translation().x;
-            
-            let did_hit = if ball_x <= ball.radius {
-                scores.score_right = (scores.score_right + 1).min(999);
-                if let Some(text) = ui_text.get_mut(score_text.p2_score) {
-                    text.text = scores.score_right.to_string();
-                }
-                true
-            } 
-            else if ball_x >= ARENA_WIDTH - ball.radius {
-                scores.score_left = (scores.score_left + 1)
-                    .min(999);
-                if let Some(text) = ui_text.get_mut(score_text.p1_score) {
-                    text.text = scores.score_left.to_string();
-                }
-                true
-            } 
-            else {
-                false
+        if let Some(text) = ui_text.get_mut(score_text.p1_score) {
+            text.text = sim.score.left.to_string();
+        }
+        if let Some(text) = ui_text.get_mut(score_text.p2_score) {
+            text.text = sim.score.right.to_string();
+        }
+        if let Some(text) = ui_text.get_mut(score_text.countdown) {
+            text.text = match sim.serve_countdown() {
+                Some(seconds) => format!("{}", seconds.ceil()),
+                None => String::new()
             };
-
-            if did_hit {
-                ball.velocity[0] = -ball.velocity[0];
-                transform.set_translation_x(ARENA_WIDTH * 0.5);
-                transform.set_translation_y(ARENA_HEIGHT * 0.5);
-
-                println!(
-                    "Score: | {:^3} | {:^3} |",
-                    scores.score_left, scores.score_right
-                );
-            }
         }
     }
-}
\ No newline at end of file
+}
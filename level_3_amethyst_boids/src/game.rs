@@ -2,7 +2,7 @@
 use amethyst::{
     DataDispose, Error, GameData, StateData, 
     assets::{AssetStorage, Handle, Loader}, 
-    core::{ArcThreadPool, SystemBundle, Transform, math::{Vector2, Vector3}}, 
+    core::{ArcThreadPool, SystemBundle, Transform, math::{Vector2, Vector3}, timing::Time}, 
     input::{VirtualKeyCode, is_close_requested, is_key_down}, 
     prelude::*, 
     renderer::{Camera, ImageFormat, SpriteRender, SpriteSheet, SpriteSheetFormat, Texture}, 
@@ -15,6 +15,7 @@ use rand::{thread_rng, Rng};
 pub mod components;
 use crate::game::components::{Physics};
 use crate::config::BoidConfig;
+use crate::timestep::FixedTimestep;
 
 use self::components::{Boid, FpsText};
 
@@ -89,10 +90,19 @@ pub struct BoidGameData<'a, 'b> {
 }
 
 impl<'a, 'b> BoidGameData<'a, 'b> {
+    /// Run the simulation as many fixed steps as the frame time allows, then
+    /// the core systems once. When paused the accumulator is left untouched so
+    /// that the interpolated sprites do not move.
     pub fn update(&mut self, world: &World, running: bool) {
         if running {
+            let steps = {
+                let dt = world.read_resource::<Time>().delta_seconds();
+                world.write_resource::<FixedTimestep>().advance(dt)
+            };
             if let Some(dispatcher) = self.running_dispatcher.as_mut() {
-                dispatcher.dispatch(&world);
+                for _ in 0..steps {
+                    dispatcher.dispatch(&world);
+                }
             }
         }
         if let Some(dispatcher) = self.core_dispatcher.as_mut() {
@@ -128,6 +138,14 @@ impl<'a, 'b> BoidGameDataBuilder<'a, 'b> {
         Ok(self)
     }
 
+    pub fn with_core<S>(mut self, system: S, name: &str, dependencies: &[&str]) -> Self
+    where
+        for<'c> S: System<'c> + Send + 'a
+    {
+        self.core.add(system, name, dependencies);
+        self
+    }
+
     pub fn with_running<S>(mut self, system: S, name: &str, dependencies: &[&str]) -> Self
     where
         for<'c> S: System<'c> + Send + 'a
@@ -139,6 +157,8 @@ impl<'a, 'b> BoidGameDataBuilder<'a, 'b> {
 
 impl<'a, 'b> DataInit<BoidGameData<'a, 'b>> for BoidGameDataBuilder<'a, 'b> {
     fn build(self, world: &mut World) -> BoidGameData<'a, 'b> {
+        world.insert(FixedTimestep::default());
+
         let pool = (*world.read_resource::<ArcThreadPool>()).clone();
         let mut core_dispatcher = self.core.with_pool(pool.clone()).build();
         let mut running_dispatcher = self.running.with_pool(pool.clone()).build();
@@ -212,15 +232,18 @@ fn init_boids(world: &mut World, sprite_sheet_handle: Handle<SpriteSheet>) {
             repulsion: Vector2::zeros()
         };
 
-        let mut transform = Transform::default();
-        transform.set_translation_xyz(
+        let position = Vector2::new(
             rng.gen_range(0.0..GAME_WIDTH) - GAME_WIDTH * 0.5, 
-            rng.gen_range(0.0..GAME_HEIGHT) - GAME_HEIGHT * 0.5, 
-            0.0
+            rng.gen_range(0.0..GAME_HEIGHT) - GAME_HEIGHT * 0.5
         );
+
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(position.x, position.y, 0.0);
         transform.set_scale(Vector3::new(boid_scale, boid_scale, boid_scale));
 
         let physics = Physics { 
+            position,
+            previous_position: position,
             velocity: (Vector2::new_random() - Vector2::new(0.5, 0.5)) * 50.0,
             acceleration: Vector2::new(0.0, 0.0) 
         };
@@ -6,7 +6,10 @@ use amethyst::{core::math::Vector2, ecs::{Component, DenseVecStorage}};
 pub use self::boid::Boid;
 pub use self::fps::FpsText;
 
+/// The simulated state of a boid, its `Transform` is only interpolated from it.
 pub struct Physics {
+    pub position: Vector2<f32>,
+    pub previous_position: Vector2<f32>,
     pub velocity: Vector2<f32>,
     pub acceleration: Vector2<f32>
 }
@@ -1,6 +1,7 @@
 mod game;
 mod systems;
 mod config;
+mod timestep;
 
 use amethyst::utils::fps_counter::FpsCounterBundle;
 #[allow(unused)]
@@ -62,8 +63,10 @@ fn main() -> amethyst::Result<()> {
         .with_bundle(input_bundle)?
         .with_bundle(ui_bundle)?
         .with_bundle(fps_bundle)?
-        .with(systems::BoidSystem, "boid_system", &[])
+        .with(systems::GridSystem, "grid_system", &[])
+        .with(systems::BoidSystem, "boid_system", &["grid_system"])
         .with(systems::MoveSystem, "move_system", &["boid_system"])
+        .with(systems::InterpolationSystem, "interpolation_system", &["move_system"])
         // .with(CameraOrthoSystem, "camera_system", &[]);
         .with(systems::CameraSystem, "camera_system", &[])
         .with(systems::FpsSystem, "fps_system", &[]);
@@ -75,11 +78,13 @@ fn main() -> amethyst::Result<()> {
         .with_base_bundle(&mut app_builder.world, input_bundle)?
         .with_base_bundle(&mut app_builder.world, ui_bundle)?
         .with_base_bundle(&mut app_builder.world, fps_bundle)?
-        .with_running(systems::BoidSystem, "boid_system", &[])
+        .with_running(systems::GridSystem, "grid_system", &[])
+        .with_running(systems::BoidSystem, "boid_system", &["grid_system"])
         .with_running(systems::MoveSystem, "move_system", &["boid_system"])
         // .with(CameraOrthoSystem, "camera_system", &[]);
-        .with_running(systems::CameraSystem, "camera_system", &[])
-        .with_running(systems::FpsSystem, "fps_system", &[]);
+        .with_core(systems::InterpolationSystem, "interpolation_system", &[])
+        .with_core(systems::CameraSystem, "camera_system", &[])
+        .with_core(systems::FpsSystem, "fps_system", &[]);
 
     // let mut game = app_builder
     //     .with_resource(boid_config)
@@ -1,16 +1,15 @@
 
 use amethyst::{
-    core::{
-        timing::Time,
-        math::{Vector2, Vector3}, 
-        transform::Transform
-    }, 
+    core::math::Vector2, 
     derive::SystemDesc, 
     ecs::{Entities, Join, Read, ReadStorage, System, SystemData, WriteStorage}
 };
 
 use crate::{config::BoidConfig, game::components::{Boid, Physics}};
+use super::grid_system::BoidGrid;
 
+/// Steer each boid from its neighbours, found in the `BoidGrid`. The
+/// neighbours are seen across the edges of the screen.
 #[derive(SystemDesc)]
 pub struct BoidSystem;
 
@@ -18,33 +17,34 @@ impl<'s> System<'s> for BoidSystem {
     type SystemData = (
         Entities<'s>,
         WriteStorage<'s, Boid>,
-        ReadStorage<'s, Transform>,
         ReadStorage<'s, Physics>,
-        Read<'s, Time>,
-        Read<'s, BoidConfig>
-        
+        Read<'s, BoidConfig>,
+        Read<'s, BoidGrid>
     );
 
-    fn run(&mut self, (entities, mut boids, transforms, physics, _time, config): Self::SystemData) {
-        for (entity_a, physics_a, boid_a, transform_a) in (&entities, &physics, &mut boids, &transforms).join() {
-            let mut attraction_position: Vector3<f32> = Vector3::zeros();
+    fn run(&mut self, (entities, mut boids, physics, config, grid): Self::SystemData) {
+        let radius = config.attraction_radius.max(config.repulsion_radius).max(config.alignment_radius);
+
+        for (entity_a, physics_a, boid_a) in (&entities, &physics, &mut boids).join() {
+            let mut attraction_position: Vector2<f32> = Vector2::zeros();
             let mut attraction_count: f32 = 0.0;
-            let mut repulsion_force: Vector3<f32> = Vector3::zeros();
+            let mut repulsion_force: Vector2<f32> = Vector2::zeros();
             let mut alignment_direction: Vector2<f32> = Vector2::zeros();
             let mut alignment_count: f32 = 0.0;
             
-            for (entity_b, physics_b, transform_b) in (&entities, &physics, &transforms).join() {
+            let position_a = [physics_a.position.x, physics_a.position.y];
+            grid.query(position_a, radius, |&(entity, velocity), offset, square_distance| {
                 // avoid self
-                if entity_a == entity_b { continue; }
+                if entity == entity_a { return; }
 
-                let distance: Vector3<f32> = transform_a.translation() - transform_b.translation();
-                let square_distance = distance.magnitude_squared();
+                // the short way around the screen
+                let offset = Vector2::new(offset[0], offset[1]);
+                let distance: Vector2<f32> = -offset;
 
                 // attraction
                 if square_distance < config.attraction_radius * config.attraction_radius {
-                    attraction_position += transform_b.translation();
+                    attraction_position += physics_a.position + offset;
                     attraction_count += 1.0;
-                    // println!("magnitude between {:?} and {:?} = {}", entity_a, entity_b, square_distance);
                 }
 
                 // repulsion
@@ -56,19 +56,19 @@ impl<'s> System<'s> for BoidSystem {
 
                 // alignment
                 if square_distance < config.alignment_radius * config.alignment_radius {
-                    alignment_direction += physics_b.velocity;
+                    alignment_direction += velocity;
                     alignment_count += 1.0;
                 }
-            }
+            });
 
             // additionne les facteurs de steering
             if attraction_count > 0.0 { 
-                boid_a.attraction.x = (attraction_position.x / attraction_count - transform_a.translation().x) * config.attraction_factor;
-                boid_a.attraction.y = (attraction_position.y / attraction_count - transform_a.translation().y) * config.attraction_factor;
+                boid_a.attraction.x = (attraction_position.x / attraction_count - physics_a.position.x) * config.attraction_factor;
+                boid_a.attraction.y = (attraction_position.y / attraction_count - physics_a.position.y) * config.attraction_factor;
             }
 
             // if repulsion_count > 0.0 {
-            //     let repulsion_vect: Vector3<f32> = transform_a.translation() - repulsion_position / repulsion_count;
+            //     let repulsion_vect: Vector2<f32> = physics_a.position - repulsion_position / repulsion_count;
             //     let repulsion_value = repulsion_vect.magnitude_squared();
                 boid_a.repulsion.x = repulsion_force.x; // (repulsion_vect.x / repulsion_value) * config.repulsion_factor;
                 boid_a.repulsion.y = repulsion_force.y; // (repulsion_vect.y / repulsion_value) * config.repulsion_factor;
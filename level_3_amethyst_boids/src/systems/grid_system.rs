@@ -0,0 +1,38 @@
+use amethyst::{
+    core::math::Vector2,
+    derive::SystemDesc,
+    ecs::{Entities, Entity, Join, Read, ReadExpect, ReadStorage, System, SystemData, Write},
+    window::ScreenDimensions,
+};
+use spatial_grid::PayloadGrid;
+
+use crate::{config::BoidConfig, game::components::{Boid, Physics}};
+
+/// The entity and velocity of the boids bucketed by position, rebuilt every
+/// step by the `GridSystem` for the `BoidSystem` to find the neighbours of
+/// each boid.
+pub type BoidGrid = PayloadGrid<(Entity, Vector2<f32>)>;
+
+#[derive(SystemDesc)]
+pub struct GridSystem;
+
+impl<'s> System<'s> for GridSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, Physics>,
+        ReadStorage<'s, Boid>,
+        ReadExpect<'s, ScreenDimensions>,
+        Read<'s, BoidConfig>,
+        Write<'s, BoidGrid>
+    );
+
+    fn run(&mut self, (entities, physics, boids, screen_dimensions, config, mut grid): Self::SystemData) {
+        // the boids wrap around the screen a bit out of it, see the `MoveSystem`
+        let area = [screen_dimensions.width() + 40.0, screen_dimensions.height() + 40.0];
+        // one cell away at most
+        let cell_size = config.attraction_radius.max(config.repulsion_radius).max(config.alignment_radius).max(1.0);
+        let boids = (&entities, &physics, &boids).join()
+            .map(|(entity, physic, _)| ([physic.position.x, physic.position.y], (entity, physic.velocity)));
+        grid.rebuild(area, cell_size, boids);
+    }
+}
@@ -0,0 +1,31 @@
+use amethyst::{
+    core::transform::Transform, 
+    derive::SystemDesc, 
+    ecs::{Join, Read, ReadStorage, System, SystemData, WriteStorage}
+};
+
+use crate::{game::components::Physics, timestep::FixedTimestep};
+
+/// Place the sprites between the last two simulation steps, so that the boids
+/// move smoothly even when the frame rate is not a multiple of the simulation rate.
+#[derive(SystemDesc)]
+pub struct InterpolationSystem;
+
+impl<'s> System<'s> for InterpolationSystem {
+    type SystemData = (
+        WriteStorage<'s, Transform>,
+        ReadStorage<'s, Physics>,
+        Read<'s, FixedTimestep>
+    );
+
+    fn run(&mut self, (mut transforms, physics, timestep): Self::SystemData) {
+        let alpha = timestep.alpha();
+
+        for (transform, physic) in (&mut transforms, &physics).join() {
+            let position = physic.previous_position.lerp(&physic.position, alpha);
+            transform.set_translation_x(position.x);
+            transform.set_translation_y(position.y);
+            transform.set_rotation_z_axis(f32::atan2(physic.velocity.y, physic.velocity.x));
+        }
+    }
+}
@@ -1,9 +1,13 @@
 mod boid;
+mod grid_system;
 mod move_system;
 mod camera_system;
 mod fps_system;
+mod interpolation_system;
 
 pub use self::boid::BoidSystem; 
+pub use self::grid_system::{BoidGrid, GridSystem};
 pub use self::move_system::MoveSystem;
 pub use self::camera_system::CameraSystem;
-pub use self::fps_system::FpsSystem;
\ No newline at end of file
+pub use self::fps_system::FpsSystem;
+pub use self::interpolation_system::InterpolationSystem;
\ No newline at end of file
@@ -2,28 +2,29 @@
 #[allow(unused)]
 use log::{debug, info, warn, error};
 
-use amethyst::{core::timing::Time, core::transform::Transform, derive::SystemDesc, ecs::{Join, Read, ReadStorage, System, SystemData, WriteStorage}, shred::ReadExpect, window::ScreenDimensions};
+use amethyst::{core::math::Vector2, derive::SystemDesc, ecs::{Join, Read, ReadStorage, System, SystemData, WriteStorage}, shred::ReadExpect, window::ScreenDimensions};
 
-use crate::{config::BoidConfig, game::{components::{Boid, Physics}}};
+use crate::{config::BoidConfig, game::{components::{Boid, Physics}}, timestep::FixedTimestep};
 
 #[derive(SystemDesc)]
 pub struct MoveSystem;
 
 impl<'s> System<'s> for MoveSystem {
     type SystemData = (
-        WriteStorage<'s, Transform>,
         WriteStorage<'s, Physics>,
         ReadStorage<'s, Boid>,
         ReadExpect<'s, ScreenDimensions>,
-        Read<'s, Time>,
+        Read<'s, FixedTimestep>,
         Read<'s, BoidConfig>
     );
 
-    fn run(&mut self, (mut transforms, mut physics, boids, screen_dimensions, time, config): Self::SystemData) {
+    fn run(&mut self, (mut physics, boids, screen_dimensions, timestep, config): Self::SystemData) {
         let width = screen_dimensions.width() + 40.0;
         let height = screen_dimensions.height() + 40.0;
 
-        for (transform, physic, boid) in (&mut transforms, &mut physics, &boids).join() {
+        let dt = timestep.step;
+
+        for (physic, boid) in (&mut physics, &boids).join() {
             // calculate the new acceleration
             physic.acceleration = boid.alignment + boid.attraction + boid.repulsion;
             let a = physic.acceleration.magnitude();
@@ -32,7 +33,7 @@ impl<'s> System<'s> for MoveSystem {
             }
 
             // calculate new velocity with applied acceleration
-            physic.velocity += physic.acceleration * time.delta_seconds();
+            physic.velocity += physic.acceleration * dt;
 
             // avoid over speed and under speed
             let v = physic.velocity.magnitude();
@@ -44,27 +45,30 @@ impl<'s> System<'s> for MoveSystem {
             }
 
             // move entity based on velocity
-            transform.prepend_translation_x(physic.velocity.x * time.delta_seconds());
-            transform.prepend_translation_y(physic.velocity.y * time.delta_seconds());
+            physic.previous_position = physic.position;
+            physic.position += physic.velocity * dt;
 
-            debug!("translation: {} {}", transform.translation().x, transform.translation().y);
+            debug!("position: {} {}", physic.position.x, physic.position.y);
 
-            // wrap entity arround screen
-            if transform.translation().x < -width * 0.5 {
-                transform.prepend_translation_x(width);
+            // wrap entity arround screen, the previous position too so that
+            // the boid is not drawn crossing the screen
+            let mut offset = Vector2::zeros();
+            if physic.position.x < -width * 0.5 {
+                offset.x = width;
             }
-            else if transform.translation().x > width * 0.5 {
-                transform.prepend_translation_x(-width);
+            else if physic.position.x > width * 0.5 {
+                offset.x = -width;
             }
 
-            if transform.translation().y < -height * 0.5 {
-                transform.prepend_translation_y(height);
+            if physic.position.y < -height * 0.5 {
+                offset.y = height;
             }
-            else if transform.translation().y > height * 0.5 {
-                transform.prepend_translation_y(-height);
+            else if physic.position.y > height * 0.5 {
+                offset.y = -height;
             }
 
-            transform.set_rotation_z_axis(f32::atan2(physic.velocity.y, physic.velocity.x));
+            physic.position += offset;
+            physic.previous_position += offset;
         }
     }
 }
\ No newline at end of file
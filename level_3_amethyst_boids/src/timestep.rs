@@ -0,0 +1,45 @@
+/// Resource accumulating frame time to run the simulation at a fixed rate,
+/// whatever the frame rate.
+pub struct FixedTimestep {
+    pub step: f32,
+    pub max_steps: u32,
+    accumulator: f32
+}
+
+/// Steps per second of the simulation.
+pub const SIMULATION_RATE: f32 = 120.0;
+
+impl FixedTimestep {
+    /// Add the duration of a frame and return the number of steps to run.
+    /// Long frames run at most `max_steps`, the rest is dropped.
+    pub fn advance(&mut self, dt: f32) -> u32 {
+        self.accumulator += dt.max(0.0);
+
+        let mut steps = 0;
+        while self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            steps += 1;
+            if steps == self.max_steps {
+                self.accumulator %= self.step;
+                break;
+            }
+        }
+
+        steps
+    }
+
+    /// Between 0 and 1, how far the frame is between the last step and the next one.
+    pub fn alpha(&self) -> f32 {
+        self.accumulator / self.step
+    }
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        FixedTimestep {
+            step: 1.0 / SIMULATION_RATE,
+            max_steps: 8,
+            accumulator: 0.0
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use gdnative::prelude::*;
+
+use pong_sim::{AiController, Difficulty, PongSim, Side};
+
+/// A paddle played by the computer. The `Ball` places and scales it like a
+/// `Paddle`, and asks it for its axis instead of reading the input actions.
+#[derive(NativeClass)]
+#[inherit(Node2D)]
+pub struct AiPaddle {
+    /// easy, normal or hard.
+    #[property]
+    difficulty: String,
+    controller: Option<AiController>
+}
+
+#[methods]
+impl AiPaddle {
+    fn new(_owner: &Node2D) -> Self {
+        AiPaddle { difficulty: "normal".to_string(), controller: None }
+    }
+
+    /// Axis of the paddle of `side` for the next `dt` seconds.
+    pub fn axis(&mut self, sim: &PongSim, side: Side, dt: f32) -> f32 {
+        let difficulty = &self.difficulty;
+        let controller = self.controller.get_or_insert_with(|| {
+            let difficulty = Difficulty::from_name(difficulty).unwrap_or_else(|error| {
+                godot_warn!("{}", error);
+                Difficulty::default()
+            });
+            // a new player every game
+            let seed = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64);
+            AiController::new(side, difficulty, seed)
+        });
+        controller.update(sim, dt)
+    }
+
+    /// Forget the player, the next match seeds a new one.
+    pub fn reset(&mut self) {
+        self.controller = None;
+    }
+}
@@ -1,150 +1,355 @@
 use gdnative::prelude::*;
 
-use crate::{
-    paddle::{PADDLE_DEMI_HEIGHT, PADDLE_DEMI_WIDTH}, 
-    score_board::ScoreBoard
+use pong_sim::{
+    sound_for, ConfigFile, Event, Inputs, MatchEffects, MatchRecord, MatchStats, PongConfig, PongSim, PowerUpKind, Side, Vec2
 };
 
+use crate::ai_paddle::AiPaddle;
+use crate::flow::Rules;
+use crate::maths::{paddle_scale, player, screen_shake, to_viewport, Overrides};
+use crate::stats::save_match;
+
+/// Drives the whole match: the ball owns the simulation and places itself
+/// and both paddles from it every frame. The `sound` signal names the sound
+/// of each match event, `goal_scored` gives the player who scored and
+/// `match_over` the winner. A paddle with an `AiPaddle` script is played by
+/// the computer. It also draws the sparks and its trail, and shakes the
+/// screen on hits and goals.
 #[derive(NativeClass)]
 #[inherit(Node2D)]
+#[register_with(Self::register_signals)]
 pub struct Ball {
+    /// The paddles placed from the simulation, set in the scene.
+    #[property]
+    player_1: NodePath,
+    #[property]
+    player_2: NodePath,
+    /// The tuning below overrides the tuning file when not zero, it applies
+    /// when the match starts and when the file is reloaded.
+    #[property]
+    paddle_speed: f32,
+    /// Width and height of the paddles.
+    #[property]
+    paddle_size: Vector2,
+    /// Served toward the right player, y goes up like in the simulation.
     #[property]
-    velocity: Vector2,
-    #[property(default = 1.0)]
-    acceleration: f32,
-    player_1: Option<Ref<Node>>,
-    player_2: Option<Ref<Node>>,
-    score_board: Option<Ref<Node>>
+    ball_velocity: Vector2,
+    paddles: [Option<Ref<Node>>; 2],
+    /// Given by the `Match` node.
+    rules: Rules,
+    /// The simulation is held during the countdown of the `Match`.
+    running: bool,
+    sim: Option<PongSim>,
+    /// Of the match being played, saved with the history once it is won.
+    stats: MatchStats,
+    effects: MatchEffects,
+    config_file: Option<ConfigFile>,
+    /// Seconds since the tuning file was last checked.
+    config_elapsed: f32
 }
 
-const DEFAULT_WINDOW_HEIGHT: f32 = 600.0;
-const DEFAULT_WINDOW_WIDTH: f32 = 800.0;
-const DEFAULT_VELOCITY: Vector2 = Vector2::new(20.0, -15.0);
-const DEFAULT_ACCELERATION: f32 = 1.0;
-const BALL_RADIUS: f32 = 10.0;
+const CONFIG_PATH: &str = "res://assets/components/paddle.ron";
+/// Seconds between two checks of the tuning file.
+const CONFIG_POLL_INTERVAL: f32 = 1.0;
+
+/// Axis of a paddle played by an `AiPaddle`, `None` for the players.
+fn ai_axis(paddle: Ref<Node>, sim: &PongSim, side: Side, dt: f32) -> Option<f32> {
+    let paddle = unsafe { paddle.assume_safe() }.cast::<Node2D>()?.cast_instance::<AiPaddle>()?;
+    paddle.map_mut(|ai, _| ai.axis(sim, side, dt)).ok()
+}
+
+/// A new computer player for the next match, nothing for the players.
+fn reset_ai(paddle: Ref<Node>) {
+    let paddle = unsafe { paddle.assume_safe() }.cast::<Node2D>().and_then(|paddle| paddle.cast_instance::<AiPaddle>());
+    if let Some(paddle) = paddle {
+        let _ = paddle.map_mut(|ai, _| ai.reset());
+    }
+}
+
+/// Power-ups are drawn in their color, the paddles are tinted with the
+/// last one their player caught.
+fn power_up_color(kind: PowerUpKind) -> Color {
+    let [r, g, b, a] = kind.color();
+    Color::rgba(r, g, b, a)
+}
 
 #[methods]
 impl Ball {
+    fn register_signals(builder: &ClassBuilder<Self>) {
+        builder.add_signal(Signal {
+            name: "sound",
+            args: &[SignalArgument {
+                name: "name",
+                default: Variant::from_str(""),
+                export_info: ExportInfo::new(VariantType::GodotString),
+                usage: PropertyUsage::DEFAULT
+            }]
+        });
+        builder.add_signal(Signal {
+            name: "goal_scored",
+            args: &[SignalArgument {
+                name: "player",
+                default: Variant::from_i64(1),
+                export_info: ExportInfo::new(VariantType::I64),
+                usage: PropertyUsage::DEFAULT
+            }]
+        });
+        builder.add_signal(Signal {
+            name: "match_over",
+            args: &[SignalArgument {
+                name: "player",
+                default: Variant::from_i64(1),
+                export_info: ExportInfo::new(VariantType::I64),
+                usage: PropertyUsage::DEFAULT
+            }]
+        });
+    }
+
     fn new(_owner: &Node2D) -> Self {
-        Ball { 
-            velocity: DEFAULT_VELOCITY,
-            acceleration: DEFAULT_ACCELERATION,
-            player_1: None,
-            player_2: None,
-            score_board: None
+        Ball {
+            player_1: NodePath::default(),
+            player_2: NodePath::default(),
+            paddle_speed: 0.0,
+            paddle_size: Vector2::new(0.0, 0.0),
+            ball_velocity: Vector2::new(0.0, 0.0),
+            paddles: [None, None],
+            rules: Rules::default(),
+            running: true,
+            sim: None,
+            stats: MatchStats::default(),
+            effects: MatchEffects::default(),
+            config_file: None,
+            config_elapsed: 0.0
         }
     }
 
     #[export]
     fn _ready(&mut self, owner: &Node2D) {
-        // place la balle au milieu de la fenêtre
-        let viewport_size = match owner.get_viewport() {
-            Some(viewport) => unsafe { viewport.assume_unique().size() },
-            None => Vector2::new(DEFAULT_WINDOW_WIDTH, DEFAULT_WINDOW_HEIGHT)
-        };
-        owner.set_global_position(viewport_size / 2.0);
+        let viewport_size = owner.get_viewport_rect().size;
 
-        self.player_1 = owner.get_node(NodePath::from_str("../Player_1"));
-        self.player_2 = owner.get_node(NodePath::from_str("../Player_2"));
-        self.score_board = owner.get_node(NodePath::from_str("/root/Level/ScoreBoard"));
-        godot_print!("{:?}", self.score_board);
+        self.paddles = [owner.get_node(self.player_1.new_ref()), owner.get_node(self.player_2.new_ref())];
+        if self.paddles.iter().any(Option::is_none) {
+            godot_warn!("Set player_1 and player_2 to the paddles of the Ball");
+        }
 
-        self.velocity = owner.get_meta("starting_velocity").to_vector2();
+        // the tuning is in the simulation frame, with y going up
+        let path = ProjectSettings::godot_singleton().globalize_path(CONFIG_PATH);
+        let mut config_file = ConfigFile::new(path.to_string());
+        let (mut config, error) = config_file.load();
+        if let Some(error) = error {
+            godot_warn!("{}, using the default tuning", error);
+        }
+        // the arena is always the whole window
+        config.arena_width = viewport_size.width;
+        config.arena_height = viewport_size.height;
+        self.apply_properties(&mut config);
+
+        self.sim = Some(PongSim::new(config));
+        self.config_file = Some(config_file);
+        self.place_nodes(owner);
 
         godot_print!("Ball ready!");
     }
 
     #[export]
     fn _process(&mut self, owner: &Node2D, dt: f32) {
-        let viewport_size = match owner.get_viewport() {
-            Some(viewport) => unsafe { viewport.assume_safe().size() },
-            None => Vector2::new(DEFAULT_WINDOW_WIDTH, DEFAULT_WINDOW_HEIGHT)
+        let input = Input::godot_singleton();
+        // analog with a gamepad stick, the `Controls` node maps the actions
+        let axis = |player: i32| {
+            let up = input.get_action_strength(format!("player_{}_up", player));
+            let down = input.get_action_strength(format!("player_{}_down", player));
+            (up - down) as f32
+        };
+        let mut inputs = Inputs { left: axis(1), right: axis(2) };
+
+        self.reload_config(dt);
+
+        let sim = match self.sim.as_mut() {
+            Some(sim) => sim,
+            None => return
+        };
+        // held during the countdown, the sparks, the shake and the nodes
+        // still follow the frames
+        let events = if self.running {
+            for (paddle, side) in self.paddles.iter().zip([Side::Left, Side::Right].iter()) {
+                if let Some(axis) = paddle.and_then(|paddle| ai_axis(paddle, sim, *side, dt)) {
+                    inputs.set(*side, axis);
+                }
+            }
+            let events = sim.step(dt, inputs);
+            self.stats.record(sim, &events);
+            events
+        }
+        else {
+            Vec::new()
+        };
+        self.effects.handle(sim, &events);
+        self.effects.update(sim, dt);
+        for event in events {
+            if let Event::MatchOver(winner) = event {
+                if let Some(record) = MatchRecord::new(sim, std::mem::take(&mut self.stats)) {
+                    save_match(record);
+                }
+                owner.emit_signal("match_over", &[Variant::from_i64(player(winner))]);
+            }
+            if let Event::Goal(side) = event {
+                owner.emit_signal("goal_scored", &[Variant::from_i64(player(side))]);
+            }
+            // both players are on this computer
+            if let Some(sound) = sound_for(&event, None) {
+                owner.emit_signal("sound", &[Variant::from_str(sound.name())]);
+            }
+        }
+
+        self.place_nodes(owner);
+        self.shake_screen(owner);
+        owner.update();
+    }
+
+    /// The power-ups and the extra balls of a multi-ball, in the frame of the ball.
+    #[export]
+    fn _draw(&self, owner: &Node2D) {
+        let sim = match self.sim.as_ref() {
+            Some(sim) => sim,
+            None => return
         };
+        let scale = owner.global_scale().x;
+        // under the ball, smaller and fainter toward the end
+        for (position, weight) in self.effects.trail.points() {
+            let center = owner.to_local(self.to_viewport(position));
+            let radius = sim.config.ball_radius * weight / scale;
+            owner.draw_circle(center, radius as f64, Color::rgba(1.0, 1.0, 1.0, weight * 0.4));
+        }
+        for particle in self.effects.sparks.particles() {
+            let center = owner.to_local(self.to_viewport(particle.position));
+            let [r, g, b, a] = self.effects.sparks.color(particle);
+            owner.draw_circle(center, (particle.size / 2.0 / scale) as f64, Color::rgba(r, g, b, a));
+        }
+        for power_up in sim.power_ups.iter() {
+            let center = owner.to_local(self.to_viewport(power_up.position));
+            owner.draw_circle(center, (sim.config.power_up_radius / scale) as f64, power_up_color(power_up.kind));
+        }
+        for ball in sim.extra_balls.iter() {
+            let center = owner.to_local(self.to_viewport(ball.position));
+            owner.draw_circle(center, (sim.config.ball_radius / scale) as f64, Color::rgb(1.0, 1.0, 1.0));
+        }
+    }
 
-        // mouvement naturel de la balle
-        let mut position = owner.global_position();
-        position += self.velocity * dt;
+    /// Stop or resume the simulation.
+    pub fn set_running(&mut self, running: bool) {
+        self.running = running;
+    }
 
-        if position.y > viewport_size.y - BALL_RADIUS {
-            self.velocity.y = -self.velocity.y;
-            position.y += viewport_size.y - BALL_RADIUS - position.y 
+    /// Play by the rules of the `Match`, from now on.
+    pub fn set_rules(&mut self, rules: Rules) {
+        self.rules = rules;
+        if let Some(sim) = self.sim.as_mut() {
+            let mut config = sim.config.clone();
+            rules.apply(&mut config);
+            sim.set_config(config);
         }
+    }
 
-        if position.y < BALL_RADIUS {
-            self.velocity.y = -self.velocity.y;
-            position.y += BALL_RADIUS - position.y
+    /// A new match with the same tuning, held until `set_running`.
+    pub fn restart(&mut self, owner: &Node2D) {
+        if let Some(sim) = self.sim.as_mut() {
+            sim.restart();
         }
+        for paddle in self.paddles.iter().flatten() {
+            reset_ai(*paddle);
+        }
+        self.stats = MatchStats::default();
+        self.effects = MatchEffects::default();
+        self.running = false;
+        self.place_nodes(owner);
+    }
+
+    /// Move the whole canvas by the shake, y goes down on screen.
+    fn shake_screen(&self, owner: &Node2D) {
+        let viewport = match owner.get_viewport() {
+            Some(viewport) => unsafe { viewport.assume_safe() },
+            None => return
+        };
+        let (offset, angle) = self.effects.shake.offset();
+        let (offset, angle) = screen_shake(offset, angle);
+        let transform = Transform2D::rotation(Angle::radians(angle))
+            .then_translate(Vector2::new(offset.x, offset.y));
+        viewport.set_canvas_transform(transform);
+    }
+
+    fn to_viewport(&self, position: Vec2) -> Vector2 {
+        let position = match self.sim.as_ref() {
+            Some(sim) => to_viewport(&sim.config, position),
+            None => position
+        };
+        Vector2::new(position.x, position.y)
+    }
 
-        // balle qui sort à droite ou à gauche
-        if position.x > viewport_size.x + BALL_RADIUS {
-            self.velocity = owner.get_meta("starting_velocity").to_vector2();
-            self.velocity.x *= -1.0;
-            position = viewport_size / 2.0;
-
-            unsafe { 
-                // let score_board = self.score_board.expect("bla bal");
-                // let score_board = score_board.assume_safe().cast_instance::<ScoreBoard>().expect("bla bli");
-                // score_board.map_mut(|x, o| x.score(&*o, 1)).unwrap();
-                let score_board = owner.get_node_as_instance::<ScoreBoard>("/root/Level/ScoreBoard");
-                godot_print!("scoreboard: {:?}", score_board);
-                // owner.get_node_as_instance::<ScoreBoard>("/root/Level/ScoreBoard").expect("Can not get the scoreboard")
-                //     .map_mut(|x, o| x.score(&*o, 1))
-                //     .ok()
-                //     .unwrap_or_else(|| godot_error!("Unable to get scoreBoard"));
-            };
-        } 
-        if position.x < -BALL_RADIUS {
-            self.velocity = owner.get_meta("starting_velocity").to_vector2();
-            position = viewport_size / 2.0;
-
-            // unsafe { 
-            //     owner.get_node_as_instance::<ScoreBoard>("ScoreBoard").unwrap()
-            //         .map_mut(|x, o| x.score(&*o, 2))
-            //         .ok()
-            //         .unwrap_or_else(|| godot_error!("Unable to get scoreBoard"));
-            // };
+    /// The properties set in the scene and the rules of the `Match` win over
+    /// the tuning file.
+    fn apply_properties(&self, config: &mut PongConfig) {
+        self.rules.apply(config);
+        let overrides = Overrides {
+            paddle_speed: self.paddle_speed,
+            paddle_size: Vec2::new(self.paddle_size.x, self.paddle_size.y),
+            ball_velocity: Vec2::new(self.ball_velocity.x, self.ball_velocity.y)
+        };
+        overrides.apply(config);
+    }
+
+    /// Apply the tuning file to the running match when it is saved.
+    fn reload_config(&mut self, dt: f32) {
+        self.config_elapsed += dt;
+        if self.config_elapsed < CONFIG_POLL_INTERVAL {
+            return;
+        }
+        self.config_elapsed = 0.0;
+
+        let file = match self.config_file.as_mut() {
+            Some(file) => file,
+            None => return
+        };
+        let mut config = match file.reload() {
+            Some(Ok(config)) => config,
+            Some(Err(error)) => {
+                godot_warn!("{}", error);
+                return;
+            }
+            None => return
+        };
+        godot_print!("Reloaded {}", file.path().display());
+
+        self.apply_properties(&mut config);
+        if let Some(sim) = self.sim.as_mut() {
+            config.arena_width = sim.config.arena_width;
+            config.arena_height = sim.config.arena_height;
+            sim.set_config(config);
         }
+    }
+
+    fn place_nodes(&self, owner: &Node2D) {
+        let sim = match self.sim.as_ref() {
+            Some(sim) => sim,
+            None => return
+        };
+        owner.set_global_position(self.to_viewport(sim.ball.position));
 
-        // rebond sur les palettes
-        let players = [self.player_1, self.player_2];
-        for player in &players {
+        let players = [(self.paddles[0], Side::Left), (self.paddles[1], Side::Right)];
+        for (player, side) in &players {
             if let Some(node) = player {
                 match unsafe { node.assume_safe().cast::<Node2D>() } {
                     Some(player) => {
-                        let paddle_position = player.global_position();
-                        if point_in_rect(
-                            position.x, position.y,
-                            paddle_position.x - PADDLE_DEMI_WIDTH - BALL_RADIUS,
-                            paddle_position.y - PADDLE_DEMI_HEIGHT - BALL_RADIUS,
-                            paddle_position.x + PADDLE_DEMI_WIDTH + BALL_RADIUS,
-                            paddle_position.y + PADDLE_DEMI_HEIGHT + BALL_RADIUS
-                        ) {
-                            if self.velocity.x < 0.0 {
-                                position.x = position.x + (paddle_position.x + PADDLE_DEMI_WIDTH + BALL_RADIUS - position.x) * 2.0;
-                            } 
-                            else {
-                                position.x = position.x + (paddle_position.x - PADDLE_DEMI_WIDTH - BALL_RADIUS - position.x) * 2.0;
-                            }
-                            self.velocity.x = -self.velocity.x;
-                            
-                            self.velocity *= self.acceleration;
-                        }
-                    }
-                    None => {
-                        godot_error!("Can not have a reference to the paddle");
+                        player.set_global_position(self.to_viewport(sim.paddle(*side).position));
+                        let scale = paddle_scale(sim, *side, player.scale().x);
+                        player.set_scale(Vector2::new(scale.x, scale.y));
+                        let tint = sim.effects.iter().rev()
+                            .find(|effect| effect.side == *side)
+                            .map_or(Color::rgb(1.0, 1.0, 1.0), |effect| power_up_color(effect.kind));
+                        player.set_modulate(tint);
                     }
+                    None => godot_error!("Can not have a reference to the paddle")
                 }
             }
         }
-
-        
-
-        
-        owner.set_global_position(position);
-        // godot_print!("{:?}", owner.scale());
     }
 }
-
-fn point_in_rect(x: f32, y: f32, left: f32, bottom: f32, right: f32, top: f32) -> bool {
-    x >= left && x <= right && y >= bottom && y <= top
-}
\ No newline at end of file
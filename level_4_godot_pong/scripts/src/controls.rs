@@ -0,0 +1,239 @@
+use gdnative::api::{GlobalConstants, InputEventJoypadButton, InputEventJoypadMotion, InputEventKey, OS};
+use gdnative::prelude::*;
+
+use pong_sim::{Action, BindingMenu, Bindings, Control, Side};
+
+/// Saved in the user data dir, the project settings are read only once exported.
+const BINDINGS_PATH: &str = "user://bindings.ron";
+
+/// How far a stick must be pushed to be bound.
+const CAPTURE_THRESHOLD: f64 = 0.5;
+
+const SIDES: [(Side, i32); 2] = [(Side::Left, 1), (Side::Right, 2)];
+
+/// Keeps the `player_{n}_up` and `player_{n}_down` actions in sync with the
+/// saved bindings, and shows the rebinding menu on F1 or the start button.
+/// The tree is paused while the menu is open.
+#[derive(NativeClass)]
+#[inherit(Node)]
+pub struct Controls {
+    bindings: Bindings,
+    menu: BindingMenu,
+    label: Option<Ref<Label>>
+}
+
+/// The keyboard layout of the game, and the left stick of the first two
+/// gamepads. Godot sticks go down towards positive values.
+fn default_bindings() -> Bindings {
+    let mut bindings = Bindings::default();
+    for &(gamepad, side) in [(0, Side::Left), (1, Side::Right)].iter() {
+        let stick = |positive| Control::Axis { gamepad, axis: "Left Stick Y".to_string(), positive };
+        bindings.bind(side, Action::Up, stick(false));
+        bindings.bind(side, Action::Down, stick(true));
+    }
+    bindings
+}
+
+fn bindings_path() -> String {
+    ProjectSettings::godot_singleton().globalize_path(BINDINGS_PATH).to_string()
+}
+
+/// The Godot input event of a control, `None` when Godot does not know its name.
+fn input_event(control: &Control) -> Option<Ref<InputEvent, Shared>> {
+    let input = Input::godot_singleton();
+    match control {
+        Control::Key(name) => {
+            let scancode = OS::godot_singleton().find_scancode_from_string(name.as_str());
+            if scancode == 0 {
+                return None;
+            }
+            let event = InputEventKey::new();
+            event.set_scancode(scancode);
+            Some(event.upcast::<InputEvent>().into_shared())
+        }
+        Control::Button { gamepad, button } => {
+            let index = input.get_joy_button_index_from_string(button.as_str());
+            if index < 0 {
+                return None;
+            }
+            let event = InputEventJoypadButton::new();
+            event.set_device(*gamepad as i64);
+            event.set_button_index(index);
+            Some(event.upcast::<InputEvent>().into_shared())
+        }
+        Control::Axis { gamepad, axis, positive } => {
+            let index = input.get_joy_axis_index_from_string(axis.as_str());
+            if index < 0 {
+                return None;
+            }
+            let event = InputEventJoypadMotion::new();
+            event.set_device(*gamepad as i64);
+            event.set_axis(index);
+            event.set_axis_value(if *positive { 1.0 } else { -1.0 });
+            Some(event.upcast::<InputEvent>().into_shared())
+        }
+    }
+}
+
+/// The control pressed by an input event, to be bound.
+fn pressed_control(event: &InputEvent) -> Option<Control> {
+    if let Some(key) = event.cast::<InputEventKey>() {
+        if key.is_pressed() && !key.is_echo() {
+            let name = OS::godot_singleton().get_scancode_string(key.scancode());
+            return Some(Control::Key(name.to_string()));
+        }
+    }
+    else if let Some(button) = event.cast::<InputEventJoypadButton>() {
+        if button.is_pressed() {
+            let name = Input::godot_singleton().get_joy_button_string(button.button_index());
+            return Some(Control::Button { gamepad: button.device() as u32, button: name.to_string() });
+        }
+    }
+    else if let Some(motion) = event.cast::<InputEventJoypadMotion>() {
+        let value = motion.axis_value();
+        if value.abs() > CAPTURE_THRESHOLD {
+            let name = Input::godot_singleton().get_joy_axis_string(motion.axis());
+            return Some(Control::Axis { gamepad: motion.device() as u32, axis: name.to_string(), positive: value > 0.0 });
+        }
+    }
+    None
+}
+
+pub fn key_pressed(event: &InputEvent, scancode: i64) -> bool {
+    event.cast::<InputEventKey>()
+        .map_or(false, |key| key.is_pressed() && !key.is_echo() && key.scancode() == scancode)
+}
+
+fn button_pressed(event: &InputEvent, index: i64) -> bool {
+    event.cast::<InputEventJoypadButton>()
+        .map_or(false, |button| button.is_pressed() && button.button_index() == index)
+}
+
+#[methods]
+impl Controls {
+    fn new(_owner: &Node) -> Self {
+        Controls { bindings: default_bindings(), menu: BindingMenu::default(), label: None }
+    }
+
+    #[export]
+    fn _ready(&mut self, owner: &Node) {
+        // the menu still gets the inputs while the game is paused
+        owner.set_pause_mode(Node::PAUSE_MODE_PROCESS);
+
+        let path = bindings_path();
+        if std::path::Path::new(&path).exists() {
+            match Bindings::load(&path) {
+                Ok(bindings) => self.bindings = bindings,
+                Err(error) => godot_warn!("{}, using the default bindings", error)
+            }
+        }
+        self.apply();
+
+        let label = Label::new();
+        label.set_position(Vector2::new(20.0, 140.0), false);
+        label.set_visible(false);
+        let label = label.into_shared();
+        owner.add_child(label, false);
+        self.label = Some(label);
+    }
+
+    #[export]
+    fn _input(&mut self, owner: &Node, event: Ref<InputEvent>) {
+        let event = unsafe { event.assume_safe() };
+        let open = self.is_open();
+
+        if !open {
+            // the stats screen is open
+            let paused = owner.get_tree().map_or(false, |tree| unsafe { tree.assume_safe().is_paused() });
+            if paused {
+                return;
+            }
+            if key_pressed(&event, GlobalConstants::KEY_F1) || button_pressed(&event, GlobalConstants::JOY_START) {
+                self.set_open(owner, true);
+            }
+            return;
+        }
+
+        let mut changed = false;
+        if self.menu.is_waiting() {
+            if key_pressed(&event, GlobalConstants::KEY_ESCAPE) {
+                self.menu.cancel();
+            }
+            else if let Some(control) = pressed_control(&event) {
+                changed = self.menu.capture(control, &mut self.bindings);
+            }
+        }
+        else if key_pressed(&event, GlobalConstants::KEY_UP) || button_pressed(&event, GlobalConstants::JOY_DPAD_UP) {
+            self.menu.previous();
+        }
+        else if key_pressed(&event, GlobalConstants::KEY_DOWN) || button_pressed(&event, GlobalConstants::JOY_DPAD_DOWN) {
+            self.menu.next();
+        }
+        else if key_pressed(&event, GlobalConstants::KEY_ENTER) || button_pressed(&event, GlobalConstants::JOY_BUTTON_0) {
+            changed = self.menu.activate(&mut self.bindings, &default_bindings());
+        }
+        else if key_pressed(&event, GlobalConstants::KEY_ESCAPE)
+            || key_pressed(&event, GlobalConstants::KEY_F1)
+            || button_pressed(&event, GlobalConstants::JOY_BUTTON_1)
+        {
+            self.set_open(owner, false);
+            return;
+        }
+
+        if changed {
+            self.apply();
+            if let Err(error) = self.bindings.save(bindings_path()) {
+                godot_warn!("{}", error);
+            }
+        }
+        self.refresh();
+    }
+
+    /// Replace the events of the player actions with the bound controls.
+    fn apply(&self) {
+        let input_map = InputMap::godot_singleton();
+        for &(side, player) in SIDES.iter() {
+            let player_bindings = self.bindings.get(side);
+            for (direction, controls) in [("up", &player_bindings.up), ("down", &player_bindings.down)].iter() {
+                let action = format!("player_{}_{}", player, direction);
+                if !input_map.has_action(action.as_str()) {
+                    input_map.add_action(action.as_str(), self.bindings.deadzone as f64);
+                }
+                input_map.action_set_deadzone(action.as_str(), self.bindings.deadzone as f64);
+                input_map.action_erase_events(action.as_str());
+                for control in controls.iter() {
+                    match input_event(control) {
+                        Some(event) => input_map.action_add_event(action.as_str(), event),
+                        None => godot_warn!("Godot does not know the control {}", control)
+                    }
+                }
+            }
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        self.label.map_or(false, |label| unsafe { label.assume_safe().is_visible() })
+    }
+
+    fn set_open(&mut self, owner: &Node, open: bool) {
+        if let Some(tree) = owner.get_tree() {
+            unsafe { tree.assume_safe().set_pause(open) };
+        }
+        if let Some(label) = self.label {
+            unsafe { label.assume_safe().set_visible(open) };
+        }
+        self.menu.cancel();
+        self.refresh();
+    }
+
+    fn refresh(&self) {
+        let label = match self.label {
+            Some(label) => label,
+            None => return
+        };
+        let mut lines = vec!["CONTROLS".to_string()];
+        lines.extend(self.menu.lines(&self.bindings));
+        lines.push("enter to change, escape to go back".to_string());
+        unsafe { label.assume_safe().set_text(lines.join("\n")) };
+    }
+}
@@ -0,0 +1,150 @@
+//! Flow of a match around the simulation: a countdown, the match, and the
+//! winner until the next one. Free of Godot types like the maths.
+
+use pong_sim::{PongConfig, Side};
+
+/// Rules set on the `Match` node, a zero keeps the one of the tuning file.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Rules {
+    pub points_to_win: u32,
+    pub win_by: u32,
+    /// Seconds the ball waits before each serve.
+    pub serve_delay: f32
+}
+
+impl Rules {
+    pub fn apply(&self, config: &mut PongConfig) {
+        if self.points_to_win != 0 {
+            config.points_to_win = self.points_to_win;
+        }
+        if self.win_by != 0 {
+            config.win_by = self.win_by;
+        }
+        if self.serve_delay != 0.0 {
+            config.serve_delay = self.serve_delay;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    /// Seconds left before the match starts.
+    Countdown(f32),
+    Playing,
+    Over(Side)
+}
+
+/// What the `Match` node signals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlowEvent {
+    /// Whole seconds left, once for each.
+    Countdown(u32),
+    Started
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchFlow {
+    /// Seconds of the countdown, 0 to start at once.
+    pub start_delay: f32,
+    phase: Phase,
+    /// The last second given by a `FlowEvent::Countdown`.
+    shown: Option<u32>
+}
+
+impl MatchFlow {
+    pub fn new(start_delay: f32) -> MatchFlow {
+        MatchFlow { start_delay, phase: Phase::Countdown(start_delay), shown: None }
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    /// Whether the simulation runs.
+    pub fn is_playing(&self) -> bool {
+        self.phase == Phase::Playing
+    }
+
+    pub fn update(&mut self, dt: f32) -> Vec<FlowEvent> {
+        let mut events = Vec::new();
+        if let Phase::Countdown(left) = self.phase {
+            let left = left - dt;
+            if left <= 0.0 {
+                self.phase = Phase::Playing;
+                events.push(FlowEvent::Started);
+            }
+            else {
+                self.phase = Phase::Countdown(left);
+                let seconds = left.ceil() as u32;
+                if self.shown != Some(seconds) {
+                    self.shown = Some(seconds);
+                    events.push(FlowEvent::Countdown(seconds));
+                }
+            }
+        }
+        events
+    }
+
+    pub fn match_over(&mut self, winner: Side) {
+        self.phase = Phase::Over(winner);
+    }
+
+    /// Count down to a new match.
+    pub fn restart(&mut self) {
+        *self = MatchFlow::new(self.start_delay);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_rules_keep_the_tuning() {
+        let mut config = PongConfig::default();
+        Rules::default().apply(&mut config);
+        assert_eq!(config, PongConfig::default());
+
+        Rules { points_to_win: 5, win_by: 0, serve_delay: 1.5 }.apply(&mut config);
+        assert_eq!(config.points_to_win, 5);
+        assert_eq!(config.win_by, PongConfig::default().win_by);
+        assert_eq!(config.serve_delay, 1.5);
+    }
+
+    #[test]
+    fn counts_down_each_second_then_starts() {
+        let mut flow = MatchFlow::new(3.0);
+        let mut events = Vec::new();
+        for _ in 0..40 {
+            events.extend(flow.update(0.1));
+        }
+        assert_eq!(events, vec![
+            FlowEvent::Countdown(3),
+            FlowEvent::Countdown(2),
+            FlowEvent::Countdown(1),
+            FlowEvent::Started
+        ]);
+        assert!(flow.is_playing());
+        assert!(flow.update(0.1).is_empty());
+    }
+
+    #[test]
+    fn starts_at_once_without_delay() {
+        let mut flow = MatchFlow::new(0.0);
+        assert!(!flow.is_playing());
+        assert_eq!(flow.update(0.016), vec![FlowEvent::Started]);
+    }
+
+    #[test]
+    fn restarts_after_the_winner() {
+        let mut flow = MatchFlow::new(1.0);
+        flow.update(1.0);
+        flow.match_over(Side::Right);
+        assert_eq!(flow.phase(), Phase::Over(Side::Right));
+        assert!(flow.update(1.0).is_empty());
+
+        flow.restart();
+        assert_eq!(flow.phase(), Phase::Countdown(1.0));
+        assert_eq!(flow.update(0.5), vec![FlowEvent::Countdown(1)]);
+    }
+}
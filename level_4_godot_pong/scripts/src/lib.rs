@@ -1,17 +1,34 @@
+mod ai_paddle;
 mod ball;
+mod controls;
+mod flow;
+mod maths;
 mod paddle;
+mod pong_match;
 mod score_board;
+mod sounds;
+mod stats;
 
 use gdnative::prelude::*;
 
+use crate::ai_paddle::AiPaddle;
 use crate::ball::Ball;
+use crate::controls::Controls;
 use crate::paddle::Paddle;
+use crate::pong_match::Match;
 use crate::score_board::ScoreBoard;
+use crate::sounds::Sounds;
+use crate::stats::StatsScreen;
 
 fn init(handle: InitHandle) {
+    handle.add_class::<AiPaddle>();
     handle.add_class::<Ball>();
+    handle.add_class::<Controls>();
+    handle.add_class::<Match>();
     handle.add_class::<Paddle>();
     handle.add_class::<ScoreBoard>();
+    handle.add_class::<Sounds>();
+    handle.add_class::<StatsScreen>();
 }
 
 godot_init!(init);
@@ -0,0 +1,145 @@
+//! The maths between the simulation and the nodes, kept free of Godot types
+//! so `cargo test` runs it without the engine.
+
+use pong_sim::{PongConfig, PongSim, Side, Vec2};
+
+/// Godot volumes are in decibels, this is as good as silent.
+pub const SILENT_DB: f32 = -80.0;
+
+/// Number of the player of a side, as in the scene and the input actions.
+pub fn player(side: Side) -> i64 {
+    match side {
+        Side::Left => 1,
+        Side::Right => 2
+    }
+}
+
+/// Side of a player number, `None` for anything but 1 and 2.
+pub fn side(player: i64) -> Option<Side> {
+    match player {
+        1 => Some(Side::Left),
+        2 => Some(Side::Right),
+        _ => None
+    }
+}
+
+/// From the simulation, centered with y going up, to the viewport, with the
+/// origin in the top left corner and y going down.
+pub fn to_viewport(config: &PongConfig, position: Vec2) -> Vec2 {
+    Vec2::new(position.x + config.arena_width / 2.0, config.arena_height / 2.0 - position.y)
+}
+
+/// The other way around, from the viewport to the simulation.
+pub fn from_viewport(config: &PongConfig, position: Vec2) -> Vec2 {
+    Vec2::new(position.x - config.arena_width / 2.0, config.arena_height / 2.0 - position.y)
+}
+
+/// Scale of a paddle node grown or shrunk by the power-ups, the scene scales
+/// the paddles evenly by `scale`.
+pub fn paddle_scale(sim: &PongSim, side: Side, scale: f32) -> Vec2 {
+    Vec2::new(scale, scale * sim.paddle_height(side) / sim.config.paddle_height)
+}
+
+/// Offset and angle of the canvas for a shake given in the simulation frame.
+pub fn screen_shake(offset: Vec2, angle: f32) -> (Vec2, f32) {
+    (Vec2::new(offset.x, -offset.y), -angle)
+}
+
+/// Tuning set on the nodes, a zero keeps the one of the tuning file.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Overrides {
+    pub paddle_speed: f32,
+    /// Width and height.
+    pub paddle_size: Vec2,
+    /// Served toward the right player, y goes up like in the simulation.
+    pub ball_velocity: Vec2
+}
+
+impl Overrides {
+    pub fn apply(&self, config: &mut PongConfig) {
+        if self.paddle_speed != 0.0 {
+            config.paddle_speed = self.paddle_speed;
+        }
+        if self.paddle_size.x != 0.0 && self.paddle_size.y != 0.0 {
+            config.paddle_width = self.paddle_size.x;
+            config.paddle_height = self.paddle_size.y;
+        }
+        if self.ball_velocity != Vec2::ZERO {
+            config.ball_velocity = self.ball_velocity;
+        }
+    }
+}
+
+/// A volume between 0 and 1 in decibels.
+pub fn to_db(volume: f32) -> f32 {
+    if volume <= 0.0 {
+        return SILENT_DB;
+    }
+    (20.0 * volume.log10()).max(SILENT_DB)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> PongConfig {
+        PongConfig { arena_width: 800.0, arena_height: 600.0, ..PongConfig::default() }
+    }
+
+    #[test]
+    fn players_and_sides() {
+        for side in [Side::Left, Side::Right].iter() {
+            assert_eq!(super::side(player(*side)), Some(*side));
+        }
+        assert_eq!(super::side(0), None);
+        assert_eq!(super::side(3), None);
+    }
+
+    #[test]
+    fn viewport_is_flipped_from_the_top_left() {
+        let config = config();
+        assert_eq!(to_viewport(&config, Vec2::ZERO), Vec2::new(400.0, 300.0));
+        assert_eq!(to_viewport(&config, Vec2::new(-400.0, 300.0)), Vec2::new(0.0, 0.0));
+        assert_eq!(to_viewport(&config, Vec2::new(400.0, -300.0)), Vec2::new(800.0, 600.0));
+
+        let position = Vec2::new(-123.0, 45.0);
+        assert_eq!(from_viewport(&config, to_viewport(&config, position)), position);
+    }
+
+    #[test]
+    fn paddles_are_scaled_with_their_height() {
+        let sim = PongSim::new(config());
+        assert_eq!(paddle_scale(&sim, Side::Left, 5.0), Vec2::new(5.0, 5.0));
+    }
+
+    #[test]
+    fn shake_goes_down_on_screen() {
+        assert_eq!(screen_shake(Vec2::new(3.0, 4.0), 0.1), (Vec2::new(3.0, -4.0), -0.1));
+    }
+
+    #[test]
+    fn zero_overrides_keep_the_tuning() {
+        let mut config = config();
+        Overrides::default().apply(&mut config);
+        assert_eq!(config, self::config());
+
+        let overrides = Overrides {
+            paddle_speed: 300.0,
+            paddle_size: Vec2::new(10.0, 0.0),
+            ball_velocity: Vec2::new(100.0, 50.0)
+        };
+        overrides.apply(&mut config);
+        assert_eq!(config.paddle_speed, 300.0);
+        // both sizes or none
+        assert_eq!(config.paddle_width, self::config().paddle_width);
+        assert_eq!(config.ball_velocity, Vec2::new(100.0, 50.0));
+    }
+
+    #[test]
+    fn volumes_in_decibels() {
+        assert_eq!(to_db(1.0), 0.0);
+        assert!((to_db(0.5) + 6.02).abs() < 0.01);
+        assert_eq!(to_db(0.0), SILENT_DB);
+        assert_eq!(to_db(0.000_001), SILENT_DB);
+    }
+}
@@ -0,0 +1,149 @@
+use gdnative::prelude::*;
+
+use crate::ball::Ball;
+use crate::flow::{FlowEvent, MatchFlow, Phase, Rules};
+use crate::maths::side;
+
+/// Rules and flow of the matches played by the `Ball`: a countdown before
+/// each match, then the winner until enter is pressed. The `countdown`,
+/// `match_started` and `match_won` signals are for the UI.
+#[derive(NativeClass)]
+#[inherit(Node)]
+#[register_with(Self::register_signals)]
+pub struct Match {
+    /// The `Ball` playing the matches, set in the scene.
+    #[property]
+    ball: NodePath,
+    /// Seconds of the countdown, 0 to start at once.
+    #[property(default = 3.0)]
+    start_delay: f32,
+    /// The rules below override the tuning file when not zero.
+    #[property]
+    points_to_win: i64,
+    #[property]
+    win_by: i64,
+    /// Seconds the ball waits before each serve.
+    #[property]
+    serve_delay: f32,
+    flow: MatchFlow,
+    ball_node: Option<Ref<Node>>,
+    label: Option<Ref<Label>>
+}
+
+#[methods]
+impl Match {
+    fn register_signals(builder: &ClassBuilder<Self>) {
+        builder.add_signal(Signal {
+            name: "countdown",
+            args: &[SignalArgument {
+                name: "seconds",
+                default: Variant::from_i64(0),
+                export_info: ExportInfo::new(VariantType::I64),
+                usage: PropertyUsage::DEFAULT
+            }]
+        });
+        builder.add_signal(Signal { name: "match_started", args: &[] });
+        builder.add_signal(Signal {
+            name: "match_won",
+            args: &[SignalArgument {
+                name: "player",
+                default: Variant::from_i64(1),
+                export_info: ExportInfo::new(VariantType::I64),
+                usage: PropertyUsage::DEFAULT
+            }]
+        });
+    }
+
+    fn new(_owner: &Node) -> Self {
+        Match {
+            ball: NodePath::default(),
+            start_delay: 3.0,
+            points_to_win: 0,
+            win_by: 0,
+            serve_delay: 0.0,
+            flow: MatchFlow::new(0.0),
+            ball_node: None,
+            label: None
+        }
+    }
+
+    #[export]
+    fn _ready(&mut self, owner: &Node) {
+        self.ball_node = owner.get_node(self.ball.new_ref());
+
+        let label = Label::new();
+        label.set_position(Vector2::new(380.0, 260.0), false);
+        let label = label.into_shared();
+        owner.add_child(label, false);
+        self.label = Some(label);
+
+        let rules = Rules {
+            points_to_win: self.points_to_win.max(0) as u32,
+            win_by: self.win_by.max(0) as u32,
+            serve_delay: self.serve_delay.max(0.0)
+        };
+        self.with_ball(|ball, _| {
+            ball.set_rules(rules);
+            ball.set_running(false);
+        });
+        self.flow = MatchFlow::new(self.start_delay);
+    }
+
+    #[export]
+    fn _process(&mut self, owner: &Node, dt: f32) {
+        for event in self.flow.update(dt) {
+            match event {
+                FlowEvent::Countdown(seconds) => {
+                    self.show(&format!("{}", seconds));
+                    owner.emit_signal("countdown", &[Variant::from_i64(seconds as i64)]);
+                }
+                FlowEvent::Started => {
+                    self.show("");
+                    self.with_ball(|ball, _| ball.set_running(true));
+                    owner.emit_signal("match_started", &[]);
+                }
+            }
+        }
+
+        let over = matches!(self.flow.phase(), Phase::Over(_));
+        if over && Input::godot_singleton().is_action_just_pressed("ui_accept") {
+            self.flow.restart();
+            self.with_ball(|ball, owner| ball.restart(owner));
+        }
+    }
+
+    /// Connected to the `match_over` signal of the `Ball`, the simulation
+    /// stops by itself.
+    #[export]
+    fn on_match_over(&mut self, owner: &Node, player: i64) {
+        let winner = match side(player) {
+            Some(winner) => winner,
+            None => return
+        };
+        self.flow.match_over(winner);
+        self.show(&format!("PLAYER {} WINS\nenter to play again", player));
+        owner.emit_signal("match_won", &[Variant::from_i64(player)]);
+    }
+
+    fn show(&self, text: &str) {
+        if let Some(label) = self.label {
+            let label = unsafe { label.assume_safe() };
+            label.set_text(text);
+            label.set_visible(!text.is_empty());
+        }
+    }
+
+    fn with_ball(&self, f: impl FnOnce(&mut Ball, &Node2D)) {
+        let ball = self.ball_node
+            .and_then(|node| unsafe { node.assume_safe() }.cast::<Node2D>())
+            .and_then(|node| node.cast_instance::<Ball>());
+        match ball {
+            Some(ball) => {
+                if ball.map_mut(|ball, owner| f(ball, &*owner)).is_err() {
+                    godot_error!("Can not have a reference to the ball");
+                }
+            }
+            None => godot_warn!("Set ball to the Ball of the Match")
+        }
+    }
+}
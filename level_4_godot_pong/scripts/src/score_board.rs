@@ -1,37 +1,72 @@
-
 use gdnative::prelude::*;
 
-#[derive(NativeClass, Debug)]
+use crate::maths::side;
+
+/// Counts the goals given by the `goal_scored` signal of the `Ball`.
+#[derive(NativeClass)]
 #[user_data(user_data::MutexData<ScoreBoard>)]
 #[inherit(Node)]
 pub struct ScoreBoard {
-    player_1_score: i32,
-    player_2_score: i32
+    /// The labels showing the scores, set in the scene.
+    #[property]
+    player_1_score: NodePath,
+    #[property]
+    player_2_score: NodePath,
+    labels: [Option<Ref<Label>>; 2],
+    scores: [i32; 2]
 }
 
 #[methods]
 impl ScoreBoard {
     fn new(_owner: &Node) -> Self {
-        ScoreBoard { player_1_score: 0, player_2_score: 0 }
+        ScoreBoard {
+            player_1_score: NodePath::default(),
+            player_2_score: NodePath::default(),
+            labels: [None, None],
+            scores: [0, 0]
+        }
     }
 
     #[export]
-    pub fn score(&mut self, owner: &Node, player: i32) {
-        if player == 1 {
-            self.player_1_score += 1;
-            let text_node = owner.get_node(NodePath::from_str("../h/v_1/PlayerScore_1")).unwrap();
-            unsafe { 
-                let label = text_node.assume_safe().cast::<Label>().unwrap(); 
-                label.set_text(format!("{}", self.player_1_score));
-            }
+    fn _ready(&mut self, owner: &Node) {
+        let label = |path: &NodePath| owner.get_node(path.new_ref())
+            .and_then(|node| unsafe { node.assume_safe() }.cast::<Label>())
+            .map(|label| label.claim());
+        self.labels = [label(&self.player_1_score), label(&self.player_2_score)];
+        if self.labels.iter().any(Option::is_none) {
+            godot_warn!("Set player_1_score and player_2_score to the labels of the ScoreBoard");
         }
-        else {
-            self.player_2_score += 1;
-            let text_node = owner.get_node(NodePath::from_str("../h/v_2/PlayerScore_2")).unwrap();
-            unsafe { 
-                let label = text_node.assume_safe().cast::<Label>().unwrap(); 
-                label.set_text(format!("{}", self.player_2_score));
+        for index in 0..2 {
+            self.show(index);
+        }
+    }
+
+    /// A goal of player 1 or 2.
+    #[export]
+    pub fn score(&mut self, _owner: &Node, player: i64) {
+        let index = match side(player) {
+            Some(side) => side.index(),
+            None => {
+                godot_warn!("No player {}", player);
+                return;
             }
+        };
+        self.scores[index] += 1;
+        self.show(index);
+    }
+
+    /// Back to zero for a new match.
+    #[export]
+    pub fn reset(&mut self, _owner: &Node) {
+        self.scores = [0, 0];
+        for index in 0..2 {
+            self.show(index);
         }
     }
-}
\ No newline at end of file
+
+    fn show(&self, index: usize) {
+        if let Some(label) = self.labels[index] {
+            unsafe { label.assume_safe() }.set_text(format!("{}", self.scores[index]));
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use gdnative::api::{AudioStream, AudioStreamPlayer, GlobalConstants, ResourceLoader};
+use gdnative::prelude::*;
+
+use pong_sim::{AudioBackend, AudioPlayer, Mixer, Music, Sound};
+
+use crate::controls::key_pressed;
+use crate::maths::to_db;
+
+/// Saved in the user data dir, next to the bindings.
+const AUDIO_PATH: &str = "user://audio.ron";
+fn audio_path() -> String {
+    ProjectSettings::godot_singleton().globalize_path(AUDIO_PATH).to_string()
+}
+
+/// A stream player child of the `Sounds` node, `None` when the file can not
+/// be loaded.
+fn add_player(owner: &Node, name: &str) -> Option<Ref<AudioStreamPlayer>> {
+    let path = format!("res://assets/audio/{}.wav", name);
+    let stream = ResourceLoader::godot_singleton()
+        .load(path.as_str(), "AudioStream", false)
+        .and_then(|resource| resource.cast::<AudioStream>());
+    let stream = match stream {
+        Some(stream) => stream,
+        None => {
+            godot_warn!("Can not load {}", path);
+            return None;
+        }
+    };
+
+    let player = AudioStreamPlayer::new();
+    player.set_stream(stream);
+    let player = player.into_shared();
+    owner.add_child(player, false);
+    Some(player)
+}
+
+/// The stream players of the effects and of the tracks.
+pub struct StreamPlayers {
+    effects: Vec<(Sound, Ref<AudioStreamPlayer>)>,
+    /// In the order of `Music::ALL`.
+    music: Vec<Option<Ref<AudioStreamPlayer>>>
+}
+
+impl AudioBackend for StreamPlayers {
+    fn play(&mut self, sound: Sound, volume: f32) {
+        if let Some((_, player)) = self.effects.iter().find(|(effect, _)| *effect == sound) {
+            let player = unsafe { player.assume_safe() };
+            player.set_volume_db(to_db(volume) as f64);
+            player.play(0.0);
+        }
+    }
+
+    fn set_music_volume(&mut self, music: Music, volume: f32) {
+        let index = Music::ALL.iter().position(|track| *track == music).unwrap_or(0);
+        if let Some(Some(player)) = self.music.get(index) {
+            let player = unsafe { player.assume_safe() };
+            player.set_volume_db(to_db(volume) as f64);
+            // started over at the end of the track to loop it
+            if volume > 0.0 && !player.is_playing() {
+                player.play(0.0);
+            }
+            else if volume <= 0.0 && player.is_playing() {
+                player.stop();
+            }
+        }
+    }
+}
+
+/// Plays the sounds named by the `sound` signal of the `Ball`, and the menu
+/// music while the game is paused. M mutes everything, F5 and F6 change the
+/// volume of the effects, F7 and F8 the one of the music.
+#[derive(NativeClass)]
+#[inherit(Node)]
+pub struct Sounds {
+    player: Option<AudioPlayer<StreamPlayers>>
+}
+
+#[methods]
+impl Sounds {
+    fn new(_owner: &Node) -> Self {
+        Sounds { player: None }
+    }
+
+    #[export]
+    fn _ready(&mut self, owner: &Node) {
+        // the music fades under the rebinding menu
+        owner.set_pause_mode(Node::PAUSE_MODE_PROCESS);
+
+        let backend = StreamPlayers {
+            effects: Sound::ALL.iter()
+                .filter_map(|sound| add_player(owner, sound.name()).map(|player| (*sound, player)))
+                .collect(),
+            music: Music::ALL.iter().map(|music| add_player(owner, music.name())).collect()
+        };
+        let mut player = AudioPlayer::new(backend);
+        match Mixer::load_saved(audio_path()) {
+            Ok(mixer) => player.mixer = mixer,
+            Err(error) => godot_warn!("{}, using the default volumes", error)
+        }
+        self.player = Some(player);
+    }
+
+    #[export]
+    fn _process(&mut self, owner: &Node, dt: f32) {
+        let paused = owner.get_tree().map_or(false, |tree| unsafe { tree.assume_safe().is_paused() });
+        if let Some(player) = self.player.as_mut() {
+            let music = if paused { Music::Menu } else { Music::Game };
+            if player.music() != Some(music) {
+                player.set_music(Some(music));
+            }
+            player.update(dt);
+        }
+    }
+
+    #[export]
+    fn _input(&mut self, owner: &Node, event: Ref<InputEvent>) {
+        // the menu pauses the tree while it binds the keys
+        if owner.get_tree().map_or(false, |tree| unsafe { tree.assume_safe().is_paused() }) {
+            return;
+        }
+        let player = match self.player.as_mut() {
+            Some(player) => player,
+            None => return
+        };
+        let event = unsafe { event.assume_safe() };
+        let mixer = &mut player.mixer;
+        let step = Mixer::VOLUME_STEP;
+        if key_pressed(&event, GlobalConstants::KEY_M) {
+            mixer.master.muted = !mixer.master.muted;
+        }
+        else if key_pressed(&event, GlobalConstants::KEY_F5) {
+            mixer.effects.adjust(-step);
+        }
+        else if key_pressed(&event, GlobalConstants::KEY_F6) {
+            mixer.effects.adjust(step);
+        }
+        else if key_pressed(&event, GlobalConstants::KEY_F7) {
+            mixer.music.adjust(-step);
+        }
+        else if key_pressed(&event, GlobalConstants::KEY_F8) {
+            mixer.music.adjust(step);
+        }
+        else {
+            return;
+        }
+
+        if let Err(error) = mixer.save(audio_path()) {
+            godot_warn!("{}", error);
+        }
+    }
+
+    /// Connected to the `sound` signal of the `Ball`.
+    #[export]
+    fn play(&mut self, _owner: &Node, name: String) {
+        match (self.player.as_mut(), Sound::from_name(&name)) {
+            (Some(player), Ok(sound)) => player.play(sound),
+            (_, Err(error)) => godot_warn!("{}", error),
+            (None, _) => {}
+        }
+    }
+}
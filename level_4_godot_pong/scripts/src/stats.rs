@@ -0,0 +1,95 @@
+use gdnative::api::GlobalConstants;
+use gdnative::prelude::*;
+
+use pong_sim::{History, MatchRecord};
+
+use crate::controls::key_pressed;
+
+/// Saved in the user data dir, next to the bindings.
+const HISTORY_PATH: &str = "user://history.ron";
+
+fn history_path() -> String {
+    ProjectSettings::godot_singleton().globalize_path(HISTORY_PATH).to_string()
+}
+
+/// The saved history, or an empty one.
+fn load_history() -> History {
+    let path = history_path();
+    if !std::path::Path::new(&path).exists() {
+        return History::default();
+    }
+    History::load(&path).unwrap_or_else(|error| {
+        godot_warn!("{}, starting a new history", error);
+        History::default()
+    })
+}
+
+/// Add a match to the saved history, the `Ball` calls it once a match is won.
+pub fn save_match(record: MatchRecord) {
+    let mut history = load_history();
+    if let Some(rank) = history.add(record) {
+        godot_print!("New high score, number {}", rank + 1);
+    }
+    if let Err(error) = history.save(history_path()) {
+        godot_warn!("{}", error);
+    }
+}
+
+/// Shows the last matches and the high scores on F2, the tree is paused
+/// while they are shown.
+#[derive(NativeClass)]
+#[inherit(Node)]
+pub struct StatsScreen {
+    label: Option<Ref<Label>>
+}
+
+#[methods]
+impl StatsScreen {
+    fn new(_owner: &Node) -> Self {
+        StatsScreen { label: None }
+    }
+
+    #[export]
+    fn _ready(&mut self, owner: &Node) {
+        // closed while the game is paused
+        owner.set_pause_mode(Node::PAUSE_MODE_PROCESS);
+
+        let label = Label::new();
+        label.set_position(Vector2::new(20.0, 140.0), false);
+        label.set_visible(false);
+        let label = label.into_shared();
+        owner.add_child(label, false);
+        self.label = Some(label);
+    }
+
+    #[export]
+    fn _input(&mut self, owner: &Node, event: Ref<InputEvent>) {
+        let event = unsafe { event.assume_safe() };
+        let open = self.is_open();
+        let toggle = key_pressed(&event, GlobalConstants::KEY_F2) || (open && key_pressed(&event, GlobalConstants::KEY_ESCAPE));
+        let tree = match owner.get_tree() {
+            Some(tree) => unsafe { tree.assume_safe() },
+            None => return
+        };
+        // the controls menu is open, F2 may be getting bound
+        if !toggle || (!open && tree.is_paused()) {
+            return;
+        }
+
+        if let Some(label) = self.label {
+            let label = unsafe { label.assume_safe() };
+            if !open {
+                let mut lines = vec!["STATS".to_string()];
+                lines.extend(load_history().lines());
+                lines.push("escape to go back".to_string());
+                label.set_text(lines.join("\n"));
+            }
+            label.set_visible(!open);
+        }
+        tree.set_pause(!open);
+    }
+
+    fn is_open(&self) -> bool {
+        self.label.map_or(false, |label| unsafe { label.assume_safe().is_visible() })
+    }
+}
@@ -0,0 +1,41 @@
+//! Plays the project with a headless Godot 3, when `GODOT_BIN` names one or
+//! `godot` is on the path. Skipped otherwise. The library is loaded from
+//! `target/debug`, as in `main_scripts.gdnlib`.
+
+use std::path::Path;
+use std::process::Command;
+
+fn godot() -> Option<String> {
+    if let Ok(bin) = std::env::var("GODOT_BIN") {
+        return Some(bin);
+    }
+    let found = Command::new("godot").arg("--version").output().map_or(false, |output| output.status.success());
+    if found { Some("godot".to_string()) } else { None }
+}
+
+#[test]
+fn level_runs_headless() {
+    let godot = match godot() {
+        Some(godot) => godot,
+        None => {
+            eprintln!("No Godot binary, set GODOT_BIN to run the level headless");
+            return;
+        }
+    };
+    let project = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+    let output = Command::new(&godot)
+        .arg("--no-window")
+        .arg("--path")
+        .arg(&project)
+        .args(&["-s", "res://tests/smoke_test.gd"])
+        .output()
+        .expect("Can not run Godot");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success() && stdout.contains("SMOKE OK"),
+        "{}\n{}",
+        stdout,
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,42 @@
+extends SceneTree
+
+# Plays the level for a few seconds and checks the Rust classes are loaded,
+# run headless by scripts/tests/headless.rs.
+
+const FRAMES = 120
+
+var level = null
+var frames = 0
+
+
+func _initialize():
+	var scene = load("res://scenes/level_test.tscn")
+	if scene == null:
+		fail("can not load the level")
+		return
+	level = scene.instance()
+	root.add_child(level)
+
+
+func _idle(_delta):
+	frames += 1
+	if level == null or frames < FRAMES:
+		return false
+
+	for name in ["Ball", "ScoreBoard", "Controls", "Sounds", "StatsScreen", "Match"]:
+		var node = level.get_node(name)
+		if node == null or node.get_script() == null:
+			fail("no script on " + name)
+			return false
+	if not level.get_node("Ball").has_signal("goal_scored"):
+		fail("the Ball has no goal_scored signal")
+		return false
+
+	print("SMOKE OK")
+	quit(0)
+	return false
+
+
+func fail(reason):
+	print("SMOKE FAILED: " + reason)
+	quit(1)
@@ -0,0 +1,179 @@
+//! Sound effects of the match events, and the menu music under the menus and
+//! the game over text, cross-faded with the game music. M mutes everything,
+//! F5 and F6 change the volume of the effects, F7 and F8 the one of the
+//! music, and the volumes are saved with the bindings.
+//!
+//! The sounds are played by kira, each group in a channel whose volume is the
+//! one of the mixer.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use bevy_kira_audio::{Audio, AudioChannel, AudioPlugin, AudioSource};
+use pong_sim::{party_sound_for, AudioPlayer, Event as PongEvent, Mixer, Music, NullBackend, PartyEvent, Side, Sound, Wall};
+
+use crate::{AppState, Players};
+
+pub struct GameAudio {
+    /// Drained by `play_sounds`.
+    player: AudioPlayer<NullBackend>,
+    sources: HashMap<Sound, Handle<AudioSource>>,
+    effects: AudioChannel,
+    /// One looping track each, their volumes make the cross-fade.
+    music: [AudioChannel; 2],
+    /// Last volumes given to the music channels.
+    music_volumes: [f32; 2],
+    path: PathBuf
+}
+
+impl GameAudio {
+    pub fn load(path: PathBuf) -> GameAudio {
+        let mut player = AudioPlayer::new(NullBackend::default());
+        player.mixer = Mixer::load_saved(&path).unwrap_or_else(|error| {
+            eprintln!("{}, using the default volumes", error);
+            Mixer::default()
+        });
+        let channel = |name: &str| AudioChannel::new(name.to_string());
+        GameAudio {
+            player,
+            sources: HashMap::new(),
+            effects: channel("effects"),
+            music: [channel(Music::ALL[0].name()), channel(Music::ALL[1].name())],
+            music_volumes: [0.0; 2],
+            path
+        }
+    }
+
+    fn save(&self) {
+        if let Err(error) = self.player.mixer.save(&self.path) {
+            eprintln!("{}", error);
+        }
+    }
+}
+
+pub fn add_systems(app: &mut AppBuilder, party: bool) {
+    app.add_plugin(AudioPlugin)
+        .add_startup_system(load_sounds.system())
+        .add_system(volume_keys.system().before("music"))
+        .add_system(fade_music.system().label("music"))
+        .add_system(play_sounds.system().label("sounds").after("music"));
+    if party {
+        app.add_system(party_sounds.system().after("simulation").before("sounds"));
+    }
+    else {
+        app.add_system(match_sounds.system().after("simulation").before("sounds"));
+    }
+}
+
+/// The effects, and both tracks looping silently until `fade_music` raises
+/// their volume.
+fn load_sounds(asset_server: Res<AssetServer>, audio_output: Res<Audio>, mut audio: ResMut<GameAudio>) {
+    let load = |name: &str| asset_server.load(format!("audio/{}.wav", name).as_str());
+    for &sound in Sound::ALL.iter() {
+        audio.sources.insert(sound, load(sound.name()));
+    }
+    for (music, channel) in Music::ALL.iter().zip(audio.music.iter()) {
+        audio_output.set_volume_in_channel(0.0, channel);
+        audio_output.play_looped_in_channel(load(music.name()), channel);
+    }
+}
+
+/// The player the win and lose sounds are for: the local one of a networked
+/// match, or the only human.
+fn listener(players: &Players) -> Option<Wall> {
+    if let Some(net) = &players.net {
+        return Some(net.side.into());
+    }
+    let walls = if players.party { &Wall::ALL[..] } else { &[Wall::Left, Wall::Right][..] };
+    let mut humans = walls.iter().filter(|wall| players.get_wall(**wall).is_none());
+    match (humans.next(), humans.next()) {
+        (Some(wall), None) => Some(*wall),
+        _ => None
+    }
+}
+
+fn match_sounds(players: Res<Players>, mut events: EventReader<PongEvent>, mut audio: ResMut<GameAudio>) {
+    let listener = listener(&players).and_then(|wall| match wall {
+        Wall::Left => Some(Side::Left),
+        Wall::Right => Some(Side::Right),
+        Wall::Top | Wall::Bottom => None
+    });
+    audio.player.play_events(events.iter(), listener);
+}
+
+fn party_sounds(players: Res<Players>, mut events: EventReader<PartyEvent>, mut audio: ResMut<GameAudio>) {
+    let listener = listener(&players);
+    for event in events.iter() {
+        if let Some(sound) = party_sound_for(event, listener) {
+            audio.player.play(sound);
+        }
+    }
+}
+
+fn play_sounds(audio_output: Res<Audio>, mut audio: ResMut<GameAudio>) {
+    let audio = &mut *audio;
+    for (sound, volume) in audio.player.backend.played.drain(..) {
+        if let Some(source) = audio.sources.get(&sound) {
+            audio_output.set_volume_in_channel(volume, &audio.effects);
+            audio_output.play_in_channel(source.clone(), &audio.effects);
+        }
+    }
+
+    let volumes = audio.player.backend.music;
+    for ((channel, last), volume) in audio.music.iter().zip(audio.music_volumes.iter_mut()).zip(volumes.iter()) {
+        if *last != *volume {
+            audio_output.set_volume_in_channel(*volume, channel);
+            *last = *volume;
+        }
+    }
+}
+
+/// The menu music over the menus and the game over screen.
+fn fade_music(time: Res<Time>, state: Res<State<AppState>>, mut audio: ResMut<GameAudio>) {
+    let music = match state.current() {
+        AppState::Loading => None,
+        AppState::Running => Some(Music::Game),
+        AppState::GameOver | AppState::Bindings | AppState::Stats => Some(Music::Menu)
+    };
+    if audio.player.music() != music {
+        audio.player.set_music(music);
+    }
+    audio.player.update(time.delta_seconds());
+}
+
+/// Not in the bindings menu, where the keys are being bound.
+fn volume_keys(keys: Res<Input<KeyCode>>, state: Res<State<AppState>>, mut audio: ResMut<GameAudio>) {
+    if *state.current() == AppState::Bindings {
+        return;
+    }
+    let mixer = &mut audio.player.mixer;
+    let step = Mixer::VOLUME_STEP;
+    let changed = if keys.just_pressed(KeyCode::M) {
+        mixer.master.muted = !mixer.master.muted;
+        true
+    }
+    else if keys.just_pressed(KeyCode::F5) {
+        mixer.effects.adjust(-step);
+        true
+    }
+    else if keys.just_pressed(KeyCode::F6) {
+        mixer.effects.adjust(step);
+        true
+    }
+    else if keys.just_pressed(KeyCode::F7) {
+        mixer.music.adjust(-step);
+        true
+    }
+    else if keys.just_pressed(KeyCode::F8) {
+        mixer.music.adjust(step);
+        true
+    }
+    else {
+        false
+    };
+
+    if changed {
+        audio.save();
+    }
+}
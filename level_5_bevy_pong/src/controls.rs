@@ -0,0 +1,245 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use pong_sim::{Action, BindingMenu, Bindings, Control, Inputs, Side};
+
+use crate::AppState;
+
+/// Axes a binding can name, bevy can not list them.
+const GAMEPAD_AXES: [GamepadAxisType; 6] = [
+    GamepadAxisType::LeftStickX,
+    GamepadAxisType::LeftStickY,
+    GamepadAxisType::LeftZ,
+    GamepadAxisType::RightStickX,
+    GamepadAxisType::RightStickY,
+    GamepadAxisType::RightZ
+];
+
+/// Gamepads looked at when waiting for a control to bind.
+const MAX_GAMEPADS: usize = 4;
+
+/// How far a stick must be pushed to be bound.
+const CAPTURE_THRESHOLD: f32 = 0.5;
+
+/// Keyboard and gamepad bindings of both players, saved when changed.
+pub struct Controls {
+    pub bindings: Bindings,
+    defaults: Bindings,
+    path: PathBuf,
+    menu: BindingMenu
+}
+
+impl Controls {
+    /// The saved bindings, or the default ones when there are none yet.
+    pub fn load(path: PathBuf) -> Controls {
+        let defaults = default_bindings();
+        let bindings = if path.exists() {
+            Bindings::load(&path).unwrap_or_else(|error| {
+                eprintln!("{}, using the default bindings", error);
+                defaults.clone()
+            })
+        }
+        else {
+            defaults.clone()
+        };
+
+        Controls { bindings, defaults, path, menu: BindingMenu::default() }
+    }
+
+    fn save(&self) {
+        if let Err(error) = self.bindings.save(&self.path) {
+            eprintln!("{}", error);
+        }
+    }
+}
+
+/// The keyboard layout of the game, and the left stick of the first two
+/// gamepads.
+fn default_bindings() -> Bindings {
+    let mut bindings = Bindings::default();
+    for &(gamepad, side) in [(0, Side::Left), (1, Side::Right)].iter() {
+        let stick = |positive| Control::Axis { gamepad, axis: "LeftStickY".to_string(), positive };
+        bindings.bind(side, Action::Up, stick(true));
+        bindings.bind(side, Action::Down, stick(false));
+    }
+    bindings
+}
+
+fn axis_type(name: &str) -> Option<GamepadAxisType> {
+    GAMEPAD_AXES.iter().copied().find(|axis| format!("{:?}", axis) == name)
+}
+
+pub fn read_inputs(
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    controls: Res<Controls>,
+    mut inputs: ResMut<Inputs>
+) {
+    let pressed = |pressed: bool| if pressed { 1.0 } else { 0.0 };
+    let strength = |control: &Control| match control {
+        Control::Key(name) => pressed(keys.get_pressed().any(|key| format!("{:?}", key) == *name)),
+        Control::Button { gamepad, button } => pressed(buttons.get_pressed().any(|held| {
+            (held.0).0 == *gamepad as usize && format!("{:?}", held.1) == *button
+        })),
+        Control::Axis { gamepad, axis, positive } => match axis_type(axis) {
+            Some(axis) => {
+                let value = axes.get(GamepadAxis(Gamepad(*gamepad as usize), axis)).unwrap_or(0.0);
+                if *positive { value } else { -value }
+            }
+            None => 0.0
+        }
+    };
+
+    inputs.left = controls.bindings.axis(Side::Left, &strength);
+    inputs.right = controls.bindings.axis(Side::Right, &strength);
+}
+
+/// The first control pressed this frame, keyboard or gamepad.
+fn pressed_control(
+    keys: &Input<KeyCode>,
+    buttons: &Input<GamepadButton>,
+    axes: &Axis<GamepadAxis>
+) -> Option<Control> {
+    if let Some(key) = keys.get_just_pressed().next() {
+        return Some(Control::Key(format!("{:?}", key)));
+    }
+    if let Some(button) = buttons.get_just_pressed().next() {
+        return Some(Control::Button { gamepad: (button.0).0 as u32, button: format!("{:?}", button.1) });
+    }
+    for gamepad in 0..MAX_GAMEPADS {
+        for &axis in GAMEPAD_AXES.iter() {
+            let value = axes.get(GamepadAxis(Gamepad(gamepad), axis)).unwrap_or(0.0);
+            if value.abs() > CAPTURE_THRESHOLD {
+                let axis = format!("{:?}", axis);
+                return Some(Control::Axis { gamepad: gamepad as u32, axis, positive: value > 0.0 });
+            }
+        }
+    }
+    None
+}
+
+struct MenuLine(usize);
+
+struct MenuOverlay;
+
+/// F1 or the gamepad start button pauses the game to change the bindings.
+pub fn open_menu(
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    mut state: ResMut<State<AppState>>
+) {
+    let start = buttons.get_just_pressed().any(|button| button.1 == GamepadButtonType::Start);
+    if keys.just_pressed(KeyCode::F1) || start {
+        // fails when another screen was asked for in the same frame, like
+        // the game over one, that screen is shown instead
+        let _ = state.push(AppState::Bindings);
+    }
+}
+
+pub fn setup_menu(
+    mut commands: Commands,
+    controls: Res<Controls>,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>
+) {
+    let style = TextStyle {
+        font: asset_server.load("fonts/square.ttf"),
+        font_size: 30.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                position_type: PositionType::Absolute,
+                ..Default::default()
+            },
+            material: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.8).into()),
+            ..Default::default()
+        })
+        .insert(MenuOverlay)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    "CONTROLS",
+                    TextStyle { font_size: 60.0, color: Color::GOLD, ..style.clone() },
+                    Default::default()
+                ),
+                ..Default::default()
+            });
+            for (index, line) in controls.menu.lines(&controls.bindings).into_iter().enumerate() {
+                parent
+                    .spawn_bundle(TextBundle {
+                        text: Text::with_section(line, style.clone(), Default::default()),
+                        ..Default::default()
+                    })
+                    .insert(MenuLine(index));
+            }
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    "enter to change, escape to go back",
+                    TextStyle { font_size: 20.0, ..style },
+                    Default::default()
+                ),
+                ..Default::default()
+            });
+        });
+}
+
+/// Arrows or the d-pad move in the menu, enter or the south button changes a
+/// binding and the next control pressed is bound. Escape goes back.
+pub fn update_menu(
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    mut controls: ResMut<Controls>,
+    mut state: ResMut<State<AppState>>,
+    mut lines: Query<(&mut Text, &MenuLine)>
+) {
+    let controls = &mut *controls;
+    let button = |button_type: GamepadButtonType| {
+        buttons.get_just_pressed().any(|button| button.1 == button_type)
+    };
+    let mut changed = false;
+
+    if controls.menu.is_waiting() {
+        if keys.just_pressed(KeyCode::Escape) {
+            controls.menu.cancel();
+        }
+        else if let Some(control) = pressed_control(&keys, &buttons, &axes) {
+            changed = controls.menu.capture(control, &mut controls.bindings);
+        }
+    }
+    else if keys.just_pressed(KeyCode::Up) || button(GamepadButtonType::DPadUp) {
+        controls.menu.previous();
+    }
+    else if keys.just_pressed(KeyCode::Down) || button(GamepadButtonType::DPadDown) {
+        controls.menu.next();
+    }
+    else if keys.just_pressed(KeyCode::Return) || button(GamepadButtonType::South) {
+        changed = controls.menu.activate(&mut controls.bindings, &controls.defaults);
+    }
+    else if keys.just_pressed(KeyCode::Escape) || keys.just_pressed(KeyCode::F1) || button(GamepadButtonType::East) {
+        let _ = state.pop();
+        return;
+    }
+
+    if changed {
+        controls.save();
+    }
+    let texts = controls.menu.lines(&controls.bindings);
+    for (mut text, line) in lines.iter_mut() {
+        text.sections[0].value = texts[line.0].clone();
+    }
+}
+
+pub fn clear_menu(mut commands: Commands, query: Query<Entity, With<MenuOverlay>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,117 @@
+//! Sparks on paddle hits and goals, screen shake and a trail behind the ball.
+//! The particles and the trail are pools of sprites, hidden when unused.
+
+use bevy::prelude::*;
+use bevy_letterbox::LetterboxCamera;
+use pong_sim::{Event as PongEvent, MatchEffects, PongSim};
+
+use crate::{AppState, Atlases, BALL_SPRITE_SIZE};
+
+/// Sprites of the pools, more particles than this are not drawn.
+const MAX_SPARKS: usize = 512;
+const MAX_TRAIL: usize = 16;
+/// Sparks over the ball, the trail under it.
+const SPARK_DEPTH: f32 = 1.0;
+const TRAIL_DEPTH: f32 = -0.5;
+
+/// Sprite of the ball in the atlas, a white square tinted per particle.
+const SQUARE_SPRITE: u32 = 1;
+
+struct Spark(usize);
+
+struct TrailDot(usize);
+
+pub fn add_systems(app: &mut AppBuilder) {
+    app.insert_resource(MatchEffects::default())
+        .add_system_set(SystemSet::on_enter(AppState::Running).with_system(setup_pools.system()))
+        .add_system(update_effects.system().label("effects").after("simulation"))
+        .add_system(draw_sparks.system().after("effects"))
+        .add_system(draw_trail.system().after("effects"))
+        .add_system(shake_camera.system().after("effects"));
+}
+
+fn setup_pools(mut commands: Commands, atlases: Res<Atlases>, sparks: Query<&Spark>) {
+    // entered again after each game over
+    if sparks.iter().next().is_some() {
+        return;
+    }
+    let hidden = |depth: f32| SpriteSheetBundle {
+        sprite: TextureAtlasSprite::new(SQUARE_SPRITE),
+        texture_atlas: atlases.main.clone(),
+        transform: Transform::from_translation(Vec3::new(0.0, 0.0, depth)),
+        visible: Visible { is_visible: false, is_transparent: true },
+        ..Default::default()
+    };
+    for index in 0..MAX_SPARKS {
+        commands.spawn_bundle(hidden(SPARK_DEPTH)).insert(Spark(index));
+    }
+    for index in 0..MAX_TRAIL {
+        commands.spawn_bundle(hidden(TRAIL_DEPTH)).insert(TrailDot(index));
+    }
+}
+
+/// The effects go on under the menus, the match does not.
+fn update_effects(
+    time: Res<Time>,
+    sim: Res<PongSim>,
+    mut events: EventReader<PongEvent>,
+    mut effects: ResMut<MatchEffects>
+) {
+    let events: Vec<PongEvent> = events.iter().copied().collect();
+    effects.handle(&sim, &events);
+    effects.update(&sim, time.delta_seconds());
+}
+
+fn draw_sparks(
+    effects: Res<MatchEffects>,
+    mut sparks: Query<(&Spark, &mut TextureAtlasSprite, &mut Transform, &mut Visible)>
+) {
+    let particles = effects.sparks.particles();
+    for (spark, mut sprite, mut transform, mut visible) in sparks.iter_mut() {
+        let particle = match particles.get(spark.0) {
+            Some(particle) => particle,
+            None => {
+                visible.is_visible = false;
+                continue;
+            }
+        };
+        let [r, g, b, a] = effects.sparks.color(particle);
+        visible.is_visible = true;
+        sprite.color = Color::rgba(r, g, b, a);
+        transform.translation = Vec3::new(particle.position.x, particle.position.y, SPARK_DEPTH);
+        transform.scale = Vec3::new(particle.size / BALL_SPRITE_SIZE, particle.size / BALL_SPRITE_SIZE, 1.0);
+    }
+}
+
+/// Smaller and fainter toward the end of the trail.
+fn draw_trail(
+    sim: Res<PongSim>,
+    effects: Res<MatchEffects>,
+    mut dots: Query<(&TrailDot, &mut TextureAtlasSprite, &mut Transform, &mut Visible)>
+) {
+    let points: Vec<_> = effects.trail.points().collect();
+    let diameter = sim.config.ball_radius * 2.0;
+    for (dot, mut sprite, mut transform, mut visible) in dots.iter_mut() {
+        let (point, weight) = match points.get(dot.0) {
+            Some(point) => *point,
+            None => {
+                visible.is_visible = false;
+                continue;
+            }
+        };
+        visible.is_visible = true;
+        sprite.color = Color::rgba(1.0, 1.0, 1.0, weight * 0.4);
+        let scale = diameter * weight / BALL_SPRITE_SIZE;
+        transform.translation = Vec3::new(point.x, point.y, TRAIL_DEPTH);
+        transform.scale = Vec3::new(scale, scale, 1.0);
+    }
+}
+
+fn shake_camera(effects: Res<MatchEffects>, mut cameras: Query<&mut Transform, With<LetterboxCamera>>) {
+    let (offset, angle) = effects.shake.offset();
+    for mut transform in cameras.iter_mut() {
+        transform.translation.x = offset.x;
+        transform.translation.y = offset.y;
+        transform.rotation = Quat::from_rotation_z(angle);
+    }
+}
@@ -1,40 +1,154 @@
+mod audio;
+mod controls;
+mod effects;
+mod party;
+mod power_ups;
+mod stats;
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use bevy::{
     diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin}, 
     prelude::*
 };
+use pong_sim::{
+    AiController, ConfigFile, Difficulty, Event as PongEvent, FixedTimestep, Inputs, Peer, PongConfig, PongSim,
+    Replay, ReplayInputs, RollbackSession, RollbackSettings, Side, UdpTransport, Wall
+};
+
+struct Ball;
+
+struct Paddle;
 
-const BALL_RADIUS: f32 = 10.0;
-const INITIAL_BALL_VELOCITY_X: f32 = 100.0;
-const INITIAL_BALL_VELOCITY_Y: f32 = 150.0;
-const INITIAL_PADDLE_SPEED: f32 = 150.0;
+/// A paddle played by the computer.
+struct Ai(AiController);
 
-struct Ball {
-    center: Vec2,
-    velocity: Vec2
+/// A networked match, the rollback session runs the simulation.
+struct Net(Peer<UdpTransport>);
+
+/// Inputs of the match saved to a replay file after every point.
+struct Recording {
+    replay: Replay,
+    path: PathBuf
+}
+
+/// A replay played instead of the players.
+struct Playback {
+    replay: Replay,
+    inputs: ReplayInputs,
+    over: bool
 }
 
-enum Side {
-    LEFT,
-    RIGHT
+/// Where to reach the remote player, and which side is played here.
+struct NetArgs {
+    local: SocketAddr,
+    remote: SocketAddr,
+    side: Side
 }
 
-struct Paddle {
-    center: Vec2,
-    width: f32,
-    height: f32,
-    speed: f32
+/// Who plays each side, `None` for a human.
+struct Players {
+    left: Option<Difficulty>,
+    right: Option<Difficulty>,
+    /// The paddles of a four-player match.
+    top: Option<Difficulty>,
+    bottom: Option<Difficulty>,
+    party: bool,
+    net: Option<NetArgs>,
+    record: Option<PathBuf>,
+    replay: Option<PathBuf>
 }
 
-#[derive(Default)]
-struct Score {
-    player_1: i32,
-    player_2: i32
+impl Players {
+    /// `--ai <difficulty>` for a single player game against the right paddle,
+    /// `--ai-left <difficulty>` and `--ai-right <difficulty>` to choose each side.
+    /// `--net <local address> <remote address> <left|right>` plays one side
+    /// against another computer, both must use the same tuning.
+    /// `--record <file>` saves the match to a replay, `--replay <file>` plays one.
+    /// `--party` plays with four paddles, `--ai-top` and `--ai-bottom` choose
+    /// the computer players of the new ones.
+    fn from_args() -> Result<Players, String> {
+        let mut players = Players {
+            left: None, right: None, top: None, bottom: None, party: false, net: None, record: None, replay: None
+        };
+        let mut args = std::env::args().skip(1);
+
+        while let Some(arg) = args.next() {
+            let mut difficulty = || -> Result<Difficulty, String> {
+                let name = args.next().ok_or(format!("Missing difficulty after {}", arg))?;
+                Difficulty::from_name(&name)
+            };
+            match arg.as_str() {
+                "--ai" | "--ai-right" => players.right = Some(difficulty()?),
+                "--ai-left" => players.left = Some(difficulty()?),
+                "--ai-top" => players.top = Some(difficulty()?),
+                "--ai-bottom" => players.bottom = Some(difficulty()?),
+                "--party" => players.party = true,
+                "--net" => {
+                    let mut next = |name: &str| args.next().ok_or(format!("Missing {} after --net", name));
+                    let address = |address: String| -> Result<SocketAddr, String> {
+                        address.parse().map_err(|error| format!("{}: {}", address, error))
+                    };
+                    let local = address(next("local address")?)?;
+                    let remote = address(next("remote address")?)?;
+                    let side = match next("side")?.as_str() {
+                        "left" => Side::Left,
+                        "right" => Side::Right,
+                        side => return Err(format!("Unknown side {}, expected left or right", side))
+                    };
+                    players.net = Some(NetArgs { local, remote, side });
+                }
+                "--record" => players.record = Some(args.next().ok_or("Missing file after --record")?.into()),
+                "--replay" => players.replay = Some(args.next().ok_or("Missing file after --replay")?.into()),
+                _ => return Err(format!("Unknown argument {}", arg))
+            }
+        }
+
+        if players.net.is_some() && (players.record.is_some() || players.replay.is_some()) {
+            return Err("A networked match can not be recorded or replayed".to_string());
+        }
+        if players.party && (players.net.is_some() || players.record.is_some() || players.replay.is_some()) {
+            return Err("A four-player match can not be networked, recorded or replayed".to_string());
+        }
+        if !players.party && (players.top.is_some() || players.bottom.is_some()) {
+            return Err("Only a four-player match has top and bottom paddles, add --party".to_string());
+        }
+        Ok(players)
+    }
+
+    fn get(&self, side: Side) -> Option<Difficulty> {
+        self.get_wall(side.into())
+    }
+
+    fn get_wall(&self, wall: Wall) -> Option<Difficulty> {
+        match wall {
+            Wall::Left => self.left,
+            Wall::Right => self.right,
+            Wall::Top => self.top,
+            Wall::Bottom => self.bottom
+        }
+    }
 }
 
 struct FpsText;
 
 struct ScoreText;
 
+struct CountdownText;
+
+struct GameOverText;
+
+/// State of the simulation before its last step, to interpolate the rendering.
+struct PreviousSim(PongSim);
+
+/// Tuning file polled while the game runs.
+struct Tuning {
+    file: ConfigFile,
+    timer: Timer
+}
+
 struct Atlases {
     pub main: Handle<TextureAtlas>
 }
@@ -43,28 +157,136 @@ struct Atlases {
 enum AppState {
     Loading,
     Running,
+    /// Pushed over `Running` when a player wins, popped to play again.
+    GameOver,
+    /// Pushed over `Running` to change the controls, the game is paused.
+    Bindings,
+    /// Pushed over `Running` or `GameOver` to show the statistics, the game is paused.
+    Stats
 }
 
+const CONFIG_PATH: &str = "assets/components/paddle.ron";
+const BINDINGS_PATH: &str = "config/bindings.ron";
+const AUDIO_PATH: &str = "config/audio.ron";
+
+/// Sprites of the atlas, in pixels.
+const PADDLE_SPRITE_WIDTH: f32 = 4.0;
+const PADDLE_SPRITE_HEIGHT: f32 = 16.0;
+const BALL_SPRITE_SIZE: f32 = 4.0;
+
 fn main() {
-    App::build()
+    // found the same way as the assets, next to the manifest under cargo run
+    let root = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".to_string());
+    let mut file = ConfigFile::new(std::path::Path::new(&root).join(CONFIG_PATH));
+    let controls = controls::Controls::load(std::path::Path::new(&root).join(BINDINGS_PATH));
+    let game_audio = audio::GameAudio::load(std::path::Path::new(&root).join(AUDIO_PATH));
+    let players = Players::from_args().unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        eprintln!("Usage: level_5_bevy_pong [--ai <easy|normal|hard>] [--ai-left <difficulty>] [--ai-right <difficulty>]");
+        eprintln!("       [--net <local address> <remote address> <left|right>] [--record <file>] [--replay <file>]");
+        eprintln!("       [--party] [--ai-top <difficulty>] [--ai-bottom <difficulty>]");
+        std::process::exit(1);
+    });
+    let playback = players.replay.as_ref().map(|path| {
+        let replay = Replay::load(path).unwrap_or_else(|error| {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        });
+        Playback { inputs: replay.iter(), replay, over: false }
+    });
+    // a replay brings its own tuning
+    let config = match &playback {
+        Some(playback) => playback.replay.config.clone(),
+        None => {
+            let (config, error) = file.load();
+            if let Some(error) = error {
+                eprintln!("{}, using the default tuning", error);
+            }
+            config
+        }
+    };
+    let timestep = match &playback {
+        Some(playback) => FixedTimestep::new(playback.replay.step, FixedTimestep::DEFAULT_MAX_STEPS),
+        None => FixedTimestep::default()
+    };
+    // the power-ups of a replay come from its seed
+    let seed = playback.as_ref().map_or(0, |playback| playback.replay.seed);
+    let recording = players.record.clone().map(|path| Recording {
+        replay: Replay::new(config.clone(), seed, timestep.step()),
+        path
+    });
+    let net = players.net.as_ref().map(|net| {
+        let transport = UdpTransport::bind(net.local, net.remote).unwrap_or_else(|error| {
+            eprintln!("Can not bind {}: {}", net.local, error);
+            std::process::exit(1);
+        });
+        let session = RollbackSession::new(config.clone(), net.side, RollbackSettings::default());
+        Net(Peer::new(session, transport))
+    });
+
+    let party = players.party;
+    let mut app = App::build();
+    // the window starts at the size of the arena, the camera fits it once resized
+    app.insert_resource(WindowDescriptor {
+            title: "Pong!".to_string(),
+            width: config.arena_width,
+            height: config.arena_height,
+            ..Default::default()
+        })
         .add_plugins(DefaultPlugins)
         .add_plugin(FrameTimeDiagnosticsPlugin::default())
+        .add_plugin(bevy_letterbox::LetterboxPlugin)
+        .insert_resource(bevy_letterbox::GameArea::new(config.arena_width, config.arena_height))
         .add_state(AppState::Loading)
         .add_system_set(SystemSet::on_enter(AppState::Loading).with_system(setup_world.system()))
-        .add_system_set(SystemSet::on_enter(AppState::Running)
-            .with_system(setup_ui.system())
-            .with_system(setup_ball.system())
-            .with_system(setup_paddles.system()))
-        .init_resource::<Score>()
-        .add_system(move_balls.system())
-        .add_system(move_paddles.system())
-        //
-        .add_system(collision.system())
-        //
-        .add_system(apply_transform.system())
-        .add_system(fps.system())
-        .add_system(score_board.system())
-        .run();
+        .add_system_set(SystemSet::on_enter(AppState::Running).with_system(setup_ui.system()))
+        .add_system_set(SystemSet::on_exit(AppState::GameOver).with_system(clear_game_over.system()))
+        .add_system_set(SystemSet::on_update(AppState::Running).with_system(controls::open_menu.system()))
+        .add_system_set(SystemSet::on_enter(AppState::Bindings).with_system(controls::setup_menu.system()))
+        .add_system_set(SystemSet::on_update(AppState::Bindings).with_system(controls::update_menu.system()))
+        .add_system_set(SystemSet::on_exit(AppState::Bindings).with_system(controls::clear_menu.system()))
+        .insert_resource(timestep)
+        .init_resource::<Inputs>()
+        .insert_resource(players)
+        .insert_resource(controls)
+        .insert_resource(game_audio)
+        .add_system(controls::read_inputs.system().label("inputs"))
+        .add_system(fps.system());
+    if party {
+        party::add_systems(&mut app, config);
+    }
+    else {
+        app.add_system_set(SystemSet::on_enter(AppState::Running)
+                .with_system(setup_score_board.system())
+                .with_system(setup_ball.system())
+                .with_system(setup_paddles.system()))
+            .add_system_set(SystemSet::on_enter(AppState::GameOver).with_system(setup_game_over.system()))
+            .add_system_set(SystemSet::on_update(AppState::GameOver).with_system(restart.system()))
+            .insert_resource(PreviousSim(PongSim::with_seed(config.clone(), seed)))
+            .insert_resource(PongSim::with_seed(config, seed))
+            .insert_resource(Tuning { file, timer: Timer::from_seconds(1.0, true) })
+            .add_event::<PongEvent>()
+            .add_system(reload_config.system().before("simulation"))
+            .add_system(step_simulation.system().label("simulation").after("inputs"))
+            .add_system(apply_transform.system().after("simulation"))
+            .add_system(check_winner.system().after("simulation"))
+            .add_system(countdown.system().after("simulation"))
+            .add_system(score_board.system());
+        power_ups::add_systems(&mut app);
+        stats::add_systems(&mut app);
+        effects::add_systems(&mut app);
+    }
+    audio::add_systems(&mut app, party);
+    if let Some(net) = net {
+        app.insert_resource(net);
+    }
+    if let Some(recording) = recording {
+        app.insert_resource(recording);
+    }
+    if let Some(playback) = playback {
+        app.insert_resource(playback);
+    }
+    app.run();
 }
 
 fn setup_world(
@@ -76,14 +298,17 @@ fn setup_world(
     // load textures / sprites
     let texture_handle = asset_server.load("textures/pong_spritesheet.png");
     let mut texture_atlas = TextureAtlas::new_empty(texture_handle, Vec2::new(8.0, 16.0));
-    texture_atlas.add_texture(bevy::sprite::Rect{ min: Vec2::new(0.0, 0.0), max: Vec2::new(4.0, 16.0) });
-    texture_atlas.add_texture(bevy::sprite::Rect{ min: Vec2::new(4.0, 0.0), max: Vec2::new(8.0, 4.0) });
+    texture_atlas.add_texture(bevy::sprite::Rect{
+        min: Vec2::new(0.0, 0.0),
+        max: Vec2::new(PADDLE_SPRITE_WIDTH, PADDLE_SPRITE_HEIGHT)
+    });
+    texture_atlas.add_texture(bevy::sprite::Rect{
+        min: Vec2::new(PADDLE_SPRITE_WIDTH, 0.0),
+        max: Vec2::new(PADDLE_SPRITE_WIDTH + BALL_SPRITE_SIZE, BALL_SPRITE_SIZE)
+    });
     let texture_atlas_handle = texture_atlases.add(texture_atlas);
     commands.insert_resource(Atlases { main: texture_atlas_handle });
 
-    // add a camera
-    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
-
     state.set(AppState::Running).unwrap();
 }
 
@@ -128,7 +353,48 @@ fn setup_ui(
     })
     .insert(FpsText);
 
-    // players score
+    // serve countdown, in the middle of the screen
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                position_type: PositionType::Absolute,
+                ..Default::default()
+            },
+            material: materials.add(Color::NONE.into()),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 120.0,
+                            color: Color::WHITE,
+                        },
+                        TextAlignment {
+                            horizontal: HorizontalAlign::Center,
+                            ..Default::default()
+                        },
+                    ),
+                    ..Default::default()
+                })
+                .insert(CountdownText);
+        });
+}
+
+/// One score on each half of the screen, above the paddles.
+fn setup_score_board(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>
+) {
+    let font = asset_server.load("fonts/square.ttf");
+
     commands
         .spawn_bundle(NodeBundle {
             style: Style {
@@ -177,7 +443,7 @@ fn setup_ui(
                             ..Default::default()
                         })
                         .insert(ScoreText)
-                        .insert(Side::LEFT);
+                        .insert(Side::Left);
                 });
 
             // right score board
@@ -217,7 +483,7 @@ fn setup_ui(
                             ..Default::default()
                         })
                         .insert(ScoreText)
-                        .insert(Side::RIGHT);
+                        .insert(Side::Right);
                 });
         });
 }
@@ -232,202 +498,306 @@ fn fps(diagnostics: Res<Diagnostics>, mut query: Query<&mut Text, With<FpsText>>
     }
 }
 
-fn score_board(score: Res<Score>, mut query: Query<(&mut Text, &Side), With<ScoreText>>) {
+fn score_board(sim: Res<PongSim>, mut query: Query<(&mut Text, &Side), With<ScoreText>>) {
     for (mut text, side) in query.iter_mut() {
-        match side {
-            Side::LEFT => {
-                text.sections[0].value = format!("{:02}", score.player_1);
-            }
-            Side::RIGHT => {
-                text.sections[0].value = format!("{:02}", score.player_2);
-            }
-        }
+        text.sections[0].value = format!("{:02}", sim.score.get(*side));
     }
 }
 
-fn setup_ball(mut commands: Commands, altlases_handle: Res<Atlases>) {
-    commands
-        .spawn_bundle(SpriteSheetBundle {
-            sprite: TextureAtlasSprite::new(1),
-            texture_atlas: altlases_handle.main.clone(),
-            transform: Transform::from_scale(Vec3::splat(5.0)),
-            ..Default::default()
-        })
-        .insert(Ball {
-            center: Vec2::new(0.0, 0.0),
-            velocity: Vec2::new(INITIAL_BALL_VELOCITY_X, INITIAL_BALL_VELOCITY_Y)
-        });
+fn countdown(sim: Res<PongSim>, mut query: Query<&mut Text, With<CountdownText>>) {
+    for mut text in query.iter_mut() {
+        text.sections[0].value = match sim.serve_countdown() {
+            Some(seconds) => format!("{}", seconds.ceil()),
+            None => String::new()
+        };
+    }
 }
 
-fn setup_paddles(
-    mut commands: Commands, 
-    windows: Res<Windows>,
-    altlases_handle: Res<Atlases>
+fn check_winner(sim: Res<PongSim>, mut state: ResMut<State<AppState>>) {
+    if sim.winner().is_some() && *state.current() == AppState::Running {
+        // asked again next frame when the bindings menu was opened first
+        let _ = state.push(AppState::GameOver);
+    }
+}
+
+fn setup_game_over(
+    mut commands: Commands,
+    sim: Res<PongSim>,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>
 ) {
-    let window_demi_width = match windows.get_primary() {
-        Some(window) => window.width() / 2.0,
-        None => 400.0
+    let winner = match sim.winner() {
+        Some(Side::Left) => "LEFT PLAYER WINS",
+        Some(Side::Right) => "RIGHT PLAYER WINS",
+        None => "GAME OVER"
+    };
+    spawn_game_over(&mut commands, &asset_server, &mut materials, winner);
+}
+
+/// The winner over a dark overlay, until space is pressed.
+fn spawn_game_over(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    materials: &mut Assets<ColorMaterial>,
+    winner: &str
+) {
+    let style = TextStyle {
+        font: asset_server.load("fonts/square.ttf"),
+        font_size: 60.0,
+        color: Color::GOLD,
     };
 
     commands
-        .spawn_bundle(SpriteSheetBundle {
-            sprite: TextureAtlasSprite::new(0),
-            texture_atlas: altlases_handle.main.clone(),
-            transform: Transform {
-                translation: Vec3::new(-window_demi_width, 0.0, 0.0),
-                scale: Vec3::splat(5.0),
-                rotation: Quat::IDENTITY
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                position_type: PositionType::Absolute,
+                ..Default::default()
             },
+            material: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.6).into()),
             ..Default::default()
         })
-        .insert(Paddle {
-            center: Vec2::new(-window_demi_width, 0.0),
-            width: 20.0,
-            height: 80.0,
-            speed: INITIAL_PADDLE_SPEED
-        })
-        .insert(Side::LEFT);
+        .insert(GameOverText)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(winner, style.clone(), Default::default()),
+                ..Default::default()
+            });
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    "press space to play again",
+                    TextStyle { font_size: 30.0, color: Color::WHITE, ..style },
+                    Default::default()
+                ),
+                ..Default::default()
+            });
+        });
+}
+
+fn restart(
+    keys: Res<Input<KeyCode>>,
+    net: Option<Res<Net>>,
+    recording: Option<Res<Recording>>,
+    playback: Option<Res<Playback>>,
+    mut sim: ResMut<PongSim>,
+    mut previous: ResMut<PreviousSim>,
+    mut state: ResMut<State<AppState>>,
+    mut ais: Query<&mut Ai>
+) {
+    // networked, recorded and replayed matches are played once
+    let once = net.is_some() || recording.is_some() || playback.is_some();
+    if !once && keys.just_pressed(KeyCode::Space) {
+        sim.restart();
+        previous.0 = sim.clone();
+        // a new player every game
+        let seed = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64);
+        for (index, mut ai) in ais.iter_mut().enumerate() {
+            ai.0 = AiController::new(ai.0.side, ai.0.difficulty, seed.wrapping_add(index as u64));
+        }
+        // a Space ignored if the stats were opened in the same frame
+        let _ = state.pop();
+    }
+}
+
+fn clear_game_over(mut commands: Commands, query: Query<Entity, With<GameOverText>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn setup_ball(mut commands: Commands, sim: Res<PongSim>, altlases_handle: Res<Atlases>) {
+    let position = sim.ball.position;
 
     commands
         .spawn_bundle(SpriteSheetBundle {
-            sprite: TextureAtlasSprite::new(0),
+            sprite: TextureAtlasSprite::new(1),
             texture_atlas: altlases_handle.main.clone(),
             transform: Transform {
-                translation: Vec3::new(-window_demi_width, 0.0, 0.0),
-                scale: Vec3::splat(5.0),
+                translation: Vec3::new(position.x, position.y, 0.0),
+                scale: ball_scale(&sim.config),
                 rotation: Quat::IDENTITY
             },
             ..Default::default()
         })
-        .insert(Paddle {
-            center: Vec2::new(window_demi_width, 0.0),
-            width: 20.0,
-            height: 80.0,
-            speed: INITIAL_PADDLE_SPEED
-        })
-        .insert(Side::RIGHT);
+        .insert(Ball);
 }
 
-fn move_balls(
-    time: Res<Time>, 
-    windows: Res<Windows>,
-    mut score: ResMut<Score>,
-    mut balls: Query<&mut Ball>
+fn setup_paddles(
+    mut commands: Commands, 
+    sim: Res<PongSim>,
+    players: Res<Players>,
+    altlases_handle: Res<Atlases>
 ) {
-    let window = windows.get_primary().unwrap();
-
-    for mut ball in balls.iter_mut() {
-        let v = ball.velocity;
-        ball.center += v * time.delta_seconds();
+    for (seed, paddle) in sim.paddles.iter().enumerate() {
+        let mut entity = commands
+            .spawn_bundle(SpriteSheetBundle {
+                sprite: TextureAtlasSprite::new(0),
+                texture_atlas: altlases_handle.main.clone(),
+                transform: Transform {
+                    translation: Vec3::new(paddle.position.x, paddle.position.y, 0.0),
+                    scale: paddle_scale(&sim.config),
+                    rotation: Quat::IDENTITY
+                },
+                ..Default::default()
+            });
+        entity.insert(Paddle).insert(paddle.side);
 
-        if ball.center.y > window.height() / 2.0 - BALL_RADIUS {
-            ball.velocity.y = -ball.velocity.y;
-            ball.center.y += window.height() / 2.0 - BALL_RADIUS - ball.center.y 
+        if let Some(difficulty) = players.get(paddle.side) {
+            entity.insert(Ai(AiController::new(paddle.side, difficulty, seed as u64)));
         }
+    }
+}
 
-        if ball.center.y < -window.height() / 2.0 + BALL_RADIUS {
-            ball.velocity.y = -ball.velocity.y;
-            ball.center.y += -window.height() / 2.0 + BALL_RADIUS - ball.center.y
-        }
+/// Apply the tuning file when it changed, the match goes on.
+fn reload_config(
+    time: Res<Time>,
+    net: Option<Res<Net>>,
+    recording: Option<Res<Recording>>,
+    playback: Option<Res<Playback>>,
+    mut tuning: ResMut<Tuning>,
+    mut area: ResMut<bevy_letterbox::GameArea>,
+    mut sim: ResMut<PongSim>,
+    mut previous: ResMut<PreviousSim>
+) {
+    // the remote player would not see the change, and replays would differ
+    let fixed = net.is_some() || recording.is_some() || playback.is_some();
+    if fixed || !tuning.timer.tick(time.delta()).just_finished() {
+        return;
+    }
 
-        // balle qui sort à droite ou à gauche
-        if ball.center.x > window.width() / 2.0 + BALL_RADIUS {
-            ball.velocity = Vec2::new(-INITIAL_BALL_VELOCITY_X, INITIAL_BALL_VELOCITY_Y);
-            ball.center = Vec2::new(0.0, 0.0);
-            score.player_1 += 1;
-            println!("Score: {} - {}", score.player_1, score.player_2);
-        } 
-        if ball.center.x < -(window.width() / 2.0 + BALL_RADIUS) {
-            ball.velocity = Vec2::new(INITIAL_BALL_VELOCITY_X, INITIAL_BALL_VELOCITY_Y);
-            ball.center = Vec2::new(0.0, 0.0);
-            score.player_2 += 1;
-            println!("Score: {} - {}", score.player_1, score.player_2);
+    match tuning.file.reload() {
+        Some(Ok(config)) => {
+            println!("Reloaded {}", tuning.file.path().display());
+            *area = bevy_letterbox::GameArea::new(config.arena_width, config.arena_height);
+            sim.set_config(config.clone());
+            previous.0.set_config(config);
         }
+        Some(Err(error)) => eprintln!("{}", error),
+        None => {}
     }
 }
 
-fn move_paddles(
-    time: Res<Time>, 
-    keys: Res<Input<KeyCode>>,
-    windows: Res<Windows>,
-    mut paddles: Query<(&mut Paddle, &Side)>
+/// Run as many fixed steps as the frame time allows, so that the game plays
+/// the same at any frame rate. The computer overrides the keyboard for its
+/// paddles, deciding at every step. A networked match is stepped by the
+/// rollback session, which may wait for the remote player or correct past
+/// frames. A replay gives the inputs of every step, a recording keeps them.
+fn step_simulation(
+    time: Res<Time>,
+    inputs: Res<Inputs>,
+    mut ais: Query<&mut Ai>,
+    mut net: Option<ResMut<Net>>,
+    mut recording: Option<ResMut<Recording>>,
+    mut playback: Option<ResMut<Playback>>,
+    state: Res<State<AppState>>,
+    mut timestep: ResMut<FixedTimestep>,
+    mut sim: ResMut<PongSim>,
+    mut previous: ResMut<PreviousSim>,
+    mut events: EventWriter<PongEvent>
 ) {
-    let window = windows.get_primary().unwrap();
-    let window_height = window.height();
-
-    for (mut paddle, side) in paddles.iter_mut() {
-        match side {
-            Side::LEFT => {
-                if keys.pressed(KeyCode::W) {
-                    paddle.center.y += paddle.speed * time.delta_seconds();
+    if matches!(state.current(), AppState::Bindings | AppState::Stats) {
+        return;
+    }
+    for _ in 0..timestep.advance(time.delta_seconds()) {
+        previous.0 = sim.clone();
+        let mut inputs = *inputs;
+        if playback.is_none() {
+            for mut ai in ais.iter_mut() {
+                let axis = ai.0.update(&sim, timestep.step());
+                inputs.set(ai.0.side, axis);
+            }
+        }
+        let step_events = match (net.as_mut(), playback.as_mut()) {
+            (Some(net), _) => {
+                let axis = inputs.get(net.0.session.local());
+                let step_events = net.0.update(axis).unwrap_or_default();
+                *sim = net.0.session.sim().clone();
+                step_events
+            }
+            (None, Some(playback)) => match playback.inputs.next() {
+                Some(inputs) => sim.step(timestep.step(), inputs),
+                None => {
+                    end_playback(playback, &sim);
+                    Vec::new()
                 }
-                if keys.pressed(KeyCode::S) {
-                    paddle.center.y -= paddle.speed * time.delta_seconds();
+            },
+            (None, None) => {
+                if let Some(recording) = recording.as_mut() {
+                    recording.replay.record(inputs);
                 }
-
-                paddle.center.x = (paddle.width - window.width()) / 2.0;
+                sim.step(timestep.step(), inputs)
             }
-            Side::RIGHT => {
-                if keys.pressed(KeyCode::Up) {
-                    paddle.center.y += paddle.speed * time.delta_seconds();
-                }
-                if keys.pressed(KeyCode::Down) {
-                    paddle.center.y -= paddle.speed * time.delta_seconds();
+        };
+        for event in step_events {
+            if let PongEvent::Goal(_) = event {
+                println!("Score: {} - {}", sim.score.left, sim.score.right);
+                if let Some(recording) = recording.as_mut() {
+                    save_recording(recording, &sim);
                 }
-
-                paddle.center.x = (window.width() - paddle.width ) / 2.0;
             }
+            events.send(event);
         }
+    }
+}
 
-        if paddle.center.y > (window_height - paddle.height) / 2.0 {
-            paddle.center.y = (window_height - paddle.height) / 2.0;
-        }
-        if paddle.center.y < (paddle.height - window_height) / 2.0 {
-            paddle.center.y = (paddle.height - window_height) / 2.0
-        }
+/// Saved after every point, so that quitting keeps the match up to there.
+fn save_recording(recording: &mut Recording, sim: &PongSim) {
+    recording.replay.finish(sim);
+    match recording.replay.save(&recording.path) {
+        Ok(()) => println!("Saved the replay to {}", recording.path.display()),
+        Err(error) => eprintln!("{}", error)
     }
 }
 
-fn collision(
-    mut balls: Query<&mut Ball>,
-    paddles: Query<&Paddle>
-) {
-    // rebond sur les palettes
-    for mut ball in balls.iter_mut() {
-        for paddle in paddles.iter() {
-            if point_in_rect(
-                ball.center.x, ball.center.y,
-                paddle.center.x - paddle.width / 2.0 - BALL_RADIUS,
-                paddle.center.y - paddle.height / 2.0 - BALL_RADIUS,
-                paddle.center.x + paddle.width / 2.0 + BALL_RADIUS,
-                paddle.center.y + paddle.height / 2.0 + BALL_RADIUS
-            ) {
-                if ball.velocity.x < 0.0 {
-                    ball.center.x = ball.center.x + (paddle.center.x + paddle.width / 2.0 + BALL_RADIUS - ball.center.x) * 2.0;
-                } 
-                else {
-                    ball.center.x = ball.center.x + (paddle.center.x - paddle.width / 2.0 - BALL_RADIUS - ball.center.x) * 2.0;
-                }
-                ball.velocity.x = -ball.velocity.x;
-            }
-        }
+fn end_playback(playback: &mut Playback, sim: &PongSim) {
+    if playback.over {
+        return;
+    }
+    playback.over = true;
+    if sim.score == playback.replay.score && sim.ball == playback.replay.ball {
+        println!("End of the replay, it played as recorded");
+    }
+    else {
+        eprintln!(
+            "End of the replay with {:?}, it was recorded with {:?}", sim.score, playback.replay.score
+        );
     }
 }
 
-fn apply_transform(mut set: QuerySet<(
-    Query<(&mut Transform, &Ball)>,
-    Query<(&mut Transform, &Paddle)>
-)>) {
-    for (mut transform,  ball) in set.q0_mut().iter_mut() {
-        transform.translation.x = ball.center.x;
-        transform.translation.y = ball.center.y;
+fn apply_transform(
+    sim: Res<PongSim>,
+    previous: Res<PreviousSim>,
+    timestep: Res<FixedTimestep>,
+    mut set: QuerySet<(
+        Query<&mut Transform, With<Ball>>,
+        Query<(&mut Transform, &Side), With<Paddle>>
+    )>
+) {
+    let frame = sim.frame(&previous.0, timestep.alpha());
+
+    for mut transform in set.q0_mut().iter_mut() {
+        transform.translation.x = frame.ball.x;
+        transform.translation.y = frame.ball.y;
+        transform.scale = ball_scale(&sim.config);
     }
-    for (mut transform, paddle) in set.q1_mut().iter_mut() {
-        transform.translation.x = paddle.center.x;
-        transform.translation.y = paddle.center.y;
+    for (mut transform, side) in set.q1_mut().iter_mut() {
+        let paddle = frame.paddle(*side);
+        transform.translation.x = paddle.x;
+        transform.translation.y = paddle.y;
+        // grown or shrunk by the power-ups
+        transform.scale = paddle_scale(&sim.config);
+        transform.scale.y *= sim.paddle_height(*side) / sim.config.paddle_height;
     }
 }
 
-fn point_in_rect(x: f32, y: f32, left: f32, bottom: f32, right: f32, top: f32) -> bool {
-    x >= left && x <= right && y >= bottom && y <= top
-}
\ No newline at end of file
+fn paddle_scale(config: &PongConfig) -> Vec3 {
+    Vec3::new(config.paddle_width / PADDLE_SPRITE_WIDTH, config.paddle_height / PADDLE_SPRITE_HEIGHT, 1.0)
+}
+
+fn ball_scale(config: &PongConfig) -> Vec3 {
+    let scale = config.ball_radius * 2.0 / BALL_SPRITE_SIZE;
+    Vec3::new(scale, scale, 1.0)
+}
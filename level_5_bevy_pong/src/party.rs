@@ -0,0 +1,245 @@
+//! Four-player matches, `--party` on the command line: the left and right
+//! paddles keep their controls, the top and bottom ones are played with the
+//! keyboard or the third and fourth gamepads.
+
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::prelude::*;
+use pong_sim::{Difficulty, FixedTimestep, Inputs, PartyEvent, PartyInputs, PartySim, PongConfig, Wall};
+
+use crate::{
+    ball_scale, paddle_scale, spawn_game_over, AppState, Atlases, Ball, CountdownText, Paddle, Players
+};
+
+/// A paddle of a four-player match played by the computer.
+struct PartyAi(Difficulty);
+
+/// State of the simulation before its last step, to interpolate the rendering.
+struct PreviousParty(PartySim);
+
+/// Lives and points of the player of a wall.
+struct LivesText;
+
+/// Keys of the top and bottom players, toward negative then positive x.
+const TOP_KEYS: [KeyCode; 2] = [KeyCode::J, KeyCode::L];
+const BOTTOM_KEYS: [KeyCode; 2] = [KeyCode::Numpad4, KeyCode::Numpad6];
+
+/// Gamepads of the top and bottom players, the first two play left and right.
+const TOP_GAMEPAD: usize = 2;
+const BOTTOM_GAMEPAD: usize = 3;
+
+pub fn add_systems(app: &mut AppBuilder, config: PongConfig) {
+    app.add_system_set(SystemSet::on_enter(AppState::Running)
+            .with_system(setup_ball.system())
+            .with_system(setup_paddles.system())
+            .with_system(setup_lives.system()))
+        .add_system_set(SystemSet::on_enter(AppState::GameOver).with_system(setup_game_over.system()))
+        .add_system_set(SystemSet::on_update(AppState::GameOver).with_system(restart.system()))
+        .insert_resource(PreviousParty(PartySim::new(config.clone())))
+        .insert_resource(PartySim::new(config))
+        .init_resource::<PartyInputs>()
+        .add_event::<PartyEvent>()
+        .add_system(read_inputs.system().label("party inputs").after("inputs"))
+        .add_system(step_simulation.system().label("simulation").after("party inputs"))
+        .add_system(apply_transform.system().after("simulation"))
+        .add_system(check_winner.system().after("simulation"))
+        .add_system(countdown.system().after("simulation"))
+        .add_system(lives_board.system());
+}
+
+fn setup_ball(mut commands: Commands, sim: Res<PartySim>, atlases: Res<Atlases>) {
+    commands
+        .spawn_bundle(SpriteSheetBundle {
+            sprite: TextureAtlasSprite::new(1),
+            texture_atlas: atlases.main.clone(),
+            transform: Transform {
+                translation: Vec3::new(sim.ball.position.x, sim.ball.position.y, 0.0),
+                scale: ball_scale(&sim.config),
+                rotation: Quat::IDENTITY
+            },
+            ..Default::default()
+        })
+        .insert(Ball);
+}
+
+/// The sprite of the side paddles, turned a quarter for the top and bottom ones.
+fn setup_paddles(mut commands: Commands, sim: Res<PartySim>, players: Res<Players>, atlases: Res<Atlases>) {
+    for paddle in sim.paddles.iter() {
+        let rotation = if paddle.wall.is_vertical() { Quat::IDENTITY } else { Quat::from_rotation_z(FRAC_PI_2) };
+        let mut entity = commands
+            .spawn_bundle(SpriteSheetBundle {
+                sprite: TextureAtlasSprite::new(0),
+                texture_atlas: atlases.main.clone(),
+                transform: Transform {
+                    translation: Vec3::new(paddle.position.x, paddle.position.y, 0.0),
+                    scale: paddle_scale(&sim.config),
+                    rotation
+                },
+                ..Default::default()
+            });
+        entity.insert(Paddle).insert(paddle.wall);
+
+        if let Some(difficulty) = players.get_wall(paddle.wall) {
+            entity.insert(PartyAi(difficulty));
+        }
+    }
+}
+
+/// A score next to each wall.
+fn setup_lives(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let style = TextStyle {
+        font: asset_server.load("fonts/square.ttf"),
+        font_size: 30.0,
+        color: Color::GOLD,
+    };
+    let places = [
+        (Wall::Left, Rect { left: Val::Px(40.0), top: Val::Percent(45.0), ..Default::default() }),
+        (Wall::Right, Rect { right: Val::Px(40.0), top: Val::Percent(45.0), ..Default::default() }),
+        (Wall::Top, Rect { left: Val::Percent(42.0), top: Val::Px(40.0), ..Default::default() }),
+        (Wall::Bottom, Rect { left: Val::Percent(42.0), bottom: Val::Px(40.0), ..Default::default() })
+    ];
+
+    for (wall, position) in places.iter() {
+        commands
+            .spawn_bundle(TextBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: *position,
+                    ..Default::default()
+                },
+                text: Text::with_section("", style.clone(), Default::default()),
+                ..Default::default()
+            })
+            .insert(LivesText)
+            .insert(*wall);
+    }
+}
+
+fn lives_board(sim: Res<PartySim>, mut query: Query<(&mut Text, &Wall), With<LivesText>>) {
+    for (mut text, wall) in query.iter_mut() {
+        let points = sim.points[wall.index()];
+        text.sections[0].value = match sim.lives[wall.index()] {
+            0 => format!("{:02} OUT", points),
+            lives => format!("{:02} {}", points, "*".repeat(lives as usize))
+        };
+    }
+}
+
+/// The left and right players come from the two-player controls.
+fn read_inputs(
+    keys: Res<Input<KeyCode>>,
+    axes: Res<Axis<GamepadAxis>>,
+    inputs: Res<Inputs>,
+    mut party_inputs: ResMut<PartyInputs>
+) {
+    let pressed = |key: KeyCode| if keys.pressed(key) { 1.0 } else { 0.0 };
+    let player = |keys: [KeyCode; 2], gamepad: usize| {
+        let stick = axes.get(GamepadAxis(Gamepad(gamepad), GamepadAxisType::LeftStickX)).unwrap_or(0.0);
+        let stick = if stick.abs() > 0.2 { stick } else { 0.0 };
+        (pressed(keys[1]) - pressed(keys[0]) + stick).clamp(-1.0, 1.0)
+    };
+
+    party_inputs.set(Wall::Left, inputs.left);
+    party_inputs.set(Wall::Right, inputs.right);
+    party_inputs.set(Wall::Top, player(TOP_KEYS, TOP_GAMEPAD));
+    party_inputs.set(Wall::Bottom, player(BOTTOM_KEYS, BOTTOM_GAMEPAD));
+}
+
+/// The computer overrides the players for its paddles, deciding at every step.
+fn step_simulation(
+    time: Res<Time>,
+    inputs: Res<PartyInputs>,
+    ais: Query<(&Wall, &PartyAi)>,
+    state: Res<State<AppState>>,
+    mut timestep: ResMut<FixedTimestep>,
+    mut sim: ResMut<PartySim>,
+    mut previous: ResMut<PreviousParty>,
+    mut events: EventWriter<PartyEvent>
+) {
+    if *state.current() == AppState::Bindings {
+        return;
+    }
+    for _ in 0..timestep.advance(time.delta_seconds()) {
+        previous.0 = sim.clone();
+        let mut inputs = *inputs;
+        for (wall, ai) in ais.iter() {
+            inputs.set(*wall, sim.follow_ball(*wall, ai.0));
+        }
+        for event in sim.step(timestep.step(), inputs) {
+            match event {
+                PartyEvent::Goal { conceder, scorer: Some(scorer) } => println!("{:?} scores on {:?}", scorer, conceder),
+                PartyEvent::Eliminated(wall) => println!("{:?} is out", wall),
+                _ => {}
+            }
+            events.send(event);
+        }
+    }
+}
+
+/// Knocked out paddles are hidden, their wall is closed.
+fn apply_transform(
+    sim: Res<PartySim>,
+    previous: Res<PreviousParty>,
+    timestep: Res<FixedTimestep>,
+    mut set: QuerySet<(
+        Query<&mut Transform, With<Ball>>,
+        Query<(&mut Transform, &mut Visible, &Wall), With<Paddle>>
+    )>
+) {
+    let frame = sim.frame(&previous.0, timestep.alpha());
+
+    for mut transform in set.q0_mut().iter_mut() {
+        transform.translation.x = frame.ball.x;
+        transform.translation.y = frame.ball.y;
+    }
+    for (mut transform, mut visible, wall) in set.q1_mut().iter_mut() {
+        let paddle = frame.paddle(*wall);
+        transform.translation.x = paddle.x;
+        transform.translation.y = paddle.y;
+        visible.is_visible = sim.is_alive(*wall);
+    }
+}
+
+fn countdown(sim: Res<PartySim>, mut query: Query<&mut Text, With<CountdownText>>) {
+    for mut text in query.iter_mut() {
+        text.sections[0].value = match sim.serve_countdown() {
+            Some(seconds) => format!("{}", seconds.ceil()),
+            None => String::new()
+        };
+    }
+}
+
+fn check_winner(sim: Res<PartySim>, mut state: ResMut<State<AppState>>) {
+    if sim.winner().is_some() && *state.current() == AppState::Running {
+        let _ = state.push(AppState::GameOver);
+    }
+}
+
+fn setup_game_over(
+    mut commands: Commands,
+    sim: Res<PartySim>,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>
+) {
+    let winner = match sim.winner() {
+        Some(Wall::Left) => "LEFT PLAYER WINS",
+        Some(Wall::Right) => "RIGHT PLAYER WINS",
+        Some(Wall::Top) => "TOP PLAYER WINS",
+        Some(Wall::Bottom) => "BOTTOM PLAYER WINS",
+        None => "GAME OVER"
+    };
+    spawn_game_over(&mut commands, &asset_server, &mut materials, winner);
+}
+
+fn restart(
+    keys: Res<Input<KeyCode>>,
+    mut sim: ResMut<PartySim>,
+    mut previous: ResMut<PreviousParty>,
+    mut state: ResMut<State<AppState>>
+) {
+    if keys.just_pressed(KeyCode::Space) {
+        sim.restart();
+        previous.0 = sim.clone();
+        let _ = state.pop();
+    }
+}
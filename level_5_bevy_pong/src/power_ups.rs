@@ -0,0 +1,139 @@
+//! Power-ups of the two-player matches: the ones waiting in the arena, the
+//! extra balls of a multi-ball and what each player caught.
+
+use bevy::prelude::*;
+use pong_sim::{FixedTimestep, PongSim, PowerUpKind, Side};
+
+use crate::{ball_scale, AppState, Atlases, PreviousSim};
+
+struct PowerUpSprite;
+
+struct ExtraBall;
+
+/// The power-ups a player caught, under their score.
+struct EffectText;
+
+pub fn add_systems(app: &mut AppBuilder) {
+    app.add_system_set(SystemSet::on_enter(AppState::Running).with_system(setup_effects.system()))
+        .add_system(power_up_sprites.system().after("simulation"))
+        .add_system(extra_balls.system().after("simulation"))
+        .add_system(effects_board.system().after("simulation"));
+}
+
+fn color(kind: PowerUpKind) -> Color {
+    let [r, g, b, a] = kind.color();
+    Color::rgba(r, g, b, a)
+}
+
+fn setup_effects(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let style = TextStyle {
+        font: asset_server.load("fonts/square.ttf"),
+        font_size: 20.0,
+        color: Color::GOLD,
+    };
+    let places = [
+        (Side::Left, Rect { left: Val::Px(40.0), bottom: Val::Px(20.0), ..Default::default() }),
+        (Side::Right, Rect { right: Val::Px(40.0), bottom: Val::Px(20.0), ..Default::default() })
+    ];
+
+    for (side, position) in places.iter() {
+        commands
+            .spawn_bundle(TextBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: *position,
+                    ..Default::default()
+                },
+                text: Text::with_section("", style.clone(), Default::default()),
+                ..Default::default()
+            })
+            .insert(EffectText)
+            .insert(*side);
+    }
+}
+
+/// The kind and the seconds left of each power-up caught by a side.
+fn effects_board(sim: Res<PongSim>, mut query: Query<(&mut Text, &Side), With<EffectText>>) {
+    for (mut text, side) in query.iter_mut() {
+        text.sections[0].value = sim.effects.iter()
+            .filter(|effect| effect.side == *side)
+            .map(|effect| format!("{} {}", effect.kind.name(), effect.remaining.ceil()))
+            .collect::<Vec<_>>()
+            .join("  ");
+    }
+}
+
+/// A ball sprite colored by kind for each power-up in the arena.
+fn power_up_sprites(
+    mut commands: Commands,
+    sim: Res<PongSim>,
+    atlases: Res<Atlases>,
+    mut query: Query<(Entity, &mut Transform, &mut TextureAtlasSprite), With<PowerUpSprite>>
+) {
+    let scale = sim.config.power_up_radius / sim.config.ball_radius;
+    let mut power_ups = sim.power_ups.iter();
+
+    for (entity, mut transform, mut sprite) in query.iter_mut() {
+        match power_ups.next() {
+            Some(power_up) => {
+                transform.translation = Vec3::new(power_up.position.x, power_up.position.y, 0.0);
+                transform.scale = ball_scale(&sim.config) * scale;
+                sprite.color = color(power_up.kind);
+            }
+            None => commands.entity(entity).despawn()
+        }
+    }
+    for power_up in power_ups {
+        let mut sprite = TextureAtlasSprite::new(1);
+        sprite.color = color(power_up.kind);
+        commands
+            .spawn_bundle(SpriteSheetBundle {
+                sprite,
+                texture_atlas: atlases.main.clone(),
+                transform: Transform {
+                    translation: Vec3::new(power_up.position.x, power_up.position.y, 0.0),
+                    scale: ball_scale(&sim.config) * scale,
+                    rotation: Quat::IDENTITY
+                },
+                ..Default::default()
+            })
+            .insert(PowerUpSprite);
+    }
+}
+
+/// The balls of a multi-ball look like the one served.
+fn extra_balls(
+    mut commands: Commands,
+    sim: Res<PongSim>,
+    previous: Res<PreviousSim>,
+    timestep: Res<FixedTimestep>,
+    atlases: Res<Atlases>,
+    mut query: Query<(Entity, &mut Transform), With<ExtraBall>>
+) {
+    let frame = sim.frame(&previous.0, timestep.alpha());
+    let mut balls = frame.extra_balls.iter();
+
+    for (entity, mut transform) in query.iter_mut() {
+        match balls.next() {
+            Some(ball) => {
+                transform.translation.x = ball.x;
+                transform.translation.y = ball.y;
+            }
+            None => commands.entity(entity).despawn()
+        }
+    }
+    for ball in balls {
+        commands
+            .spawn_bundle(SpriteSheetBundle {
+                sprite: TextureAtlasSprite::new(1),
+                texture_atlas: atlases.main.clone(),
+                transform: Transform {
+                    translation: Vec3::new(ball.x, ball.y, 0.0),
+                    scale: ball_scale(&sim.config),
+                    rotation: Quat::IDENTITY
+                },
+                ..Default::default()
+            })
+            .insert(ExtraBall);
+    }
+}
@@ -0,0 +1,150 @@
+//! Statistics of the matches, kept in the user data dir. F2 shows the last
+//! matches and the high scores, the game is paused meanwhile.
+
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use pong_sim::{user_data_dir, Event as PongEvent, History, MatchRecord, MatchStats, PongSim};
+
+use crate::{AppState, Playback};
+
+const HISTORY_PATH: &str = "pong/history.ron";
+
+pub struct Stats {
+    /// The match being played.
+    current: MatchStats,
+    history: History,
+    /// `None` when the system has no data dir, the history is then lost on exit.
+    path: Option<PathBuf>
+}
+
+impl Stats {
+    /// The saved history, or an empty one.
+    pub fn load() -> Stats {
+        let path = user_data_dir().map(|directory| directory.join(HISTORY_PATH));
+        let history = match &path {
+            Some(path) if path.exists() => History::load(path).unwrap_or_else(|error| {
+                eprintln!("{}, starting a new history", error);
+                History::default()
+            }),
+            _ => History::default()
+        };
+        Stats { current: MatchStats::default(), history, path }
+    }
+
+    fn save(&self) {
+        if let Some(path) = &self.path {
+            if let Err(error) = self.history.save(path) {
+                eprintln!("{}", error);
+            }
+        }
+    }
+}
+
+struct StatsOverlay;
+
+pub fn add_systems(app: &mut AppBuilder) {
+    app.insert_resource(Stats::load())
+        .add_system(record_stats.system().after("simulation"))
+        .add_system_set(SystemSet::on_update(AppState::Running).with_system(open_stats.system()))
+        .add_system_set(SystemSet::on_update(AppState::GameOver).with_system(open_stats.system()))
+        .add_system_set(SystemSet::on_enter(AppState::Stats).with_system(setup_stats.system()))
+        .add_system_set(SystemSet::on_update(AppState::Stats).with_system(close_stats.system()))
+        .add_system_set(SystemSet::on_exit(AppState::Stats).with_system(clear_stats.system()));
+}
+
+/// A replay was already counted when it was played.
+fn record_stats(
+    sim: Res<PongSim>,
+    playback: Option<Res<Playback>>,
+    mut events: EventReader<PongEvent>,
+    mut stats: ResMut<Stats>
+) {
+    let events: Vec<PongEvent> = events.iter().copied().collect();
+    stats.current.record(&sim, &events);
+    if !events.iter().any(|event| matches!(event, PongEvent::MatchOver(_))) {
+        return;
+    }
+
+    let current = std::mem::take(&mut stats.current);
+    if playback.is_some() {
+        return;
+    }
+    if let Some(record) = MatchRecord::new(&sim, current) {
+        if let Some(rank) = stats.history.add(record) {
+            println!("New high score, number {}", rank + 1);
+        }
+        stats.save();
+    }
+}
+
+fn open_stats(keys: Res<Input<KeyCode>>, mut state: ResMut<State<AppState>>) {
+    if keys.just_pressed(KeyCode::F2) {
+        // a restart or the bindings menu asked for in the same frame wins
+        let _ = state.push(AppState::Stats);
+    }
+}
+
+fn close_stats(keys: Res<Input<KeyCode>>, mut state: ResMut<State<AppState>>) {
+    if keys.just_pressed(KeyCode::F2) || keys.just_pressed(KeyCode::Escape) {
+        let _ = state.pop();
+    }
+}
+
+fn setup_stats(
+    mut commands: Commands,
+    stats: Res<Stats>,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>
+) {
+    let style = TextStyle {
+        font: asset_server.load("fonts/square.ttf"),
+        font_size: 22.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                position_type: PositionType::Absolute,
+                ..Default::default()
+            },
+            material: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.8).into()),
+            ..Default::default()
+        })
+        .insert(StatsOverlay)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    "STATS",
+                    TextStyle { font_size: 60.0, color: Color::GOLD, ..style.clone() },
+                    Default::default()
+                ),
+                ..Default::default()
+            });
+            for line in stats.history.lines() {
+                parent.spawn_bundle(TextBundle {
+                    text: Text::with_section(line, style.clone(), Default::default()),
+                    ..Default::default()
+                });
+            }
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    "escape to go back",
+                    TextStyle { font_size: 20.0, ..style },
+                    Default::default()
+                ),
+                ..Default::default()
+            });
+        });
+}
+
+fn clear_stats(mut commands: Commands, query: Query<Entity, With<StatsOverlay>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -1,8 +1,10 @@
-use bevy::{prelude::*, tasks::ComputeTaskPool};
+use bevy::{core::{FixedTimestep, FixedTimesteps}, prelude::*, tasks::ComputeTaskPool};
 use bevy_prototype_lyon::prelude::*;
 use bevy_egui::EguiPlugin;
 use bevy_inspector_egui::{Inspectable, InspectableRegistry, InspectorPlugin, WorldInspectorPlugin, plugin::InspectorWindows};
+use bevy_letterbox::{GameArea, LetterboxCamera};
 use rand::{thread_rng, Rng};
+use spatial_grid::PayloadGrid;
 
 mod fps_plugin;
 
@@ -10,6 +12,8 @@ const SELECT_DISTANCE: f32 = 50.0 * 50.0;
 const BOIDS_COUNT: usize = 2000;
 const BOID_SIZE: f32 = 0.1;
 const BOID_BASE_VELOCITY: f32 = 100.0;
+const SIMULATION_RATE: f64 = 120.0;
+const SIMULATION_TIMESTEP: &str = "simulation_timestep";
 
 #[derive(Inspectable)]
 struct BoidsParams {
@@ -60,8 +64,12 @@ struct Boid {
     pub repulsion: Vec3
 }
 
+/// The simulated state of a boid, the `Transform` only shows it, interpolated
+/// between the last two fixed steps.
 #[derive(Inspectable, Default)]
 struct Physics {
+    pub position: Vec3,
+    pub previous_position: Vec3,
     pub velocity: Vec3,
     pub acceleration: Vec3
 }
@@ -76,7 +84,10 @@ struct SelectedBoid {
 
 struct SelectShape;
 
-struct MainCamera;
+/// The entity and velocity of the boids bucketed by position, rebuilt every
+/// step by the `grid_system` for the `boids_system` to find the neighbours of
+/// each boid.
+type BoidGrid = PayloadGrid<(Entity, Vec3)>;
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 enum GameState {
@@ -84,18 +95,12 @@ enum GameState {
     Running
 }
 
-struct GameArea {
-    width: f32,
-    height: f32,
-    demi_width: f32,
-    demi_height: f32
-}
-
 fn main() {
     App::build()
         .insert_resource(Msaa { samples: 4 })
         .add_plugins(DefaultPlugins)
         .add_plugin(fps_plugin::FpsPlugin)
+        .add_plugin(bevy_letterbox::LetterboxPlugin)
         .add_plugin(ShapePlugin)
         .add_plugin(EguiPlugin)
         .add_plugin(WorldInspectorPlugin::new())
@@ -104,18 +109,22 @@ fn main() {
         .insert_resource(ClearColor(Color::rgb(0.592156, 0.796078, 0.941176)))
         .insert_resource(SelectedBoid { boid: None, ..Default::default() })
         .insert_resource(BoidsParams::default())
-        .insert_resource(GameArea { width: 800.0, height: 600.0, demi_width: 400.0, demi_height: 300.0 })
+        .insert_resource(GameArea::new(800.0, 600.0))
+        .insert_resource(BoidGrid::default())
         .add_state(GameState::Running)
-        .add_startup_system(setup.system())
         .add_startup_system(spawn_boids.system())
         .add_startup_system(spawn_selected_shape.system())
         .add_startup_system(setup_inspector.system())
-        .add_system(boids_system.system())
         .add_system_set(
-            SystemSet::on_update(GameState::Running)
-                .with_system(move_system.system())
+            SystemSet::new()
+                .label("simulation")
+                .with_run_criteria(FixedTimestep::step(1.0 / SIMULATION_RATE).with_label(SIMULATION_TIMESTEP))
+                .with_system(grid_system.system().label("grid"))
+                .with_system(boids_system.system().label("boids").after("grid"))
+                .with_system(move_system.system().label("move").after("boids"))
+                .with_system(wrap_system.system().after("move"))
         )
-        .add_system(wrap_system.system())
+        .add_system(interpolate_system.system().after("simulation"))
         .add_system(select_system.system())
         .add_system(display_selected_system.system())
         .add_system(toggle_inspector.system())
@@ -126,13 +135,6 @@ fn main() {
         .run();
 }
 
-fn setup(
-    mut commands: Commands
-) {
-    commands.spawn_bundle(OrthographicCameraBundle::new_2d())
-        .insert(MainCamera);
-}
-
 fn spawn_boids(
     mut commands: Commands, 
     game_area: Res<GameArea>,
@@ -145,8 +147,8 @@ fn spawn_boids(
     for _ in 0..BOIDS_COUNT {
         println!("add boid");
         let position = Vec3::new(
-            rng.gen_range(-game_area.demi_width..game_area.demi_width),
-            rng.gen_range(-game_area.demi_height..game_area.demi_height),
+            rng.gen_range(-game_area.demi_width()..game_area.demi_width()),
+            rng.gen_range(-game_area.demi_height()..game_area.demi_height()),
             0.0
         );
         let rotation = rng.gen_range(0.0..std::f32::consts::TAU);
@@ -163,6 +165,8 @@ fn spawn_boids(
             })
             .insert(Boid {..Default::default()})
             .insert(Physics {
+                position,
+                previous_position: position,
                 velocity: Vec3::new(rotation.cos() * BOID_BASE_VELOCITY, rotation.sin() * BOID_BASE_VELOCITY, 0.0),
                 acceleration: Vec3::new(0.0, 0.0, 0.0)
             });
@@ -230,15 +234,22 @@ fn inspector_system(
 }
 
 fn move_system(
-    time: Res<Time>,
+    game_state: Res<State<GameState>>,
     boids_params: Res<BoidsParams>,
-    boids: Query<(&mut Transform, &mut Physics, &Boid), With<Boid>>
+    boids: Query<(&mut Physics, &Boid)>
 ) {
-    let dt = time.delta_seconds();
+    if *game_state.current() == GameState::Paused {
+        boids.for_each_mut(|(mut physics, _boid)| {
+            physics.previous_position = physics.position;
+        });
+        return;
+    }
+
+    let dt = (1.0 / SIMULATION_RATE) as f32;
     let min_velocity_sq = boids_params.boid_min_velocity * boids_params.boid_min_velocity;
     let max_velocity_sq = boids_params.boid_max_velocity * boids_params.boid_max_velocity;
 
-    boids.for_each_mut(|(mut transform, mut physics, boid)| {
+    boids.for_each_mut(|(mut physics, boid)| {
         // acceleration
         physics.acceleration = boid.alignment + boid.cohesion + boid.repulsion;
         // avoid over acceleration
@@ -259,40 +270,81 @@ fn move_system(
         }
 
 
-        transform.translation = transform.translation + physics.velocity * dt;
-        transform.rotation = Quat::from_rotation_z(f32::atan2(physics.velocity.y, physics.velocity.x));
+        physics.previous_position = physics.position;
+        physics.position = physics.position + physics.velocity * dt;
     });
 }
 
 fn wrap_system(
     game_area: Res<GameArea>,
-    boids: Query<&mut Transform, With<Boid>>
+    boids: Query<&mut Physics, With<Boid>>
 ) {
-    boids.for_each_mut(|mut transform| {
+    boids.for_each_mut(|mut physics| {
         // wrap boid around edge
-        if transform.translation.x < -game_area.demi_width {
-            transform.translation.x += game_area.width;
+        let mut offset = Vec3::ZERO;
+        if physics.position.x < -game_area.demi_width() {
+            offset.x += game_area.width;
         }
-        if transform.translation.x > game_area.demi_width {
-            transform.translation.x -= game_area.width * 2.0;
+        if physics.position.x > game_area.demi_width() {
+            offset.x -= game_area.width;
         }
-        if transform.translation.y < -game_area.demi_height {
-            transform.translation.y += game_area.height * 2.0;
+        if physics.position.y < -game_area.demi_height() {
+            offset.y += game_area.height;
         }
-        if transform.translation.y > game_area.demi_height {
-            transform.translation.y -= game_area.height * 2.0;
+        if physics.position.y > game_area.demi_height() {
+            offset.y -= game_area.height;
         }
+
+        // move the previous position too, the boid must not be drawn crossing the screen
+        physics.position += offset;
+        physics.previous_position += offset;
     });
 }
 
+fn interpolate_system(
+    fixed_timesteps: Res<FixedTimesteps>,
+    boids: Query<(&mut Transform, &Physics), With<Boid>>
+) {
+    let alpha = fixed_timesteps.get(SIMULATION_TIMESTEP)
+        .map(|timestep| timestep.overstep_percentage() as f32)
+        .unwrap_or(1.0);
+
+    boids.for_each_mut(|(mut transform, physics)| {
+        transform.translation = physics.previous_position.lerp(physics.position, alpha);
+        transform.rotation = Quat::from_rotation_z(f32::atan2(physics.velocity.y, physics.velocity.x));
+    });
+}
+
+fn grid_system(
+    mut grid: ResMut<BoidGrid>,
+    game_area: Res<GameArea>,
+    boids_params: Res<BoidsParams>,
+    boids: Query<(Entity, &Physics), With<Boid>>
+) {
+    let area = [game_area.width, game_area.height];
+    // one cell away at most
+    let cell_size = boids_params.cohesion_radius
+        .max(boids_params.alignment_radius)
+        .max(boids_params.repulsion_radius)
+        .max(1.0);
+    let boids = boids.iter()
+        .map(|(entity, physics)| ([physics.position.x, physics.position.y], (entity, physics.velocity)));
+    grid.rebuild(area, cell_size, boids);
+}
+
+/// Steer each boid from its neighbours, found in the `BoidGrid`. The
+/// neighbours are seen across the edges of the area.
 fn boids_system(
-    mut boids: Query<(Entity, &mut Boid, &Physics, &Transform)>,
+    mut boids: Query<(Entity, &mut Boid, &Physics)>,
     pool: Res<ComputeTaskPool>,
     boids_params: Res<BoidsParams>,
-    others: Query<(Entity, &Physics, &Transform), With<Boid>>
+    grid: Res<BoidGrid>
 ) {
-    boids.par_for_each_mut(&pool, 32, |(boid_entity, mut boid, physics, transform)| {
-    // for (boid_entity, mut boid, physics, transform) in boids.iter_mut() {
+    let radius = boids_params.cohesion_radius
+        .max(boids_params.alignment_radius)
+        .max(boids_params.repulsion_radius);
+
+    boids.par_for_each_mut(&pool, 32, |(boid_entity, mut boid, physics)| {
         let mut cohesion_position: Vec3 = Vec3::ZERO;
         let mut cohesion_count: f32 = 0.0;
         let mut alignment_direction: Vec3 = Vec3::ZERO;
@@ -303,29 +355,30 @@ fn boids_system(
         let alignment_radius_sq = boids_params.alignment_radius * boids_params.alignment_radius;
         let repulstion_radius_sq = boids_params.repulsion_radius * boids_params.repulsion_radius;
 
-        for (other_entity, other_physics, other_transform) in others.iter() {
+        grid.query([physics.position.x, physics.position.y], radius, |&(entity, velocity), offset, distance_sq| {
             // avoid self
-            if boid_entity == other_entity {
-                continue;
+            if entity == boid_entity {
+                return;
             }
 
-            let distance: Vec3 = transform.translation - other_transform.translation;
-            let distance_sq = distance.length_squared();
+            // the short way around the area
+            let offset = Vec3::new(offset[0], offset[1], 0.0);
+            let distance: Vec3 = -offset;
             let angle = distance.angle_between(physics.velocity);
 
             if angle < boids_params.boid_dead_angle {
-                continue;
+                return;
             }
             
             // cohesion
             if distance_sq < cohesion_radius_sq {
-                cohesion_position += other_transform.translation;
+                cohesion_position += physics.position + offset;
                 cohesion_count += 1.0;
             }
 
             // alignment
             if distance_sq < alignment_radius_sq {
-                alignment_direction += other_physics.velocity;
+                alignment_direction += velocity;
                 alignment_count += 1.0; 
             }
 
@@ -333,11 +386,11 @@ fn boids_system(
             if distance_sq < repulstion_radius_sq {
                 repulsion_force += (distance / distance_sq.sqrt()) * boids_params.repulsion_factor;
             }
-        }
+        });
 
         // cohesion
         if cohesion_count > 0.0 { 
-            boid.cohesion = (cohesion_position / cohesion_count - transform.translation) / boids_params.cohesion_radius * boids_params.cohesion_factor;
+            boid.cohesion = (cohesion_position / cohesion_count - physics.position) / boids_params.cohesion_radius * boids_params.cohesion_factor;
         }
         else {
             boid.cohesion = Vec3::ZERO;
@@ -353,7 +406,6 @@ fn boids_system(
 
         // repulsion
         boid.repulsion = repulsion_force;
-    // }
     })
 }
 
@@ -363,17 +415,13 @@ fn select_system(
     buttons: Res<Input<MouseButton>>,
     keys: Res<Input<KeyCode>>,
     windows: Res<Windows>,
-    cameras: Query<&Transform, With<MainCamera>>
+    cameras: Query<&Transform, With<LetterboxCamera>>
 ) {
     if buttons.just_pressed(MouseButton::Left) {
         if let Some(window) = windows.get_primary() {
             if let Some(position) = window.cursor_position() {
                 if let Ok(cam) = cameras.single() {
-                    let position = Vec3::new(
-                        (position.x - window.width() * 0.5) * cam.scale.x,
-                        (position.y - window.height() * 0.5) * cam.scale.y,
-                        0.0
-                    );
+                    let position = bevy_letterbox::window_to_world(window, cam, position).extend(0.0);
 
                     let mut closest_boid = None;
                     let mut closest_distance_sq = f32::INFINITY;
@@ -433,8 +481,8 @@ fn restart_system(
         let mut rng = thread_rng();
         for (mut physics, mut transform) in boids.iter_mut() {
             let position = Vec3::new(
-                rng.gen_range(-game_area.demi_width..game_area.demi_width),
-                rng.gen_range(-game_area.demi_height..game_area.demi_height),
+                rng.gen_range(-game_area.demi_width()..game_area.demi_width()),
+                rng.gen_range(-game_area.demi_height()..game_area.demi_height()),
                 0.0
             );
             let rotation = rng.gen_range(0.0..std::f32::consts::TAU);
@@ -442,6 +490,8 @@ fn restart_system(
             transform.translation = position;
             transform.rotation = Quat::from_rotation_z(rotation);
             
+            physics.position = position;
+            physics.previous_position = position;
             physics.velocity = Vec3::new(rotation.cos() * BOID_BASE_VELOCITY, rotation.sin() * BOID_BASE_VELOCITY, 0.0);
             physics.acceleration = Vec3::new(0.0, 0.0, 0.0)
         }
@@ -461,25 +511,17 @@ fn pause_system(
     }
 }
 
+/// Only the view changes, the boids keep flying in the same area.
 fn zoom_system(
     keys: Res<Input<KeyCode>>,
-    mut cameras: Query<&mut Transform, With<MainCamera>>,
-    windows: Res<Windows>,
-    mut game_area: ResMut<GameArea>
+    mut cameras: Query<&mut LetterboxCamera>
 ) {
     if let Ok(mut cam) = cameras.single_mut() {
         if keys.just_pressed(KeyCode::NumpadAdd) {
-            cam.scale *= 1.1;
+            cam.zoom /= 1.1;
         }
         if keys.just_pressed(KeyCode::NumpadSubtract) {
-            cam.scale /= 1.1;
-        }
-
-        if let Some(window) = windows.get_primary() {
-            game_area.width = window.width() * cam.scale.x;
-            game_area.height = window.height() * cam.scale.y;
-            game_area.demi_width = game_area.width * 0.5;
-            game_area.demi_height = game_area.height * 0.5;
+            cam.zoom *= 1.1;
         }
     }
 }
\ No newline at end of file
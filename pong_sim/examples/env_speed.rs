@@ -0,0 +1,48 @@
+//! Steps per second of the reinforcement learning environment, with random
+//! actions:
+//!
+//! ```text
+//! cargo run --release --example env_speed -- [steps] [pixel width] [pixel height]
+//! ```
+
+use std::time::Instant;
+
+use pong_sim::{EnvConfig, PongEnv, Rng};
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let number = |index: usize, default: usize| -> usize {
+        args.get(index).map_or(default, |arg| arg.parse().unwrap_or_else(|error| {
+            eprintln!("Invalid number {}: {}", arg, error);
+            std::process::exit(1);
+        }))
+    };
+    let steps = number(0, 1_000_000);
+    let pixels = match (args.get(1), args.get(2)) {
+        (Some(_), Some(_)) => Some((number(1, 0), number(2, 0))),
+        _ => None
+    };
+
+    let mut env = PongEnv::new(EnvConfig { pixels, ..EnvConfig::default() });
+    let mut rng = Rng::new(0);
+    let mut episodes = 0;
+    let mut seed = 0;
+    env.reset(seed);
+
+    let start = Instant::now();
+    for _ in 0..steps {
+        let action = (rng.next_u64() % PongEnv::ACTIONS.len() as u64) as usize;
+        let (_, _, done) = env.step_discrete(action);
+        if done {
+            episodes += 1;
+            seed += 1;
+            env.reset(seed);
+        }
+    }
+    let seconds = start.elapsed().as_secs_f64();
+
+    println!(
+        "{} steps in {:.2}s, {:.0} steps per second, {} episodes",
+        steps, seconds, steps as f64 / seconds, episodes
+    );
+}
@@ -0,0 +1,117 @@
+//! A networked match between two computer players, one in each process:
+//!
+//! ```text
+//! cargo run --example netplay -- 127.0.0.1:7000 127.0.0.1:7001 left
+//! cargo run --example netplay -- 127.0.0.1:7001 127.0.0.1:7000 right --latency 60 --loss 0.1
+//! ```
+//!
+//! `--latency <ms>`, `--jitter <ms>` and `--loss <0..1>` degrade the outgoing
+//! packets. Both processes print the same final score.
+
+use std::net::SocketAddr;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use pong_sim::{
+    AiController, Difficulty, Event, FixedTimestep, LossyTransport, Peer, PongConfig, RollbackSession,
+    RollbackSettings, Side, UdpTransport
+};
+
+struct Args {
+    local: SocketAddr,
+    remote: SocketAddr,
+    side: Side,
+    latency: Duration,
+    jitter: Duration,
+    loss: f32
+}
+
+impl Args {
+    fn parse() -> Result<Args, String> {
+        let mut args = std::env::args().skip(1);
+        let mut next = |name: &str| args.next().ok_or(format!("Missing {}", name));
+
+        let local = next("local address")?;
+        let remote = next("remote address")?;
+        let side = match next("side")?.as_str() {
+            "left" => Side::Left,
+            "right" => Side::Right,
+            side => return Err(format!("Unknown side {}, expected left or right", side))
+        };
+        let mut parsed = Args {
+            local: local.parse().map_err(|error| format!("{}: {}", local, error))?,
+            remote: remote.parse().map_err(|error| format!("{}: {}", remote, error))?,
+            side,
+            latency: Duration::ZERO,
+            jitter: Duration::ZERO,
+            loss: 0.0
+        };
+
+        while let Ok(option) = next("option") {
+            let value = next(&option)?;
+            let number = value.parse::<f32>().map_err(|error| format!("{} {}: {}", option, value, error))?;
+            match option.as_str() {
+                "--latency" => parsed.latency = Duration::from_secs_f32(number / 1000.0),
+                "--jitter" => parsed.jitter = Duration::from_secs_f32(number / 1000.0),
+                "--loss" => parsed.loss = number,
+                _ => return Err(format!("Unknown option {}", option))
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+fn main() {
+    let args = Args::parse().unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        eprintln!("Usage: netplay <local address> <remote address> <left|right> [--latency <ms>] [--jitter <ms>] [--loss <0..1>]");
+        std::process::exit(1);
+    });
+
+    let transport = UdpTransport::bind(args.local, args.remote).unwrap_or_else(|error| {
+        eprintln!("Can not bind {}: {}", args.local, error);
+        std::process::exit(1);
+    });
+    let transport = LossyTransport::new(transport, args.latency, args.jitter, args.loss, args.side as u64);
+    // a short match with rallies getting faster
+    let config = PongConfig { ball_acceleration: 1.1, points_to_win: 3, win_by: 1, ..PongConfig::default() };
+    let mut peer = Peer::new(RollbackSession::new(config, args.side, RollbackSettings::default()), transport);
+    let mut ai = AiController::new(args.side, Difficulty::NORMAL, args.side as u64);
+
+    let mut timestep = FixedTimestep::default();
+    let mut last = Instant::now();
+    // a predicted win can still be rolled back, the match is over once confirmed
+    while peer.session.confirmed_sim().winner().is_none() {
+        let now = Instant::now();
+        for _ in 0..timestep.advance((now - last).as_secs_f32()) {
+            if peer.session.sim().winner().is_some() {
+                peer.poll();
+                peer.send();
+                continue;
+            }
+            let axis = ai.update(peer.session.sim(), timestep.step());
+            for event in peer.update(axis).unwrap_or_default() {
+                if let Event::Goal(_) = event {
+                    let score = &peer.session.sim().score;
+                    println!("Score: {} - {}", score.left, score.right);
+                }
+            }
+        }
+        last = now;
+        sleep(Duration::from_millis(1));
+    }
+
+    // the last acknowledgements for the remote peer
+    let end = Instant::now();
+    while end.elapsed() < Duration::from_millis(500) {
+        peer.poll();
+        peer.send();
+        sleep(Duration::from_millis(5));
+    }
+
+    let resimulated = peer.session.resimulated();
+    let sim = peer.session.confirmed_sim();
+    println!("Final score: {} - {}", sim.score.left, sim.score.right);
+    println!("Frames simulated again: {}", resimulated);
+}
@@ -0,0 +1,53 @@
+//! Record a match between two computer players to a replay file:
+//!
+//! ```text
+//! cargo run --example record -- match.ron [seed] [left difficulty] [right difficulty]
+//! ```
+//!
+//! The replay can be played again by the front-ends or kept as a regression
+//! test in `tests/replays`.
+
+use pong_sim::{AiController, Difficulty, FixedTimestep, Inputs, PongConfig, PongSim, Replay, Side};
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let run = || -> Result<Replay, String> {
+        let path = args.first().ok_or("Missing replay file")?;
+        let seed = match args.get(1) {
+            Some(seed) => seed.parse().map_err(|error| format!("Invalid seed {}: {}", seed, error))?,
+            None => 0
+        };
+        let difficulty = |index: usize| match args.get(index) {
+            Some(name) => Difficulty::from_name(name),
+            None => Ok(Difficulty::NORMAL)
+        };
+        let (left, right) = (difficulty(2)?, difficulty(3)?);
+
+        let config = PongConfig { ball_acceleration: 1.1, points_to_win: 3, win_by: 1, ..PongConfig::default() };
+        let step = FixedTimestep::default().step();
+        let mut sim = PongSim::with_seed(config.clone(), seed);
+        let mut replay = Replay::new(config, seed, step);
+        let mut left = AiController::new(Side::Left, left, seed);
+        let mut right = AiController::new(Side::Right, right, seed + 1);
+
+        while sim.winner().is_none() {
+            let inputs = Inputs { left: left.update(&sim, step), right: right.update(&sim, step) };
+            replay.record(inputs);
+            sim.step(step, inputs);
+        }
+        replay.finish(&sim);
+        replay.save(path)?;
+        Ok(replay)
+    };
+
+    match run() {
+        Ok(replay) => println!(
+            "{} steps, final score {} - {}", replay.steps(), replay.score.left, replay.score.right
+        ),
+        Err(error) => {
+            eprintln!("{}", error);
+            eprintln!("Usage: record <replay file> [seed] [left difficulty] [right difficulty]");
+            std::process::exit(1);
+        }
+    }
+}
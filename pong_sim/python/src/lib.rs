@@ -0,0 +1,96 @@
+//! The [`PongEnv`] as a Python module, built from this directory with
+//! `maturin develop`:
+//!
+//! ```python
+//! import pong_sim
+//! env = pong_sim.PongEnv(side="left", opponent="hard", pixels=(80, 60))
+//! state, pixels = env.reset(seed=0)
+//! (state, pixels), reward, done = env.step(1.0)
+//! (state, pixels), reward, done = env.step_discrete(2)
+//! ```
+
+use pyo3::exceptions::{PyIndexError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use pong_sim::{Difficulty, EnvConfig, Observation, PongConfig, PongEnv, Side};
+
+#[pyclass(name = "PongEnv")]
+struct PyPongEnv {
+    env: PongEnv
+}
+
+/// The state as a list of floats and the pixels as bytes, or `None`.
+type PyObservation = (Vec<f32>, Option<Py<PyBytes>>);
+
+fn to_python(py: Python<'_>, observation: Observation) -> PyObservation {
+    let pixels = observation.pixels.map(|pixels| PyBytes::new(py, &pixels.data).unbind());
+    (observation.state.to_vec(), pixels)
+}
+
+#[pymethods]
+impl PyPongEnv {
+    /// `config` is the text of a Pong tuning file, `pixels` the width and
+    /// height of the pixel observations.
+    #[new]
+    #[pyo3(signature = (side = "left", opponent = "normal", config = None, step = 1.0 / 60.0, action_repeat = 1, max_steps = 10_000, pixels = None))]
+    fn new(
+        side: &str,
+        opponent: &str,
+        config: Option<&str>,
+        step: f32,
+        action_repeat: u32,
+        max_steps: u32,
+        pixels: Option<(usize, usize)>
+    ) -> PyResult<Self> {
+        let side = match side {
+            "left" => Side::Left,
+            "right" => Side::Right,
+            _ => return Err(PyValueError::new_err(format!("Unknown side {}, expected left or right", side)))
+        };
+        let opponent = Difficulty::from_name(opponent).map_err(PyValueError::new_err)?;
+        let pong = match config {
+            Some(text) => PongConfig::from_ron(text).map_err(PyValueError::new_err)?,
+            None => PongConfig::default()
+        };
+        let config = EnvConfig { pong, side, opponent, step, action_repeat, max_steps, pixels };
+        Ok(PyPongEnv { env: PongEnv::new(config) })
+    }
+
+    #[pyo3(signature = (seed = 0))]
+    fn reset(&mut self, py: Python<'_>, seed: u64) -> PyObservation {
+        to_python(py, self.env.reset(seed))
+    }
+
+    /// `axis` between -1 and 1.
+    fn step(&mut self, py: Python<'_>, axis: f32) -> (PyObservation, f32, bool) {
+        let (observation, reward, done) = self.env.step(axis);
+        (to_python(py, observation), reward, done)
+    }
+
+    /// `index` of one of `ACTIONS`.
+    fn step_discrete(&mut self, py: Python<'_>, index: usize) -> PyResult<(PyObservation, f32, bool)> {
+        if index >= PongEnv::ACTIONS.len() {
+            return Err(PyIndexError::new_err(format!(
+                "Action {} out of range, expected less than {}",
+                index,
+                PongEnv::ACTIONS.len()
+            )));
+        }
+        let (observation, reward, done) = self.env.step_discrete(index);
+        Ok((to_python(py, observation), reward, done))
+    }
+
+    #[classattr]
+    #[allow(non_snake_case)]
+    fn ACTIONS() -> Vec<f32> {
+        PongEnv::ACTIONS.to_vec()
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "pong_sim")]
+fn python_module(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyPongEnv>()?;
+    Ok(())
+}
@@ -0,0 +1,202 @@
+use crate::rng::Rng;
+use crate::sim::{PongSim, Side};
+
+/// How well a computer player plays.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Difficulty {
+    /// Seconds before reacting to a new ball direction.
+    pub reaction_delay: f32,
+    /// Fraction of the paddle speed the computer uses, between 0 and 1.
+    pub max_speed: f32,
+    /// The aimed point is off by up to this many arena units.
+    pub prediction_error: f32
+}
+
+impl Difficulty {
+    pub const EASY: Difficulty = Difficulty { reaction_delay: 0.4, max_speed: 0.6, prediction_error: 60.0 };
+    pub const NORMAL: Difficulty = Difficulty { reaction_delay: 0.2, max_speed: 0.85, prediction_error: 30.0 };
+    pub const HARD: Difficulty = Difficulty { reaction_delay: 0.05, max_speed: 1.0, prediction_error: 5.0 };
+
+    /// Parse `easy`, `normal` or `hard`.
+    pub fn from_name(name: &str) -> Result<Difficulty, String> {
+        match name {
+            "easy" => Ok(Difficulty::EASY),
+            "normal" => Ok(Difficulty::NORMAL),
+            "hard" => Ok(Difficulty::HARD),
+            _ => Err(format!("Unknown difficulty {}, expected easy, normal or hard", name))
+        }
+    }
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Difficulty::NORMAL
+    }
+}
+
+/// Computer player for one side, it gives the paddle axis to feed [`Inputs`](crate::Inputs).
+#[derive(Debug, Clone, PartialEq)]
+pub struct AiController {
+    pub side: Side,
+    pub difficulty: Difficulty,
+    rng: Rng,
+    /// Whether the ball was coming toward the paddle on the last update.
+    incoming: bool,
+    /// Time left before choosing a new target, `None` once it is chosen.
+    reaction_timer: Option<f32>,
+    target: f32
+}
+
+impl AiController {
+    pub fn new(side: Side, difficulty: Difficulty, seed: u64) -> AiController {
+        AiController {
+            side,
+            difficulty,
+            rng: Rng::new(seed),
+            incoming: false,
+            reaction_timer: None,
+            target: 0.0
+        }
+    }
+
+    /// Axis for the next `dt` seconds, between -1 (down) and 1 (up).
+    pub fn update(&mut self, sim: &PongSim, dt: f32) -> f32 {
+        let intercept = predict_intercept(sim, self.side);
+
+        // the ball changed direction, wait a bit before choosing where to go
+        if intercept.is_some() != self.incoming {
+            self.incoming = intercept.is_some();
+            self.reaction_timer = Some(self.difficulty.reaction_delay);
+        }
+
+        if let Some(timer) = self.reaction_timer {
+            let timer = timer - dt;
+            if timer <= 0.0 {
+                // go back to the center while the ball goes away
+                let error = self.difficulty.prediction_error;
+                self.target = intercept.unwrap_or(0.0) + self.rng.range(-error, error);
+                self.reaction_timer = None;
+            }
+            else {
+                self.reaction_timer = Some(timer);
+            }
+        }
+
+        let paddle = sim.paddle(self.side).position.y;
+        let full_step = sim.config.paddle_speed * dt;
+        if full_step <= 0.0 {
+            return 0.0;
+        }
+
+        // slow down when close to the target instead of shaking around it
+        let max = self.difficulty.max_speed.clamp(0.0, 1.0);
+        ((self.target - paddle) / full_step).clamp(-max, max)
+    }
+}
+
+/// Height at which the ball will reach the paddle of `side`, bouncing on the
+/// top and bottom walls, or `None` when the ball goes the other way. During a
+/// multi-ball, the first ball to arrive.
+pub fn predict_intercept(sim: &PongSim, side: Side) -> Option<f32> {
+    let config = &sim.config;
+    let face = sim.paddle(side).position.x - side.sign() * (config.paddle_width / 2.0 + config.ball_radius);
+    let mut first: Option<(f32, f32)> = None;
+    for ball in sim.balls() {
+        if ball.velocity.x * side.sign() <= 0.0 {
+            continue;
+        }
+        let time = ((face - ball.position.x) / ball.velocity.x).max(0.0);
+        let earlier = match first {
+            Some((first, _)) => time < first,
+            None => true
+        };
+        if earlier {
+            first = Some((time, ball.position.y + ball.velocity.y * time));
+        }
+    }
+    let (_, y) = first?;
+
+    // unfold the bounces: the ball goes back and forth between -top and top
+    let top = config.arena_height / 2.0 - config.ball_radius;
+    if top <= 0.0 {
+        return Some(0.0);
+    }
+    let folded = (y + top).rem_euclid(4.0 * top);
+    let folded = if folded > 2.0 * top { 4.0 * top - folded } else { folded };
+
+    Some(folded - top)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PongConfig;
+    use crate::math::Vec2;
+    use crate::sim::Inputs;
+
+    #[test]
+    fn intercept_without_bounce() {
+        let mut sim = PongSim::new(PongConfig::default());
+        sim.ball.position = Vec2::new(0.0, 0.0);
+        sim.ball.velocity = Vec2::new(370.0, 37.0);
+
+        assert_eq!(predict_intercept(&sim, Side::Right), Some(37.0));
+        assert_eq!(predict_intercept(&sim, Side::Left), None);
+    }
+
+    #[test]
+    fn intercept_folds_wall_bounces() {
+        let mut sim = PongSim::new(PongConfig::default());
+        sim.ball.position = Vec2::new(0.0, 0.0);
+
+        // 370 units up reach the top wall at 290 and come back down 80
+        sim.ball.velocity = Vec2::new(-370.0, 370.0);
+        let y = predict_intercept(&sim, Side::Left).unwrap();
+        assert!((y - 210.0).abs() < 1e-3, "{}", y);
+
+        // two bounces: 1110 units down, 290 to the bottom wall, 580 up to the
+        // top wall and 240 down again
+        sim.ball.velocity = Vec2::new(-370.0, -1110.0);
+        let y = predict_intercept(&sim, Side::Left).unwrap();
+        assert!((y - 50.0).abs() < 1e-3, "{}", y);
+    }
+
+    #[test]
+    fn prediction_matches_the_simulation() {
+        let mut sim = PongSim::new(PongConfig { paddle_height: 600.0, ..PongConfig::default() });
+        sim.ball.velocity = Vec2::new(500.0, 1300.0);
+        let predicted = predict_intercept(&sim, Side::Right).unwrap();
+
+        for _ in 0..1000 {
+            let events = sim.step(1.0 / 120.0, Inputs::default());
+            if events.contains(&crate::Event::PaddleHit(Side::Right)) {
+                break;
+            }
+        }
+        assert!(sim.ball.velocity.x < 0.0);
+        // the paddle covers the whole height, the hit happened at the predicted height
+        let face = sim.paddle(Side::Right).position.x - 20.0;
+        let back = (face - sim.ball.position.x) / -sim.ball.velocity.x;
+        let hit_y = sim.ball.position.y - sim.ball.velocity.y * back;
+        assert!((hit_y - predicted).abs() < 1.0, "{} {}", hit_y, predicted);
+    }
+
+    #[test]
+    fn perfect_ai_reaches_the_ball() {
+        let perfect = Difficulty { reaction_delay: 0.0, max_speed: 1.0, prediction_error: 0.0 };
+        let mut ai = AiController::new(Side::Right, perfect, 1);
+        let mut sim = PongSim::new(PongConfig::default());
+        sim.ball.velocity = Vec2::new(200.0, 90.0);
+
+        let dt = 1.0 / 120.0;
+        for _ in 0..240 {
+            let axis = ai.update(&sim, dt);
+            let events = sim.step(dt, Inputs { left: 0.0, right: axis });
+            assert!(!events.contains(&crate::Event::Goal(Side::Left)));
+            if events.contains(&crate::Event::PaddleHit(Side::Right)) {
+                return;
+            }
+        }
+        panic!("the ball never reached the paddle");
+    }
+}
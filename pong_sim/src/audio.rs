@@ -0,0 +1,415 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::party::{PartyEvent, Wall};
+use crate::sim::{Event, Side};
+
+/// Sound effects of a match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sound {
+    /// The ball bounced on a wall.
+    Bounce,
+    PaddleHit,
+    /// Someone scored.
+    Score,
+    /// The match is over and the listener won, or nobody in particular listens.
+    Win,
+    /// The match is over and the listener lost.
+    Lose
+}
+
+impl Sound {
+    pub const ALL: [Sound; 5] = [Sound::Bounce, Sound::PaddleHit, Sound::Score, Sound::Win, Sound::Lose];
+
+    /// Also the name of its file in the assets of the front-ends.
+    pub fn name(self) -> &'static str {
+        match self {
+            Sound::Bounce => "bounce",
+            Sound::PaddleHit => "paddle_hit",
+            Sound::Score => "score",
+            Sound::Win => "win",
+            Sound::Lose => "lose"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Sound, String> {
+        Sound::ALL.iter().copied().find(|sound| sound.name() == name)
+            .ok_or_else(|| format!("Unknown sound {}", name))
+    }
+}
+
+/// Music tracks, looped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Music {
+    Menu,
+    Game
+}
+
+impl Music {
+    pub const ALL: [Music; 2] = [Music::Menu, Music::Game];
+
+    /// Also the name of its file in the assets of the front-ends.
+    pub fn name(self) -> &'static str {
+        match self {
+            Music::Menu => "menu_music",
+            Music::Game => "game_music"
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Music::Menu => 0,
+            Music::Game => 1
+        }
+    }
+}
+
+/// Volume of a group of sounds.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Channel {
+    /// Between 0 and 1.
+    pub volume: f32,
+    pub muted: bool
+}
+
+impl Channel {
+    /// The volume heard, 0 when muted.
+    pub fn gain(&self) -> f32 {
+        if self.muted { 0.0 } else { self.volume.clamp(0.0, 1.0) }
+    }
+
+    /// Change the volume by `delta`, staying between 0 and 1.
+    pub fn adjust(&mut self, delta: f32) {
+        self.volume = (self.volume + delta).clamp(0.0, 1.0);
+    }
+}
+
+impl Default for Channel {
+    fn default() -> Self {
+        Channel { volume: 1.0, muted: false }
+    }
+}
+
+/// Volumes of each category, the master one scales the others. Saved by the
+/// front-ends next to their bindings.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Mixer {
+    pub master: Channel,
+    pub effects: Channel,
+    pub music: Channel
+}
+
+impl Mixer {
+    /// Volume change of a key press in the front-ends.
+    pub const VOLUME_STEP: f32 = 0.1;
+
+    pub fn effects_gain(&self) -> f32 {
+        self.master.gain() * self.effects.gain()
+    }
+
+    pub fn music_gain(&self) -> f32 {
+        self.master.gain() * self.music.gain()
+    }
+
+    pub fn from_ron(text: &str) -> Result<Mixer, String> {
+        ron::de::from_str(text).map_err(|error| format!("Invalid Pong audio settings: {}", error))
+    }
+
+    pub fn to_ron(&self) -> Result<String, String> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| format!("Can not write the Pong audio settings: {}", error))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Mixer, String> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|error| format!("Can not read {}: {}", path.display(), error))?;
+
+        Mixer::from_ron(&text).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+
+    /// Like [`Mixer::load`], with the default volumes when nothing was saved
+    /// yet.
+    pub fn load_saved<P: AsRef<Path>>(path: P) -> Result<Mixer, String> {
+        if path.as_ref().exists() { Mixer::load(path) } else { Ok(Mixer::default()) }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)
+                .map_err(|error| format!("Can not create {}: {}", directory.display(), error))?;
+        }
+        fs::write(path, self.to_ron()?).map_err(|error| format!("Can not write {}: {}", path.display(), error))
+    }
+}
+
+/// Plays the sounds, one per engine. The volumes are between 0 and 1.
+pub trait AudioBackend {
+    fn play(&mut self, sound: Sound, volume: f32);
+    /// Called every update for each track, a track at 0 can be paused.
+    fn set_music_volume(&mut self, music: Music, volume: f32);
+}
+
+/// Plays nothing and remembers everything, for headless runs and tests. The
+/// front-ends drain it every frame to play the sounds with their engine.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct NullBackend {
+    pub played: Vec<(Sound, f32)>,
+    /// Last volume of each track, in the order of [`Music::ALL`].
+    pub music: [f32; 2]
+}
+
+impl NullBackend {
+    /// The sounds played, without their volume.
+    pub fn sounds(&self) -> Vec<Sound> {
+        self.played.iter().map(|(sound, _)| *sound).collect()
+    }
+}
+
+impl AudioBackend for NullBackend {
+    fn play(&mut self, sound: Sound, volume: f32) {
+        self.played.push((sound, volume));
+    }
+
+    fn set_music_volume(&mut self, music: Music, volume: f32) {
+        self.music[music.index()] = volume;
+    }
+}
+
+/// The sound of a match event, `listener` is the player the win and lose
+/// sounds are for, `None` for two players on the same screen.
+pub fn sound_for(event: &Event, listener: Option<Side>) -> Option<Sound> {
+    match event {
+        Event::WallBounce => Some(Sound::Bounce),
+        Event::PaddleHit(_) => Some(Sound::PaddleHit),
+        Event::Goal(_) => Some(Sound::Score),
+        Event::MatchOver(winner) => Some(result(listener.unwrap_or(*winner) == *winner)),
+        Event::Serve | Event::PowerUp(_, _) | Event::PowerUpOver(_, _) => None
+    }
+}
+
+/// Like [`sound_for`] in a four-player match.
+pub fn party_sound_for(event: &PartyEvent, listener: Option<Wall>) -> Option<Sound> {
+    match event {
+        PartyEvent::WallBounce => Some(Sound::Bounce),
+        PartyEvent::PaddleHit(_) => Some(Sound::PaddleHit),
+        PartyEvent::Goal { .. } => Some(Sound::Score),
+        PartyEvent::Eliminated(wall) if listener == Some(*wall) => Some(Sound::Lose),
+        PartyEvent::MatchOver(winner) => Some(result(listener.unwrap_or(*winner) == *winner)),
+        PartyEvent::Serve | PartyEvent::Eliminated(_) => None
+    }
+}
+
+fn result(won: bool) -> Sound {
+    if won { Sound::Win } else { Sound::Lose }
+}
+
+/// Turns the match events into sounds at the mixer volumes, and fades from
+/// one music track to the other.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioPlayer<B> {
+    pub backend: B,
+    pub mixer: Mixer,
+    /// Seconds to go from one track to the other.
+    pub fade_duration: f32,
+    music: Option<Music>,
+    /// Volume of each track before the mixer, in the order of [`Music::ALL`].
+    fades: [f32; 2]
+}
+
+impl<B: AudioBackend> AudioPlayer<B> {
+    pub const DEFAULT_FADE_DURATION: f32 = 1.5;
+
+    pub fn new(backend: B) -> AudioPlayer<B> {
+        AudioPlayer {
+            backend,
+            mixer: Mixer::default(),
+            fade_duration: AudioPlayer::<B>::DEFAULT_FADE_DURATION,
+            music: None,
+            fades: [0.0; 2]
+        }
+    }
+
+    pub fn play(&mut self, sound: Sound) {
+        let gain = self.mixer.effects_gain();
+        if gain > 0.0 {
+            self.backend.play(sound, gain);
+        }
+    }
+
+    /// Play the sound of each event of a step.
+    pub fn play_events<'a>(&mut self, events: impl IntoIterator<Item = &'a Event>, listener: Option<Side>) {
+        for event in events {
+            if let Some(sound) = sound_for(event, listener) {
+                self.play(sound);
+            }
+        }
+    }
+
+    pub fn music(&self) -> Option<Music> {
+        self.music
+    }
+
+    /// Fade to `music`, or to silence.
+    pub fn set_music(&mut self, music: Option<Music>) {
+        self.music = music;
+    }
+
+    /// Volume of a track as given to the backend.
+    pub fn music_volume(&self, music: Music) -> f32 {
+        self.fades[music.index()] * self.mixer.music_gain()
+    }
+
+    /// Move the fades on by `dt` seconds and give the volumes to the backend.
+    pub fn update(&mut self, dt: f32) {
+        let step = if self.fade_duration > 0.0 { dt / self.fade_duration } else { 1.0 };
+        for &music in Music::ALL.iter() {
+            let target = if self.music == Some(music) { 1.0 } else { 0.0 };
+            let fade = &mut self.fades[music.index()];
+            *fade = if *fade < target { (*fade + step).min(target) } else { (*fade - step).max(target) };
+            let volume = self.music_volume(music);
+            self.backend.set_music_volume(music, volume);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PongConfig;
+    use crate::math::Vec2;
+    use crate::sim::{Inputs, PongSim};
+
+    fn player() -> AudioPlayer<NullBackend> {
+        AudioPlayer::new(NullBackend::default())
+    }
+
+    #[test]
+    fn match_events_play_their_sound() {
+        let mut audio = player();
+        let mut sim = PongSim::new(PongConfig { points_to_win: 1, win_by: 1, ..PongConfig::default() });
+
+        sim.ball.position = Vec2::new(0.0, 285.0);
+        sim.ball.velocity = Vec2::new(0.0, 600.0);
+        audio.play_events(&sim.step(0.1, Inputs::default()), None);
+
+        sim.ball.position = Vec2::new(-360.0, 0.0);
+        sim.ball.velocity = Vec2::new(-300.0, 0.0);
+        audio.play_events(&sim.step(0.1, Inputs::default()), None);
+
+        sim.ball.position = Vec2::new(405.0, 200.0);
+        sim.ball.velocity = Vec2::new(300.0, 0.0);
+        audio.play_events(&sim.step(0.1, Inputs::default()), Some(Side::Right));
+
+        assert_eq!(
+            audio.backend.sounds(),
+            vec![Sound::Bounce, Sound::PaddleHit, Sound::Score, Sound::Lose]
+        );
+    }
+
+    #[test]
+    fn winner_hears_the_win() {
+        let over = Event::MatchOver(Side::Left);
+        assert_eq!(sound_for(&over, Some(Side::Left)), Some(Sound::Win));
+        assert_eq!(sound_for(&over, Some(Side::Right)), Some(Sound::Lose));
+        assert_eq!(sound_for(&over, None), Some(Sound::Win));
+        assert_eq!(sound_for(&Event::Serve, None), None);
+
+        let out = PartyEvent::Eliminated(Wall::Top);
+        assert_eq!(party_sound_for(&out, Some(Wall::Top)), Some(Sound::Lose));
+        assert_eq!(party_sound_for(&out, Some(Wall::Left)), None);
+    }
+
+    #[test]
+    fn sounds_by_name() {
+        for &sound in Sound::ALL.iter() {
+            assert_eq!(Sound::from_name(sound.name()), Ok(sound));
+        }
+        assert!(Sound::from_name("boing").is_err());
+    }
+
+    #[test]
+    fn effects_follow_the_mixer() {
+        let mut audio = player();
+        audio.mixer.master.volume = 0.5;
+        audio.mixer.effects.volume = 0.5;
+        audio.play(Sound::Bounce);
+
+        audio.mixer.effects.muted = true;
+        audio.play(Sound::Bounce);
+        audio.mixer.effects.muted = false;
+        audio.mixer.master.muted = true;
+        audio.play(Sound::Bounce);
+
+        assert_eq!(audio.backend.played, vec![(Sound::Bounce, 0.25)]);
+    }
+
+    #[test]
+    fn music_cross_fades() {
+        let mut audio = player();
+        audio.fade_duration = 1.0;
+        audio.set_music(Some(Music::Menu));
+        audio.update(1.0);
+        assert_eq!(audio.backend.music, [1.0, 0.0]);
+
+        audio.set_music(Some(Music::Game));
+        audio.update(0.25);
+        assert_eq!(audio.backend.music, [0.75, 0.25]);
+        audio.update(0.5);
+        assert_eq!(audio.backend.music, [0.25, 0.75]);
+        audio.update(10.0);
+        assert_eq!(audio.backend.music, [0.0, 1.0]);
+
+        // the mixer applies on top of the fade
+        audio.mixer.music.volume = 0.5;
+        audio.update(0.1);
+        assert_eq!(audio.backend.music, [0.0, 0.5]);
+        audio.mixer.music.muted = true;
+        audio.update(0.1);
+        assert_eq!(audio.backend.music, [0.0, 0.0]);
+
+        audio.set_music(None);
+        audio.mixer.music.muted = false;
+        audio.update(0.5);
+        assert_eq!(audio.backend.music, [0.0, 0.25]);
+    }
+
+    #[test]
+    fn volumes_stay_in_range() {
+        let mut channel = Channel::default();
+        channel.adjust(0.5);
+        assert_eq!(channel.volume, 1.0);
+        channel.adjust(-1.5);
+        assert_eq!(channel.gain(), 0.0);
+    }
+
+    #[test]
+    fn mixer_ron_round_trip() {
+        let mut mixer = Mixer::default();
+        mixer.music.volume = 0.3;
+        mixer.effects.muted = true;
+        assert_eq!(Mixer::from_ron(&mixer.to_ron().unwrap()), Ok(mixer));
+
+        let partial = Mixer::from_ron("(music: (muted: true))").unwrap();
+        assert_eq!(partial.music, Channel { volume: 1.0, muted: true });
+        assert_eq!(partial.master, Channel::default());
+    }
+
+    #[test]
+    fn mixer_saved_and_loaded() {
+        let path = std::env::temp_dir().join(format!("pong_sim_audio_{}.ron", std::process::id()));
+        let _ = fs::remove_file(&path);
+        assert_eq!(Mixer::load_saved(&path), Ok(Mixer::default()));
+
+        let mut mixer = Mixer::default();
+        mixer.effects.volume = 0.5;
+        mixer.save(&path).unwrap();
+        assert_eq!(Mixer::load_saved(&path), Ok(mixer));
+        let _ = fs::remove_file(&path);
+    }
+}
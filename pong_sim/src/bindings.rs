@@ -0,0 +1,344 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::sim::Side;
+
+/// Something a player presses or pushes. The names are the ones of the
+/// front-end (bevy `KeyCode`, winit `VirtualKeyCode`, godot scancodes...),
+/// only the front-end reads them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum Control {
+    Key(String),
+    Button { gamepad: u32, button: String },
+    /// A stick or a trigger, `positive` when pushing it towards its positive
+    /// values triggers the action.
+    Axis { gamepad: u32, axis: String, positive: bool }
+}
+
+impl Control {
+    pub fn is_gamepad(&self) -> bool {
+        !matches!(self, Control::Key(_))
+    }
+}
+
+impl fmt::Display for Control {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Control::Key(key) => write!(f, "{}", key),
+            Control::Button { gamepad, button } => write!(f, "Gamepad {} {}", gamepad, button),
+            Control::Axis { gamepad, axis, positive } => {
+                write!(f, "Gamepad {} {}{}", gamepad, axis, if *positive { "+" } else { "-" })
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Up,
+    Down
+}
+
+/// The controls moving the paddle of one player, any of them works.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PlayerBindings {
+    pub up: Vec<Control>,
+    pub down: Vec<Control>
+}
+
+impl PlayerBindings {
+    pub fn new(up: Vec<Control>, down: Vec<Control>) -> PlayerBindings {
+        PlayerBindings { up, down }
+    }
+
+    pub fn get(&self, action: Action) -> &[Control] {
+        match action {
+            Action::Up => &self.up,
+            Action::Down => &self.down
+        }
+    }
+
+    fn get_mut(&mut self, action: Action) -> &mut Vec<Control> {
+        match action {
+            Action::Up => &mut self.up,
+            Action::Down => &mut self.down
+        }
+    }
+
+    /// The paddle axis, from how much each control is pressed between 0 and 1.
+    /// Anything under the dead zone counts as released.
+    pub fn axis<F: Fn(&Control) -> f32>(&self, deadzone: f32, strength: F) -> f32 {
+        let strongest = |controls: &[Control]| {
+            controls.iter()
+                .map(&strength)
+                .filter(|&strength| strength > deadzone)
+                .fold(0.0, f32::max)
+        };
+        (strongest(&self.up) - strongest(&self.down)).clamp(-1.0, 1.0)
+    }
+}
+
+/// Controls of both players, saved to a RON file by the front-ends.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Bindings {
+    pub left: PlayerBindings,
+    pub right: PlayerBindings,
+    /// Stick travel ignored around the center, between 0 and 1.
+    pub deadzone: f32
+}
+
+impl Default for Bindings {
+    /// The keyboard layout every front-end had: W/S on the left, arrows on the right.
+    fn default() -> Bindings {
+        let key = |name: &str| vec![Control::Key(name.to_string())];
+        Bindings {
+            left: PlayerBindings::new(key("W"), key("S")),
+            right: PlayerBindings::new(key("Up"), key("Down")),
+            deadzone: 0.2
+        }
+    }
+}
+
+impl Bindings {
+    pub fn get(&self, side: Side) -> &PlayerBindings {
+        match side {
+            Side::Left => &self.left,
+            Side::Right => &self.right
+        }
+    }
+
+    fn get_mut(&mut self, side: Side) -> &mut PlayerBindings {
+        match side {
+            Side::Left => &mut self.left,
+            Side::Right => &mut self.right
+        }
+    }
+
+    pub fn axis<F: Fn(&Control) -> f32>(&self, side: Side, strength: F) -> f32 {
+        self.get(side).axis(self.deadzone, strength)
+    }
+
+    /// Bind a control to an action. It replaces the controls of the same
+    /// kind, so that a player keeps one key and one gamepad control, and it is
+    /// taken away from any other action.
+    pub fn bind(&mut self, side: Side, action: Action, control: Control) {
+        for player in [&mut self.left, &mut self.right].iter_mut() {
+            player.up.retain(|bound| bound != &control);
+            player.down.retain(|bound| bound != &control);
+        }
+
+        let controls = self.get_mut(side).get_mut(action);
+        controls.retain(|bound| bound.is_gamepad() != control.is_gamepad());
+        controls.push(control);
+    }
+
+    pub fn from_ron(text: &str) -> Result<Bindings, String> {
+        ron::de::from_str(text).map_err(|error| format!("Invalid Pong bindings: {}", error))
+    }
+
+    pub fn to_ron(&self) -> Result<String, String> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| format!("Can not write the Pong bindings: {}", error))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Bindings, String> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|error| format!("Can not read {}: {}", path.display(), error))?;
+
+        Bindings::from_ron(&text).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)
+                .map_err(|error| format!("Can not create {}: {}", directory.display(), error))?;
+        }
+        fs::write(path, self.to_ron()?).map_err(|error| format!("Can not write {}: {}", path.display(), error))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuEntry {
+    Bind(Side, Action),
+    /// Back to the bindings of the front-end.
+    Reset
+}
+
+/// The in-game rebinding menu, without the drawing: the front-ends move the
+/// selection with the menu keys and hand over the next control pressed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BindingMenu {
+    selected: usize,
+    waiting: bool
+}
+
+impl BindingMenu {
+    pub const ENTRIES: [MenuEntry; 5] = [
+        MenuEntry::Bind(Side::Left, Action::Up),
+        MenuEntry::Bind(Side::Left, Action::Down),
+        MenuEntry::Bind(Side::Right, Action::Up),
+        MenuEntry::Bind(Side::Right, Action::Down),
+        MenuEntry::Reset
+    ];
+
+    pub fn selected(&self) -> MenuEntry {
+        BindingMenu::ENTRIES[self.selected]
+    }
+
+    /// Waiting for the control to bind to the selected action, every control
+    /// pressed goes to `capture` instead of moving in the menu.
+    pub fn is_waiting(&self) -> bool {
+        self.waiting
+    }
+
+    pub fn previous(&mut self) {
+        self.selected = (self.selected + BindingMenu::ENTRIES.len() - 1) % BindingMenu::ENTRIES.len();
+    }
+
+    pub fn next(&mut self) {
+        self.selected = (self.selected + 1) % BindingMenu::ENTRIES.len();
+    }
+
+    /// Validate the selected entry, returns true when the bindings changed.
+    pub fn activate(&mut self, bindings: &mut Bindings, defaults: &Bindings) -> bool {
+        match self.selected() {
+            MenuEntry::Bind(..) => {
+                self.waiting = true;
+                false
+            }
+            MenuEntry::Reset => {
+                *bindings = defaults.clone();
+                true
+            }
+        }
+    }
+
+    /// The control pressed while waiting, returns true when it was bound.
+    pub fn capture(&mut self, control: Control, bindings: &mut Bindings) -> bool {
+        if !self.waiting {
+            return false;
+        }
+        self.waiting = false;
+        if let MenuEntry::Bind(side, action) = self.selected() {
+            bindings.bind(side, action, control);
+        }
+        true
+    }
+
+    pub fn cancel(&mut self) {
+        self.waiting = false;
+    }
+
+    /// One line of text per entry, the selected one starts with `>`.
+    pub fn lines(&self, bindings: &Bindings) -> Vec<String> {
+        BindingMenu::ENTRIES.iter().enumerate()
+            .map(|(index, entry)| {
+                let cursor = if index == self.selected { ">" } else { " " };
+                let text = match entry {
+                    MenuEntry::Bind(side, action) if self.waiting && index == self.selected => {
+                        format!("{:?} {:?}: press a key or a gamepad control", side, action)
+                    }
+                    MenuEntry::Bind(side, action) => {
+                        let controls: Vec<String> = bindings.get(*side).get(*action).iter()
+                            .map(Control::to_string)
+                            .collect();
+                        format!("{:?} {:?}: {}", side, action, controls.join(", "))
+                    }
+                    MenuEntry::Reset => "Reset".to_string()
+                };
+                format!("{} {}", cursor, text)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(name: &str) -> Control {
+        Control::Key(name.to_string())
+    }
+
+    fn stick(positive: bool) -> Control {
+        Control::Axis { gamepad: 0, axis: "LeftStickY".to_string(), positive }
+    }
+
+    #[test]
+    fn axis_from_any_control() {
+        let mut bindings = Bindings::default();
+        bindings.bind(Side::Left, Action::Up, stick(true));
+        bindings.bind(Side::Left, Action::Down, stick(false));
+
+        let pressed = |keys: &'static [&'static str], stick_value: f32| {
+            move |control: &Control| match control {
+                Control::Key(name) => if keys.contains(&name.as_str()) { 1.0 } else { 0.0 },
+                Control::Axis { positive, .. } => if *positive { stick_value } else { -stick_value },
+                Control::Button { .. } => 0.0
+            }
+        };
+
+        assert_eq!(bindings.axis(Side::Left, pressed(&["W"], 0.0)), 1.0);
+        assert_eq!(bindings.axis(Side::Left, pressed(&["W", "S"], 0.0)), 0.0);
+        assert_eq!(bindings.axis(Side::Left, pressed(&[], -0.5)), -0.5);
+        // inside the dead zone
+        assert_eq!(bindings.axis(Side::Left, pressed(&[], 0.1)), 0.0);
+        // the other player's keys
+        assert_eq!(bindings.axis(Side::Left, pressed(&["Up"], 0.0)), 0.0);
+        assert_eq!(bindings.axis(Side::Right, pressed(&["Up"], 0.0)), 1.0);
+    }
+
+    #[test]
+    fn bind_keeps_one_control_of_each_kind() {
+        let mut bindings = Bindings::default();
+        bindings.bind(Side::Left, Action::Up, stick(true));
+        bindings.bind(Side::Left, Action::Up, key("Z"));
+        assert_eq!(bindings.left.up, vec![stick(true), key("Z")]);
+
+        // a control moves only one paddle one way
+        bindings.bind(Side::Right, Action::Down, key("Z"));
+        assert_eq!(bindings.left.up, vec![stick(true)]);
+        assert_eq!(bindings.right.down, vec![key("Z")]);
+    }
+
+    #[test]
+    fn ron_round_trip() {
+        let mut bindings = Bindings::default();
+        bindings.bind(Side::Right, Action::Up, Control::Button { gamepad: 1, button: "DPadUp".to_string() });
+
+        assert_eq!(Bindings::from_ron(&bindings.to_ron().unwrap()), Ok(bindings));
+        assert_eq!(Bindings::from_ron("(deadzone: 0.5)").unwrap().left, Bindings::default().left);
+    }
+
+    #[test]
+    fn menu_rebinds_the_selected_action() {
+        let defaults = Bindings::default();
+        let mut bindings = defaults.clone();
+        let mut menu = BindingMenu::default();
+
+        menu.next();
+        assert_eq!(menu.selected(), MenuEntry::Bind(Side::Left, Action::Down));
+        assert!(!menu.capture(key("X"), &mut bindings));
+
+        assert!(!menu.activate(&mut bindings, &defaults));
+        assert!(menu.is_waiting());
+        assert!(menu.lines(&bindings)[1].starts_with("> Left Down: press"));
+        assert!(menu.capture(key("X"), &mut bindings));
+        assert!(!menu.is_waiting());
+        assert_eq!(bindings.left.down, vec![key("X")]);
+        assert_eq!(menu.lines(&bindings)[1], "> Left Down: X");
+
+        menu.previous();
+        menu.previous();
+        assert_eq!(menu.selected(), MenuEntry::Reset);
+        assert!(menu.activate(&mut bindings, &defaults));
+        assert_eq!(bindings, defaults);
+    }
+}
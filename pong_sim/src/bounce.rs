@@ -0,0 +1,191 @@
+use crate::collision::{self, Hit};
+use crate::config::PongConfig;
+use crate::math::Vec2;
+use crate::sim::Ball;
+
+/// Bounces handled in a single step before the rest of the motion is dropped,
+/// a ball stuck between a paddle and a wall would loop forever otherwise.
+pub(crate) const MAX_CONTACTS_PER_STEP: usize = 8;
+
+/// A paddle as the ball sees it, in the two-player match or on any wall of
+/// the four-player one. The vectors are along x or y.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct PaddleBody {
+    /// Center of the paddle.
+    pub position: Vec2,
+    /// Unit vector out of the face of the paddle, toward the arena.
+    pub normal: Vec2,
+    /// Unit vector a positive axis moves the paddle along.
+    pub tangent: Vec2,
+    /// Speed along the tangent during the last step, for the spin.
+    pub velocity: f32,
+    /// Length along the tangent, changed by the power-ups.
+    pub length: f32
+}
+
+impl PaddleBody {
+    /// Half size of the paddle.
+    fn extent(&self, config: &PongConfig) -> Vec2 {
+        let along = self.length / 2.0;
+        let across = config.paddle_width / 2.0;
+        Vec2::new(
+            self.tangent.x.abs() * along + self.normal.x.abs() * across,
+            self.tangent.y.abs() * along + self.normal.y.abs() * across
+        )
+    }
+
+    /// Half size of the paddle grown by the ball radius.
+    fn half_size(&self, config: &PongConfig) -> Vec2 {
+        self.extent(config) + Vec2::new(config.ball_radius, config.ball_radius)
+    }
+}
+
+/// A wall closing the arena, or a goal, `distance` away from its center.
+/// The normal points toward the center and is along x or y.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct WallBody {
+    pub normal: Vec2,
+    pub distance: f32
+}
+
+impl WallBody {
+    /// Whether the whole ball went through, the goal of the player of this
+    /// wall in both matches.
+    pub fn is_crossed(&self, config: &PongConfig, ball: &Ball) -> bool {
+        ball.position.dot(-self.normal) > self.distance + config.ball_radius
+    }
+}
+
+/// What the ball bounced on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Contact {
+    /// Index in the paddles given to [`move_ball`].
+    Paddle(usize),
+    Wall
+}
+
+/// Move the ball by `dt` seconds against the paddles and the walls, calling
+/// `contact` on every bounce.
+///
+/// The ball is swept along its motion, stopping at each contact to reflect
+/// the rest of the motion, so that a fast ball can not go through a paddle.
+pub(crate) fn move_ball<F: FnMut(Contact)>(
+    config: &PongConfig,
+    ball: &mut Ball,
+    dt: f32,
+    paddles: &[PaddleBody],
+    walls: &[WallBody],
+    mut contact: F
+) {
+    let radius = config.ball_radius;
+
+    // a paddle moved onto the ball, push the ball out of its face, only
+    // when the ball goes toward the paddle so that it can not bounce twice
+    for (index, paddle) in paddles.iter().enumerate() {
+        let toward = ball.velocity.dot(paddle.normal) < 0.0;
+        let offset = ball.position - paddle.position;
+        let half_size = paddle.half_size(config);
+
+        if toward && offset.x.abs() < half_size.x && offset.y.abs() < half_size.y {
+            if paddle.normal.x != 0.0 {
+                ball.position.x = paddle.position.x + paddle.normal.x * half_size.x;
+            }
+            else {
+                ball.position.y = paddle.position.y + paddle.normal.y * half_size.y;
+            }
+            ball.velocity = paddle_bounce(config, ball, paddle);
+            contact(Contact::Paddle(index));
+        }
+    }
+
+    let mut time = dt;
+    for _ in 0..MAX_CONTACTS_PER_STEP {
+        let motion = ball.velocity * time;
+        let mut first: Option<(Hit, Option<usize>)> = None;
+        let earlier = |first: &Option<(Hit, Option<usize>)>, time: f32| match first {
+            Some((first, _)) => time < first.time,
+            None => true
+        };
+
+        for wall in walls.iter() {
+            let outward = -wall.normal;
+            let toward = motion.dot(outward);
+            if toward > 0.0 {
+                let t = ((wall.distance - radius - ball.position.dot(outward)) / toward).max(0.0);
+                if t <= 1.0 && earlier(&first, t) {
+                    first = Some((Hit { time: t, normal: wall.normal }, None));
+                }
+            }
+        }
+
+        for (index, paddle) in paddles.iter().enumerate() {
+            let extent = paddle.extent(config);
+            if let Some(hit) = collision::sweep(ball.position, motion, radius, paddle.position - extent, paddle.position + extent) {
+                if earlier(&first, hit.time) {
+                    first = Some((hit, Some(index)));
+                }
+            }
+        }
+
+        let (hit, paddle) = match first {
+            Some(first) => first,
+            None => {
+                ball.position += motion;
+                break;
+            }
+        };
+
+        ball.position += motion * hit.time;
+        time *= 1.0 - hit.time;
+        match paddle {
+            // the face of a paddle or the corner next to it, the outgoing angle
+            // depends on where it hit
+            Some(index) if hit.normal.dot(paddles[index].normal).abs() >= hit.normal.dot(paddles[index].tangent).abs() => {
+                ball.velocity = paddle_bounce(config, ball, &paddles[index]);
+                contact(Contact::Paddle(index));
+            }
+            // an end of a paddle
+            Some(index) => {
+                reflect(ball, hit.normal);
+                ball.velocity *= sped_up(config, ball.velocity.length()) / ball.velocity.length();
+                contact(Contact::Paddle(index));
+            }
+            None => {
+                reflect(ball, hit.normal);
+                contact(Contact::Wall);
+            }
+        }
+    }
+}
+
+fn reflect(ball: &mut Ball, normal: Vec2) {
+    ball.velocity -= normal * (2.0 * ball.velocity.dot(normal));
+}
+
+/// Ball speed after a paddle hit, it never gets faster than the configured
+/// maximum but a ball already faster is not slowed down either.
+pub(crate) fn sped_up(config: &PongConfig, speed: f32) -> f32 {
+    if speed >= config.max_ball_speed {
+        speed
+    }
+    else {
+        (speed * config.ball_acceleration).min(config.max_ball_speed)
+    }
+}
+
+/// Velocity of the ball leaving the face of `paddle`: hitting the center sends
+/// it back along the normal, hitting an end sends it at `max_bounce_angle`,
+/// and the paddle movement adds some spin.
+fn paddle_bounce(config: &PongConfig, ball: &Ball, paddle: &PaddleBody) -> Vec2 {
+    let speed = sped_up(config, ball.velocity.length());
+    let max_angle = config.max_bounce_angle.to_radians();
+    let reach = paddle.length / 2.0 + config.ball_radius;
+
+    let offset = ((ball.position - paddle.position).dot(paddle.tangent) / reach).clamp(-1.0, 1.0);
+    let angle = offset * max_angle;
+
+    let spin = paddle.velocity * config.paddle_spin;
+    let angle = (speed * angle.sin() + spin).atan2(speed * angle.cos()).clamp(-max_angle, max_angle);
+
+    (paddle.normal * angle.cos() + paddle.tangent * angle.sin()) * speed
+}
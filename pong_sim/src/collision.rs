@@ -0,0 +1,137 @@
+use crate::math::Vec2;
+
+/// First contact of a moving circle with a box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hit {
+    /// Fraction of the motion done before the contact, between 0 and 1.
+    pub time: f32,
+    /// Unit outward normal where the box was hit, along x or y on a face and
+    /// away from the corner on a rounded corner.
+    pub normal: Vec2
+}
+
+/// Sweep a circle of `radius` centered on `origin` along `motion` against the
+/// box `min`..`max`.
+///
+/// The center is swept against the box grown by the radius with rounded
+/// corners: the grown box first, then the circle of the corner when the
+/// center enters it beyond both faces. Nothing is returned when the circle
+/// starts overlapping the box or moves away from it.
+pub fn sweep(origin: Vec2, motion: Vec2, radius: f32, min: Vec2, max: Vec2) -> Option<Hit> {
+    let mut enter = f32::NEG_INFINITY;
+    let mut exit = f32::INFINITY;
+    let mut normal = Vec2::ZERO;
+
+    let axes = [
+        (origin.x, motion.x, min.x - radius, max.x + radius, Vec2::new(1.0, 0.0)),
+        (origin.y, motion.y, min.y - radius, max.y + radius, Vec2::new(0.0, 1.0))
+    ];
+    for &(origin, motion, min, max, axis) in axes.iter() {
+        if motion == 0.0 {
+            // parallel to the slab, either always inside or never
+            if origin < min || origin > max {
+                return None;
+            }
+            continue;
+        }
+
+        let (near, far, face) = if motion > 0.0 {
+            ((min - origin) / motion, (max - origin) / motion, -axis)
+        }
+        else {
+            ((max - origin) / motion, (min - origin) / motion, axis)
+        };
+        if near > enter {
+            enter = near;
+            normal = face;
+        }
+        exit = exit.min(far);
+    }
+
+    if enter > 1.0 || enter > exit || exit < 0.0 {
+        return None;
+    }
+
+    // where the center enters the grown box, or starts in it
+    let point = origin + motion * enter.max(0.0);
+    let along_x = point.x >= min.x && point.x <= max.x;
+    let along_y = point.y >= min.y && point.y <= max.y;
+    if along_x || along_y {
+        if enter < 0.0 {
+            return None;
+        }
+        return Some(Hit { time: enter, normal });
+    }
+
+    // beyond both faces, the center either hits the circle of the corner
+    // or leaves the grown box without touching the box
+    let corner = Vec2::new(point.x.clamp(min.x, max.x), point.y.clamp(min.y, max.y));
+    let offset = origin - corner;
+    let a = motion.length_squared();
+    let b = offset.dot(motion);
+    let c = offset.length_squared() - radius * radius;
+    let discriminant = b * b - a * c;
+    if c <= 0.0 || b >= 0.0 || discriminant < 0.0 {
+        return None;
+    }
+    let time = (-b - discriminant.sqrt()) / a;
+    if time > 1.0 {
+        return None;
+    }
+
+    Some(Hit { time, normal: (origin + motion * time - corner) * (1.0 / radius) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIN: Vec2 = Vec2::new(-1.0, -1.0);
+    const MAX: Vec2 = Vec2::new(1.0, 1.0);
+
+    #[test]
+    fn hit_on_the_near_face() {
+        let hit = sweep(Vec2::new(-5.0, 0.5), Vec2::new(8.0, 0.0), 0.0, MIN, MAX);
+        assert_eq!(hit, Some(Hit { time: 0.5, normal: Vec2::new(-1.0, 0.0) }));
+
+        let hit = sweep(Vec2::new(0.0, 3.0), Vec2::new(0.0, -4.0), 0.0, MIN, MAX);
+        assert_eq!(hit, Some(Hit { time: 0.5, normal: Vec2::new(0.0, 1.0) }));
+    }
+
+    #[test]
+    fn motion_much_longer_than_the_box() {
+        let hit = sweep(Vec2::new(-1000.0, 0.0), Vec2::new(100_000.0, 0.0), 0.0, MIN, MAX);
+        assert_eq!(hit.map(|hit| hit.normal), Some(Vec2::new(-1.0, 0.0)));
+    }
+
+    #[test]
+    fn misses() {
+        // too short
+        assert_eq!(sweep(Vec2::new(-5.0, 0.0), Vec2::new(3.0, 0.0), 0.0, MIN, MAX), None);
+        // passes above
+        assert_eq!(sweep(Vec2::new(-5.0, 2.0), Vec2::new(10.0, 0.0), 0.0, MIN, MAX), None);
+        // diagonal passing next to a corner
+        assert_eq!(sweep(Vec2::new(-3.0, 0.0), Vec2::new(3.0, 3.0), 0.0, MIN, MAX), None);
+        // going away
+        assert_eq!(sweep(Vec2::new(1.0, 0.0), Vec2::new(5.0, 0.0), 0.0, MIN, MAX), None);
+        // starting inside
+        assert_eq!(sweep(Vec2::ZERO, Vec2::new(5.0, 0.0), 0.0, MIN, MAX), None);
+    }
+
+    #[test]
+    fn round_ball_against_a_face() {
+        let hit = sweep(Vec2::new(-5.0, 0.8), Vec2::new(8.0, 0.0), 0.5, MIN, MAX);
+        assert_eq!(hit, Some(Hit { time: 0.4375, normal: Vec2::new(-1.0, 0.0) }));
+    }
+
+    #[test]
+    fn round_ball_against_a_corner() {
+        // the grown box is entered at x = -1.5, the circle of the corner later
+        let hit = sweep(Vec2::new(-5.0, 1.3), Vec2::new(8.0, 0.0), 0.5, MIN, MAX).unwrap();
+        assert!((hit.time - (5.0 - 1.0 - 0.4) / 8.0).abs() < 1e-5);
+        assert!((hit.normal.x + 0.8).abs() < 1e-5 && (hit.normal.y - 0.6).abs() < 1e-5);
+
+        // a square ball would hit, the round one passes the corner
+        assert_eq!(sweep(Vec2::new(-3.0, -0.2), Vec2::new(3.0, 3.0), 0.5, MIN, MAX), None);
+    }
+}
@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+
+use crate::math::Vec2;
+
+/// Who serves after a point. The ball always leaves the center away from the
+/// server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ServeRule {
+    /// The player who conceded the point serves.
+    Conceder,
+    /// The players serve in turn.
+    Alternate
+}
+
+/// Tuning of a Pong match, all distances are in arena units.
+///
+/// Every field has a default, so a RON file only lists what it changes.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PongConfig {
+    pub arena_width: f32,
+    pub arena_height: f32,
+
+    pub paddle_width: f32,
+    pub paddle_height: f32,
+    pub paddle_speed: f32,
+
+    pub ball_radius: f32,
+    /// Velocity of the ball when served toward the right player.
+    pub ball_velocity: Vec2,
+    /// Factor applied to the ball speed on every paddle hit, the speed goes
+    /// back to the serve speed on every new rally.
+    pub ball_acceleration: f32,
+    /// The speed-up stops at this speed.
+    pub max_ball_speed: f32,
+
+    /// Angle from the horizontal, in degrees, of a ball hitting the very end
+    /// of a paddle. A ball hitting the center goes back horizontally.
+    pub max_bounce_angle: f32,
+    /// Part of the paddle velocity added to the ball velocity on a hit.
+    pub paddle_spin: f32,
+
+    /// Seconds the ball waits at the center before being served.
+    pub serve_delay: f32,
+    pub serve: ServeRule,
+
+    /// Points needed to win the match, 0 to play forever.
+    pub points_to_win: u32,
+    /// The winner must lead by this many points, the match goes on until then.
+    pub win_by: u32,
+
+    /// Goals a player can let in before being knocked out of a four-player
+    /// match, see [`PartySim`](crate::PartySim).
+    pub lives: u32,
+
+    /// Power-ups appear in the middle of the arena, see [`PowerUpKind`](crate::PowerUpKind).
+    pub power_ups: bool,
+    /// Seconds between two power-ups.
+    pub power_up_interval: f32,
+    /// Seconds a power-up lasts once caught.
+    pub power_up_duration: f32,
+    pub power_up_radius: f32
+}
+
+impl PongConfig {
+    /// Read a config written in RON, missing fields keep their default.
+    pub fn from_ron(text: &str) -> Result<PongConfig, String> {
+        ron::de::from_str(text).map_err(|error| format!("Invalid Pong config: {}", error))
+    }
+
+    pub fn to_ron(&self) -> Result<String, String> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| format!("Can not write Pong config: {}", error))
+    }
+}
+
+impl Default for PongConfig {
+    fn default() -> Self {
+        PongConfig {
+            arena_width: 800.0,
+            arena_height: 600.0,
+
+            paddle_width: 20.0,
+            paddle_height: 80.0,
+            paddle_speed: 150.0,
+
+            ball_radius: 10.0,
+            ball_velocity: Vec2::new(100.0, 150.0),
+            ball_acceleration: 1.05,
+            max_ball_speed: 600.0,
+
+            max_bounce_angle: 60.0,
+            paddle_spin: 0.25,
+
+            serve_delay: 0.0,
+            serve: ServeRule::Conceder,
+
+            points_to_win: 11,
+            win_by: 2,
+
+            lives: 3,
+
+            power_ups: false,
+            power_up_interval: 8.0,
+            power_up_duration: 10.0,
+            power_up_radius: 15.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_fields_keep_their_default() {
+        let config = PongConfig::from_ron("(paddle_speed: 300.0, ball_velocity: (x: 1.0, y: 2.0))").unwrap();
+        assert_eq!(config, PongConfig {
+            paddle_speed: 300.0,
+            ball_velocity: Vec2::new(1.0, 2.0),
+            ..PongConfig::default()
+        });
+    }
+
+    #[test]
+    fn round_trip() {
+        let config = PongConfig { max_bounce_angle: 45.0, paddle_spin: 0.0, ..PongConfig::default() };
+        assert_eq!(PongConfig::from_ron(&config.to_ron().unwrap()), Ok(config));
+    }
+
+    #[test]
+    fn example_file_matches_the_defaults() {
+        assert_eq!(PongConfig::from_ron(include_str!("../pong.ron")), Ok(PongConfig::default()));
+    }
+
+    /// Names of the fields given in a RON file.
+    fn fields(text: &str) -> Vec<String> {
+        match ron::de::from_str::<ron::Value>(text) {
+            Ok(ron::Value::Map(map)) => map.keys()
+                .filter_map(|key| match key {
+                    ron::Value::String(name) => Some(name.clone()),
+                    _ => None
+                })
+                .collect(),
+            other => panic!("not a struct: {:?}", other)
+        }
+    }
+
+    #[test]
+    fn shipped_tunings_are_complete() {
+        let all = fields(&PongConfig::default().to_ron().unwrap());
+        let files = [
+            ("amethyst", include_str!("../../level_2_amethyst_pong/assets/components/paddle.ron"), &[][..]),
+            // the arena is the window
+            ("godot", include_str!("../../level_4_godot_pong/assets/components/paddle.ron"), &["arena_width", "arena_height"][..]),
+            ("bevy", include_str!("../../level_5_bevy_pong/assets/components/paddle.ron"), &[][..])
+        ];
+        for (name, text, left_out) in files.iter() {
+            assert!(PongConfig::from_ron(text).is_ok(), "{}: {:?}", name, PongConfig::from_ron(text));
+            let given = fields(text);
+            for field in all.iter().filter(|field| !left_out.contains(&field.as_str())) {
+                assert!(given.contains(field), "{} does not give {}", name, field);
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_fields_are_errors() {
+        assert!(PongConfig::from_ron("(paddle_sped: 300.0)").is_err());
+    }
+}
@@ -0,0 +1,496 @@
+//! Visual effects without any engine: particles, screen shake and trails.
+//! The front-ends only draw what they give.
+
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+
+use crate::math::Vec2;
+use crate::rng::Rng;
+use crate::sim::{Event, PongSim, Side};
+
+/// Red, green, blue and alpha between 0 and 1.
+pub type Rgba = [f32; 4];
+
+/// Colors over the life of a particle, from 0 at birth to 1 at death.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    /// Sorted by time.
+    stops: Vec<(f32, Rgba)>
+}
+
+impl Gradient {
+    /// `stops` are the times and their colors, in any order.
+    pub fn new(stops: &[(f32, Rgba)]) -> Gradient {
+        let mut stops = stops.to_vec();
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Gradient { stops }
+    }
+
+    /// From `color` to transparent.
+    pub fn fade(color: Rgba) -> Gradient {
+        Gradient::new(&[(0.0, color), (1.0, [color[0], color[1], color[2], 0.0])])
+    }
+
+    /// The color at `t`, interpolated between the stops around it.
+    pub fn sample(&self, t: f32) -> Rgba {
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return [1.0; 4]
+        };
+        if t <= first.0 {
+            return first.1;
+        }
+        if t >= last.0 {
+            return last.1;
+        }
+        let next = self.stops.iter().position(|(time, _)| *time > t).unwrap_or(self.stops.len() - 1);
+        let (start, from) = self.stops[next - 1];
+        let (end, to) = self.stops[next];
+        let blend = (t - start) / (end - start).max(f32::EPSILON);
+        let mut color = [0.0; 4];
+        for (channel, value) in color.iter_mut().enumerate() {
+            *value = from[channel] + (to[channel] - from[channel]) * blend;
+        }
+        color
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Particle {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    /// Seconds since it was emitted.
+    pub age: f32,
+    pub lifetime: f32,
+    pub size: f32
+}
+
+impl Particle {
+    /// 0 at birth, 1 at death.
+    pub fn progress(&self) -> f32 {
+        (self.age / self.lifetime.max(f32::EPSILON)).min(1.0)
+    }
+}
+
+/// How an emitter throws its particles.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmitterConfig {
+    /// Seconds, each particle lives between these two.
+    pub lifetime: (f32, f32),
+    /// Units per second, each particle leaves between these two.
+    pub speed: (f32, f32),
+    /// Particles leave within this angle around the direction of the
+    /// emitter, in radians, `2 * PI` for all around.
+    pub spread: f32,
+    pub size: (f32, f32),
+    /// Acceleration of every particle, in units per second squared.
+    pub gravity: Vec2,
+    pub gradient: Gradient,
+    /// The oldest particles go first beyond this.
+    pub max_particles: usize
+}
+
+impl Default for EmitterConfig {
+    fn default() -> Self {
+        EmitterConfig {
+            lifetime: (0.5, 1.0),
+            speed: (50.0, 100.0),
+            spread: 2.0 * PI,
+            size: (2.0, 4.0),
+            gravity: Vec2::ZERO,
+            gradient: Gradient::fade([1.0; 4]),
+            max_particles: 512
+        }
+    }
+}
+
+/// Emits particles in bursts, or continuously at `rate` per second from its
+/// `position`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParticleEmitter {
+    pub config: EmitterConfig,
+    pub position: Vec2,
+    /// Angle of the direction of the particles, in radians from the x axis.
+    pub direction: f32,
+    /// Particles per second emitted by [`ParticleEmitter::update`], 0 for
+    /// bursts only.
+    pub rate: f32,
+    particles: Vec<Particle>,
+    /// Part of a particle owed by the continuous emission.
+    owed: f32,
+    rng: Rng
+}
+
+impl ParticleEmitter {
+    pub fn new(config: EmitterConfig, seed: u64) -> ParticleEmitter {
+        ParticleEmitter {
+            config,
+            position: Vec2::ZERO,
+            direction: 0.0,
+            rate: 0.0,
+            particles: Vec::new(),
+            owed: 0.0,
+            rng: Rng::new(seed)
+        }
+    }
+
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    /// The color of a particle of this emitter.
+    pub fn color(&self, particle: &Particle) -> Rgba {
+        self.config.gradient.sample(particle.progress())
+    }
+
+    /// Emit `count` particles at once from `position`, toward `direction`.
+    pub fn burst(&mut self, position: Vec2, direction: f32, count: usize) {
+        for _ in 0..count {
+            self.emit(position, direction);
+        }
+    }
+
+    /// Move the particles on by `dt` seconds, drop the dead ones and emit the
+    /// continuous ones.
+    pub fn update(&mut self, dt: f32) {
+        let gravity = self.config.gravity;
+        for particle in self.particles.iter_mut() {
+            particle.age += dt;
+            particle.velocity += gravity * dt;
+            particle.position += particle.velocity * dt;
+        }
+        self.particles.retain(|particle| particle.age < particle.lifetime);
+
+        if self.rate > 0.0 {
+            self.owed += self.rate * dt;
+            while self.owed >= 1.0 {
+                self.owed -= 1.0;
+                self.emit(self.position, self.direction);
+            }
+        }
+        else {
+            self.owed = 0.0;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.owed = 0.0;
+    }
+
+    fn emit(&mut self, position: Vec2, direction: f32) {
+        let config = &self.config;
+        if config.max_particles == 0 {
+            return;
+        }
+        if self.particles.len() >= config.max_particles {
+            let excess = self.particles.len() + 1 - config.max_particles;
+            self.particles.drain(..excess);
+        }
+        let angle = direction + self.rng.range(-0.5, 0.5) * config.spread;
+        let speed = self.rng.range(config.speed.0, config.speed.1);
+        let particle = Particle {
+            position,
+            velocity: Vec2::new(speed, 0.0).rotated(angle),
+            age: 0.0,
+            lifetime: self.rng.range(config.lifetime.0, config.lifetime.1),
+            size: self.rng.range(config.size.0, config.size.1)
+        };
+        self.particles.push(particle);
+    }
+}
+
+/// Camera shake driven by trauma: hits add trauma, which wears off, and the
+/// shake grows with its square so that small hits stay subtle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScreenShake {
+    /// Offset of the camera at full trauma, in units.
+    pub max_offset: f32,
+    /// Rotation of the camera at full trauma, in radians.
+    pub max_angle: f32,
+    /// Trauma lost per second.
+    pub decay: f32,
+    /// Oscillations per second.
+    pub frequency: f32,
+    trauma: f32,
+    time: f32
+}
+
+impl Default for ScreenShake {
+    fn default() -> Self {
+        ScreenShake { max_offset: 12.0, max_angle: 0.05, decay: 1.5, frequency: 25.0, trauma: 0.0, time: 0.0 }
+    }
+}
+
+impl ScreenShake {
+    pub fn trauma(&self) -> f32 {
+        self.trauma
+    }
+
+    /// Add trauma, capped at 1.
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.trauma = (self.trauma - self.decay * dt).max(0.0);
+        self.time += dt;
+    }
+
+    /// Offset and rotation of the camera now.
+    pub fn offset(&self) -> (Vec2, f32) {
+        let shake = self.trauma * self.trauma;
+        // sums of sines of unrelated frequencies, smooth and never repeating
+        let wave = |phase: f32| {
+            let t = self.time * self.frequency + phase;
+            (t.sin() + (t * 1.7 + 1.3).sin() * 0.5) / 1.5
+        };
+        let offset = Vec2::new(wave(0.0), wave(10.0)) * (self.max_offset * shake);
+        (offset, wave(20.0) * self.max_angle * shake)
+    }
+}
+
+/// The last positions of something moving, to draw a trail behind it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trail {
+    /// Oldest first.
+    points: VecDeque<Vec2>,
+    pub max_points: usize,
+    /// A point is only added this far from the last one.
+    pub min_distance: f32
+}
+
+impl Trail {
+    pub fn new(max_points: usize, min_distance: f32) -> Trail {
+        Trail { points: VecDeque::with_capacity(max_points), max_points, min_distance }
+    }
+
+    pub fn push(&mut self, position: Vec2) {
+        if let Some(last) = self.points.back() {
+            if (position - *last).length_squared() < self.min_distance * self.min_distance {
+                return;
+            }
+        }
+        self.points.push_back(position);
+        while self.points.len() > self.max_points {
+            self.points.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    /// Each point with its weight, from near 0 for the oldest to 1 for the
+    /// newest, to fade and thin the trail.
+    pub fn points(&self) -> impl Iterator<Item = (Vec2, f32)> + '_ {
+        let count = self.points.len() as f32;
+        self.points.iter().enumerate().map(move |(index, point)| (*point, (index + 1) as f32 / count))
+    }
+}
+
+/// Trauma of a paddle hit and of a goal.
+const HIT_TRAUMA: f32 = 0.25;
+const GOAL_TRAUMA: f32 = 0.7;
+const HIT_SPARKS: usize = 16;
+const GOAL_SPARKS: usize = 60;
+
+/// The effects of a Pong match: sparks on paddle hits, a shower on goals, a
+/// shake for both and a trail behind the ball.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchEffects {
+    pub sparks: ParticleEmitter,
+    pub shake: ScreenShake,
+    pub trail: Trail,
+    /// Ball position of the last step, where the goals are scored.
+    last_ball: Vec2
+}
+
+impl Default for MatchEffects {
+    fn default() -> Self {
+        let config = EmitterConfig {
+            lifetime: (0.3, 0.8),
+            speed: (80.0, 260.0),
+            spread: PI * 0.75,
+            size: (2.0, 5.0),
+            gravity: Vec2::new(0.0, -400.0),
+            gradient: Gradient::new(&[
+                (0.0, [1.0, 1.0, 1.0, 1.0]),
+                (0.3, [1.0, 0.84, 0.0, 1.0]),
+                (1.0, [1.0, 0.2, 0.0, 0.0])
+            ]),
+            max_particles: 512
+        };
+        MatchEffects {
+            sparks: ParticleEmitter::new(config, 0),
+            shake: ScreenShake::default(),
+            trail: Trail::new(12, 4.0),
+            last_ball: Vec2::ZERO
+        }
+    }
+}
+
+impl MatchEffects {
+    /// React to the events of a step of `sim`.
+    pub fn handle(&mut self, sim: &PongSim, events: &[Event]) {
+        let config = &sim.config;
+        for event in events {
+            match event {
+                Event::PaddleHit(side) => {
+                    let paddle = sim.paddle(*side).position;
+                    let x = paddle.x - side.sign() * config.paddle_width / 2.0;
+                    // the ball that bounced is the nearest one
+                    let y = sim.balls()
+                        .min_by(|a, b| (a.position.x - x).abs().total_cmp(&(b.position.x - x).abs()))
+                        .map_or(paddle.y, |ball| ball.position.y);
+                    self.sparks.burst(Vec2::new(x, y), away_from(*side), HIT_SPARKS);
+                    self.shake.add_trauma(HIT_TRAUMA);
+                }
+                Event::Goal(scorer) => {
+                    let conceder = scorer.opponent();
+                    let x = conceder.sign() * config.arena_width / 2.0;
+                    self.sparks.burst(Vec2::new(x, self.last_ball.y), away_from(conceder), GOAL_SPARKS);
+                    self.shake.add_trauma(GOAL_TRAUMA);
+                    // the ball is back at the center
+                    self.trail.clear();
+                }
+                _ => {}
+            }
+        }
+        self.last_ball = sim.ball.position;
+    }
+
+    /// Move the effects on by `dt` seconds and follow the ball with the trail.
+    pub fn update(&mut self, sim: &PongSim, dt: f32) {
+        self.sparks.update(dt);
+        self.shake.update(dt);
+        if sim.is_serving() {
+            self.trail.clear();
+        }
+        else {
+            self.trail.push(sim.ball.position);
+        }
+    }
+}
+
+/// Angle pointing from the wall of `side` toward the center.
+fn away_from(side: Side) -> f32 {
+    match side {
+        Side::Left => 0.0,
+        Side::Right => PI
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PongConfig;
+
+    #[test]
+    fn bursts_live_their_lifetime() {
+        let config = EmitterConfig { lifetime: (1.0, 1.0), ..EmitterConfig::default() };
+        let mut emitter = ParticleEmitter::new(config, 1);
+        emitter.burst(Vec2::new(10.0, 0.0), 0.0, 20);
+        assert_eq!(emitter.particles().len(), 20);
+
+        emitter.update(0.5);
+        assert_eq!(emitter.particles().len(), 20);
+        assert!(emitter.particles().iter().all(|particle| particle.progress() == 0.5));
+        emitter.update(0.6);
+        assert!(emitter.particles().is_empty());
+    }
+
+    #[test]
+    fn gravity_pulls_the_particles() {
+        let config = EmitterConfig { speed: (0.0, 0.0), gravity: Vec2::new(0.0, -10.0), ..EmitterConfig::default() };
+        let mut emitter = ParticleEmitter::new(config, 2);
+        emitter.burst(Vec2::ZERO, 0.0, 1);
+        emitter.update(0.1);
+        emitter.update(0.1);
+        let particle = emitter.particles()[0];
+        assert_eq!(particle.velocity, Vec2::new(0.0, -2.0));
+        assert!(particle.position.y < 0.0);
+    }
+
+    #[test]
+    fn continuous_emission_follows_the_rate() {
+        let config = EmitterConfig { lifetime: (10.0, 10.0), spread: 0.0, ..EmitterConfig::default() };
+        let mut emitter = ParticleEmitter::new(config, 3);
+        emitter.rate = 30.0;
+        emitter.direction = PI / 2.0;
+        for _ in 0..60 {
+            emitter.update(1.0 / 60.0);
+        }
+        assert!((29..=30).contains(&emitter.particles().len()), "{}", emitter.particles().len());
+        assert!(emitter.particles().iter().all(|particle| particle.velocity.y > 0.0 && particle.velocity.x.abs() < 1e-3));
+
+        emitter.config.max_particles = 10;
+        emitter.burst(Vec2::ZERO, 0.0, 15);
+        assert_eq!(emitter.particles().len(), 10);
+    }
+
+    #[test]
+    fn gradient_blends_between_stops() {
+        let gradient = Gradient::new(&[(1.0, [0.0, 0.0, 1.0, 0.0]), (0.0, [1.0, 0.0, 0.0, 1.0]), (0.5, [0.0, 1.0, 0.0, 1.0])]);
+        assert_eq!(gradient.sample(-1.0), [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(gradient.sample(0.25), [0.5, 0.5, 0.0, 1.0]);
+        assert_eq!(gradient.sample(0.75), [0.0, 0.5, 0.5, 0.5]);
+        assert_eq!(gradient.sample(2.0), [0.0, 0.0, 1.0, 0.0]);
+        assert_eq!(Gradient::fade([1.0; 4]).sample(0.5), [1.0, 1.0, 1.0, 0.5]);
+    }
+
+    #[test]
+    fn trauma_shakes_then_wears_off() {
+        let mut shake = ScreenShake::default();
+        assert_eq!(shake.offset(), (Vec2::ZERO, 0.0));
+
+        shake.add_trauma(2.0);
+        assert_eq!(shake.trauma(), 1.0);
+        let mut moved = false;
+        for _ in 0..30 {
+            shake.update(1.0 / 60.0);
+            let (offset, angle) = shake.offset();
+            assert!(offset.x.abs() <= shake.max_offset && offset.y.abs() <= shake.max_offset);
+            assert!(angle.abs() <= shake.max_angle);
+            moved |= offset != Vec2::ZERO;
+        }
+        assert!(moved);
+        shake.update(1.0);
+        assert_eq!(shake.offset(), (Vec2::ZERO, 0.0));
+    }
+
+    #[test]
+    fn trail_keeps_the_last_points() {
+        let mut trail = Trail::new(3, 1.0);
+        for x in 0..5 {
+            trail.push(Vec2::new(x as f32, 0.0));
+            trail.push(Vec2::new(x as f32 + 0.5, 0.0));
+        }
+        let points: Vec<(Vec2, f32)> = trail.points().collect();
+        assert_eq!(points.len(), 3);
+        assert_eq!(points[0].0, Vec2::new(2.0, 0.0));
+        assert_eq!(points[2], (Vec2::new(4.0, 0.0), 1.0));
+    }
+
+    #[test]
+    fn match_events_make_sparks_and_shake() {
+        let mut sim = PongSim::new(PongConfig::default());
+        let mut effects = MatchEffects::default();
+
+        sim.ball.position = Vec2::new(-360.0, 0.0);
+        sim.ball.velocity = Vec2::new(-300.0, 0.0);
+        let events = sim.step(0.1, Default::default());
+        assert!(events.contains(&Event::PaddleHit(Side::Left)));
+        effects.handle(&sim, &events);
+        assert_eq!(effects.sparks.particles().len(), HIT_SPARKS);
+        assert!(effects.sparks.particles().iter().all(|particle| particle.velocity.x > 0.0));
+        assert_eq!(effects.shake.trauma(), HIT_TRAUMA);
+
+        effects.update(&sim, 0.01);
+        assert_eq!(effects.trail.points().count(), 1);
+        effects.handle(&sim, &[Event::Goal(Side::Left)]);
+        assert_eq!(effects.trail.points().count(), 0);
+        let goal = effects.sparks.particles().last().unwrap();
+        assert_eq!(goal.position.x, sim.config.arena_width / 2.0);
+    }
+}
@@ -0,0 +1,258 @@
+use crate::ai::{AiController, Difficulty};
+use crate::config::PongConfig;
+use crate::render::Pixels;
+use crate::sim::{Event, Inputs, PongSim, Side};
+
+/// Number of values in [`Observation::state`].
+pub const OBSERVATION_SIZE: usize = 8;
+
+/// Settings of a [`PongEnv`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvConfig {
+    pub pong: PongConfig,
+    /// The paddle played by the agent.
+    pub side: Side,
+    /// The computer plays the other paddle.
+    pub opponent: Difficulty,
+    /// Duration of a simulation step in seconds.
+    pub step: f32,
+    /// Simulation steps per call to [`PongEnv::step`], with the same action.
+    pub action_repeat: u32,
+    /// An episode is cut after this many calls to [`PongEnv::step`], 0 to let
+    /// it run until the match is won.
+    pub max_steps: u32,
+    /// Width and height of [`Observation::pixels`], `None` to skip the rendering.
+    pub pixels: Option<(usize, usize)>
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        EnvConfig {
+            pong: PongConfig::default(),
+            side: Side::Left,
+            opponent: Difficulty::NORMAL,
+            step: 1.0 / 60.0,
+            action_repeat: 1,
+            max_steps: 10_000,
+            pixels: None
+        }
+    }
+}
+
+/// What the agent sees, from its own point of view: it always plays on the
+/// left, the arena is mirrored for the right paddle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Observation {
+    /// Ball position and velocity, then the position and velocity of the agent
+    /// paddle and of the opponent paddle. Positions are between -1 and 1 in
+    /// the arena, velocities relative to the maximum ball and paddle speeds.
+    pub state: [f32; OBSERVATION_SIZE],
+    /// The whole arena, not mirrored, when [`EnvConfig::pixels`] is set.
+    pub pixels: Option<Pixels>
+}
+
+/// Pong as a reinforcement learning environment, in the manner of Gym: the
+/// agent plays a paddle against the computer, scoring gives a reward of 1 and
+/// conceding -1. Nothing is drawn unless pixel observations are asked for.
+#[derive(Debug, Clone)]
+pub struct PongEnv {
+    pub config: EnvConfig,
+    sim: PongSim,
+    opponent: AiController,
+    steps: u32
+}
+
+impl PongEnv {
+    /// Move the paddle down, keep it still or move it up, for agents with
+    /// discrete actions.
+    pub const ACTIONS: [f32; 3] = [-1.0, 0.0, 1.0];
+
+    pub fn new(config: EnvConfig) -> PongEnv {
+        let side = config.side;
+        PongEnv {
+            sim: PongSim::new(config.pong.clone()),
+            opponent: AiController::new(side.opponent(), config.opponent, 0),
+            steps: 0,
+            config
+        }
+    }
+
+    pub fn sim(&self) -> &PongSim {
+        &self.sim
+    }
+
+    /// Start a new episode, the same seed gives the same episode with the same
+    /// actions.
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.sim = PongSim::with_seed(self.config.pong.clone(), seed);
+        self.opponent = AiController::new(self.config.side.opponent(), self.config.opponent, seed.wrapping_add(1));
+        self.steps = 0;
+        self.observe()
+    }
+
+    /// Play `action`, the axis of the agent paddle between -1 (down) and 1
+    /// (up). Gives what the agent sees next, the reward and whether the
+    /// episode is over.
+    pub fn step(&mut self, action: f32) -> (Observation, f32, bool) {
+        let side = self.config.side;
+        let action = if action.is_nan() { 0.0 } else { action.clamp(-1.0, 1.0) };
+        let mut reward = 0.0;
+
+        for _ in 0..self.config.action_repeat.max(1) {
+            if self.sim.winner().is_some() {
+                break;
+            }
+            let mut inputs = Inputs::default();
+            inputs.set(side, action);
+            inputs.set(side.opponent(), self.opponent.update(&self.sim, self.config.step));
+
+            for event in self.sim.step(self.config.step, inputs) {
+                if let Event::Goal(scorer) = event {
+                    reward += if scorer == side { 1.0 } else { -1.0 };
+                }
+            }
+        }
+        self.steps += 1;
+
+        let cut = self.config.max_steps > 0 && self.steps >= self.config.max_steps;
+        let done = self.sim.winner().is_some() || cut;
+        (self.observe(), reward, done)
+    }
+
+    /// Like [`PongEnv::step`], with the index of one of [`PongEnv::ACTIONS`].
+    ///
+    /// Panics when the index is out of `ACTIONS`.
+    pub fn step_discrete(&mut self, action: usize) -> (Observation, f32, bool) {
+        self.step(PongEnv::ACTIONS[action])
+    }
+
+    fn observe(&self) -> Observation {
+        let config = &self.sim.config;
+        let side = self.config.side;
+        // the agent is on the left
+        let mirror = -side.sign();
+        let half_width = (config.arena_width / 2.0).max(f32::EPSILON);
+        let half_height = (config.arena_height / 2.0).max(f32::EPSILON);
+        let ball_speed = config.max_ball_speed.max(f32::EPSILON);
+        let paddle_speed = config.paddle_speed.max(f32::EPSILON);
+
+        let ball = &self.sim.ball;
+        let own = self.sim.paddle(side);
+        let other = self.sim.paddle(side.opponent());
+        let state = [
+            ball.position.x * mirror / half_width,
+            ball.position.y / half_height,
+            ball.velocity.x * mirror / ball_speed,
+            ball.velocity.y / ball_speed,
+            own.position.y / half_height,
+            own.velocity / paddle_speed,
+            other.position.y / half_height,
+            other.velocity / paddle_speed
+        ];
+
+        let pixels = self.config.pixels.map(|(width, height)| {
+            let mut pixels = Pixels::new(width, height);
+            pixels.render(&self.sim);
+            pixels
+        });
+
+        Observation { state, pixels }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Follows the ball.
+    fn follow(observation: &Observation) -> f32 {
+        let state = observation.state;
+        ((state[1] - state[4]) * 10.0).clamp(-1.0, 1.0)
+    }
+
+    fn episode(env: &mut PongEnv, seed: u64) -> (Vec<[f32; OBSERVATION_SIZE]>, f32) {
+        let mut observation = env.reset(seed);
+        let mut states = vec![observation.state];
+        let mut total = 0.0;
+        loop {
+            let (next, reward, done) = env.step(follow(&observation));
+            states.push(next.state);
+            total += reward;
+            observation = next;
+            if done {
+                return (states, total);
+            }
+        }
+    }
+
+    #[test]
+    fn episodes_end_with_the_match() {
+        let config = EnvConfig {
+            pong: PongConfig { points_to_win: 3, win_by: 1, ..PongConfig::default() },
+            max_steps: 0,
+            ..EnvConfig::default()
+        };
+        let mut env = PongEnv::new(config);
+        env.reset(1);
+
+        // a paddle that does not move loses every point
+        let mut total = 0.0;
+        for _ in 0..100_000 {
+            let (_, reward, done) = env.step(0.0);
+            total += reward;
+            if done {
+                break;
+            }
+        }
+
+        let score = &env.sim().score;
+        assert!(env.sim().winner().is_some(), "{:?}", score);
+        assert_eq!(total, score.left as f32 - score.right as f32);
+        assert!(total < 0.0);
+    }
+
+    #[test]
+    fn same_seed_same_episode() {
+        let config = EnvConfig { max_steps: 2_000, ..EnvConfig::default() };
+        let mut env = PongEnv::new(config);
+        let first = episode(&mut env, 5);
+        assert_eq!(first.0.len(), 2_001);
+        assert_eq!(episode(&mut env, 5), first);
+        assert_ne!(episode(&mut env, 6), first);
+    }
+
+    #[test]
+    fn right_side_sees_the_mirrored_arena() {
+        let mut left = PongEnv::new(EnvConfig::default());
+        let mut right = PongEnv::new(EnvConfig { side: Side::Right, ..EnvConfig::default() });
+        left.reset(0);
+        right.reset(0);
+
+        let (left, _, _) = left.step(0.0);
+        let (right, _, _) = right.step(0.0);
+        assert_eq!(left.state[0], -right.state[0]);
+        assert_eq!(left.state[2], -right.state[2]);
+        assert!(left.state.iter().all(|value| value.abs() <= 1.0), "{:?}", left.state);
+    }
+
+    #[test]
+    fn pixels_only_when_asked() {
+        let mut env = PongEnv::new(EnvConfig::default());
+        assert_eq!(env.reset(0).pixels, None);
+
+        let mut env = PongEnv::new(EnvConfig { pixels: Some((80, 60)), ..EnvConfig::default() });
+        let (observation, _, _) = env.step_discrete(2);
+        let pixels = observation.pixels.unwrap();
+        assert_eq!((pixels.width, pixels.height, pixels.data.len()), (80, 60, 4800));
+        assert!(pixels.data.iter().any(|pixel| *pixel != 0));
+    }
+
+    #[test]
+    fn action_repeat_steps_the_match_further() {
+        let mut env = PongEnv::new(EnvConfig { action_repeat: 4, ..EnvConfig::default() });
+        env.reset(0);
+        env.step(1.0);
+        let expected = env.config.pong.paddle_speed * env.config.step * 4.0;
+        assert!((env.sim().paddle(Side::Left).position.y - expected).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,122 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::config::PongConfig;
+
+impl PongConfig {
+    /// Read a RON config file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<PongConfig, String> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|error| format!("Can not read {}: {}", path.display(), error))?;
+
+        PongConfig::from_ron(&text).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+}
+
+/// The data dir of the user in the manner of each system, where the games
+/// keep their saves. `None` when the environment does not tell.
+pub fn user_data_dir() -> Option<PathBuf> {
+    let var = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+    if cfg!(windows) {
+        var("APPDATA")
+    }
+    else if cfg!(target_os = "macos") {
+        var("HOME").map(|home| home.join("Library").join("Application Support"))
+    }
+    else {
+        var("XDG_DATA_HOME").or_else(|| var("HOME").map(|home| home.join(".local").join("share")))
+    }
+}
+
+/// A config file watched for changes, so that the game can be tuned while it
+/// runs. The front-ends poll it, about once per second is plenty.
+#[derive(Debug, Clone)]
+pub struct ConfigFile {
+    path: PathBuf,
+    modified: Option<SystemTime>
+}
+
+impl ConfigFile {
+    pub fn new<P: Into<PathBuf>>(path: P) -> ConfigFile {
+        ConfigFile { path: path.into(), modified: None }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Load the file, or the default config with the error when it can not be
+    /// read, so that a game always starts.
+    pub fn load(&mut self) -> (PongConfig, Option<String>) {
+        self.modified = self.modified_time();
+        match PongConfig::load(&self.path) {
+            Ok(config) => (config, None),
+            Err(error) => (PongConfig::default(), Some(error))
+        }
+    }
+
+    /// The new config when the file changed since the last load or reload.
+    /// An invalid file is reported once, the game keeps its current config.
+    pub fn reload(&mut self) -> Option<Result<PongConfig, String>> {
+        let modified = self.modified_time();
+        if modified.is_none() || modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+
+        Some(PongConfig::load(&self.path))
+    }
+
+    fn modified_time(&self) -> Option<SystemTime> {
+        fs::metadata(&self.path).and_then(|metadata| metadata.modified()).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::time::Duration;
+
+    use super::*;
+
+    fn write(path: &Path, text: &str, age: u64) {
+        fs::write(path, text).unwrap();
+        // file systems with a coarse clock would see the same time twice
+        let time = SystemTime::now() - Duration::from_secs(age);
+        File::options().write(true).open(path).unwrap().set_modified(time).unwrap();
+    }
+
+    #[test]
+    fn reload_only_when_the_file_changes() {
+        let path = std::env::temp_dir().join(format!("pong_sim_config_{}.ron", std::process::id()));
+        write(&path, "(paddle_speed: 200.0)", 20);
+
+        let mut file = ConfigFile::new(&path);
+        let (config, error) = file.load();
+        assert_eq!(error, None);
+        assert_eq!(config.paddle_speed, 200.0);
+        assert_eq!(file.reload(), None);
+
+        write(&path, "(paddle_speed: 300.0)", 10);
+        let config = file.reload().unwrap().unwrap();
+        assert_eq!(config.paddle_speed, 300.0);
+        assert_eq!(file.reload(), None);
+
+        write(&path, "(paddle_speed: oops)", 0);
+        assert!(file.reload().unwrap().is_err());
+        assert_eq!(file.reload(), None);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn missing_file_falls_back_to_the_defaults() {
+        let mut file = ConfigFile::new("does/not/exist.ron");
+        let (config, error) = file.load();
+        assert_eq!(config, PongConfig::default());
+        assert!(error.is_some());
+        assert_eq!(file.reload(), None);
+    }
+}
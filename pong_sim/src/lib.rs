@@ -0,0 +1,44 @@
+//! Engine agnostic Pong rules.
+//!
+//! The amethyst, godot and bevy front-ends only read inputs, call
+//! [`PongSim::step`] and draw the resulting state. The arena is centered on the
+//! origin and the y axis goes up, each front-end maps it to its own space.
+
+mod ai;
+mod audio;
+mod bindings;
+mod bounce;
+mod collision;
+mod config;
+mod effects;
+mod env;
+mod file;
+mod math;
+mod net;
+mod party;
+mod rally;
+mod render;
+mod replay;
+mod rng;
+mod rollback;
+mod sim;
+mod stats;
+mod timestep;
+
+pub use crate::ai::{predict_intercept, AiController, Difficulty};
+pub use crate::audio::{party_sound_for, sound_for, AudioBackend, AudioPlayer, Channel, Mixer, Music, NullBackend, Sound};
+pub use crate::bindings::{Action, BindingMenu, Bindings, Control, MenuEntry, PlayerBindings};
+pub use crate::config::{PongConfig, ServeRule};
+pub use crate::effects::{EmitterConfig, Gradient, MatchEffects, Particle, ParticleEmitter, Rgba, ScreenShake, Trail};
+pub use crate::env::{EnvConfig, Observation, PongEnv, OBSERVATION_SIZE};
+pub use crate::file::{user_data_dir, ConfigFile};
+pub use crate::math::Vec2;
+pub use crate::net::{LocalTransport, LossyTransport, Peer, Transport, UdpTransport};
+pub use crate::party::{PartyEvent, PartyFrame, PartyInputs, PartyPaddle, PartySim, Wall};
+pub use crate::render::Pixels;
+pub use crate::replay::{InputRun, Replay, ReplayInputs};
+pub use crate::rng::Rng;
+pub use crate::rollback::{dequantize, quantize, Message, NetInput, RollbackSession, RollbackSettings};
+pub use crate::sim::{Ball, Effect, Event, Frame, Inputs, Paddle, PongSim, PowerUp, PowerUpKind, Score, Side};
+pub use crate::stats::{History, MatchRecord, MatchStats, MAX_HIGH_SCORES, MAX_MATCHES};
+pub use crate::timestep::FixedTimestep;
@@ -0,0 +1,217 @@
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+use crate::rng::Rng;
+use crate::rollback::{Message, RollbackSession};
+use crate::sim::Event;
+
+/// Unreliable, unordered packets to the remote peer.
+pub trait Transport {
+    fn send(&mut self, packet: &[u8]);
+
+    /// The next packet received, without blocking.
+    fn receive(&mut self) -> Option<Vec<u8>>;
+}
+
+/// Packets over a UDP socket connected to the remote peer.
+pub struct UdpTransport {
+    socket: UdpSocket
+}
+
+/// Larger than any message.
+const MAX_PACKET_SIZE: usize = 512;
+
+impl UdpTransport {
+    pub fn new(socket: UdpSocket, remote: SocketAddr) -> io::Result<UdpTransport> {
+        socket.connect(remote)?;
+        socket.set_nonblocking(true)?;
+        Ok(UdpTransport { socket })
+    }
+
+    pub fn bind(local: SocketAddr, remote: SocketAddr) -> io::Result<UdpTransport> {
+        UdpTransport::new(UdpSocket::bind(local)?, remote)
+    }
+}
+
+impl Transport for UdpTransport {
+    fn send(&mut self, packet: &[u8]) {
+        // lost like any packet when the remote is not there yet
+        let _ = self.socket.send(packet);
+    }
+
+    fn receive(&mut self) -> Option<Vec<u8>> {
+        let mut buffer = [0; MAX_PACKET_SIZE];
+        loop {
+            match self.socket.recv(&mut buffer) {
+                Ok(size) => return Some(buffer[..size].to_vec()),
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => return None,
+                // an earlier packet was refused by the remote, not this one
+                Err(_) => continue
+            }
+        }
+    }
+}
+
+/// Packets between two peers in the same process.
+pub struct LocalTransport {
+    sender: Sender<Vec<u8>>,
+    receiver: Receiver<Vec<u8>>
+}
+
+impl LocalTransport {
+    pub fn pair() -> (LocalTransport, LocalTransport) {
+        let (left_sender, right_receiver) = channel();
+        let (right_sender, left_receiver) = channel();
+        (
+            LocalTransport { sender: left_sender, receiver: left_receiver },
+            LocalTransport { sender: right_sender, receiver: right_receiver }
+        )
+    }
+}
+
+impl Transport for LocalTransport {
+    fn send(&mut self, packet: &[u8]) {
+        let _ = self.sender.send(packet.to_vec());
+    }
+
+    fn receive(&mut self) -> Option<Vec<u8>> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+/// Delays and drops the outgoing packets of another transport, to try the
+/// netcode on one machine. With some jitter the packets arrive out of order.
+pub struct LossyTransport<T> {
+    inner: T,
+    latency: Duration,
+    jitter: Duration,
+    loss: f32,
+    rng: Rng,
+    queue: Vec<(Instant, Vec<u8>)>
+}
+
+impl<T: Transport> LossyTransport<T> {
+    /// `loss` is the probability to drop a packet, between 0 and 1.
+    pub fn new(inner: T, latency: Duration, jitter: Duration, loss: f32, seed: u64) -> LossyTransport<T> {
+        LossyTransport { inner, latency, jitter, loss, rng: Rng::new(seed), queue: Vec::new() }
+    }
+
+    fn flush(&mut self) {
+        let now = Instant::now();
+        let inner = &mut self.inner;
+        self.queue.retain(|(time, packet)| {
+            if *time > now {
+                return true;
+            }
+            inner.send(packet);
+            false
+        });
+    }
+}
+
+impl<T: Transport> Transport for LossyTransport<T> {
+    fn send(&mut self, packet: &[u8]) {
+        if self.rng.next_f32() >= self.loss {
+            let delay = self.latency + self.jitter.mul_f32(self.rng.next_f32());
+            self.queue.push((Instant::now() + delay, packet.to_vec()));
+        }
+        self.flush();
+    }
+
+    fn receive(&mut self) -> Option<Vec<u8>> {
+        self.flush();
+        self.inner.receive()
+    }
+}
+
+/// A rollback session talking to its remote peer through a transport.
+pub struct Peer<T> {
+    pub session: RollbackSession,
+    transport: T
+}
+
+impl<T: Transport> Peer<T> {
+    pub fn new(session: RollbackSession, transport: T) -> Peer<T> {
+        Peer { session, transport }
+    }
+
+    /// One frame of a networked match: read the remote messages, simulate
+    /// with the local axis when possible and tell the remote peer about it.
+    pub fn update(&mut self, local_axis: f32) -> Option<Vec<Event>> {
+        self.poll();
+        let events = self.session.advance(local_axis);
+        self.send();
+        events
+    }
+
+    /// Read the remote messages and correct the mispredictions, without
+    /// simulating a new frame. Invalid packets are ignored.
+    pub fn poll(&mut self) {
+        while let Some(packet) = self.transport.receive() {
+            if let Ok(message) = Message::decode(&packet) {
+                self.session.receive(&message);
+            }
+        }
+        self.session.rollback();
+    }
+
+    pub fn send(&mut self) {
+        self.transport.send(&self.session.message().encode());
+    }
+
+    /// Both peers have all the inputs up to `frame`.
+    pub fn is_synchronized(&self, frame: u32) -> bool {
+        self.session.confirmed_frame() >= frame && self.session.remote_ack() >= frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PongConfig;
+    use crate::rollback::RollbackSettings;
+    use crate::sim::Side;
+
+    #[test]
+    fn lossy_transport_delays_and_drops() {
+        let (left, mut right) = LocalTransport::pair();
+        let mut left = LossyTransport::new(left, Duration::from_millis(20), Duration::ZERO, 0.5, 1);
+
+        for index in 0..100u8 {
+            left.send(&[index]);
+        }
+        assert_eq!(right.receive(), None);
+
+        std::thread::sleep(Duration::from_millis(30));
+        left.flush();
+        let received: Vec<Vec<u8>> = std::iter::from_fn(|| right.receive()).collect();
+        assert!(received.len() > 25 && received.len() < 75, "{} packets received", received.len());
+    }
+
+    #[test]
+    fn peers_over_a_local_transport() {
+        let (left, right) = LocalTransport::pair();
+        let session = |side| RollbackSession::new(PongConfig::default(), side, RollbackSettings::default());
+        let mut left = Peer::new(session(Side::Left), left);
+        let mut right = Peer::new(session(Side::Right), right);
+
+        while left.session.frame() < 600 || !left.is_synchronized(600) || !right.is_synchronized(600) {
+            if left.session.frame() < 600 {
+                left.update(1.0);
+                right.update(-1.0);
+            }
+            else {
+                left.poll();
+                left.send();
+                right.poll();
+                right.send();
+            }
+        }
+
+        assert_eq!(left.session.sim(), right.session.sim());
+        assert_eq!(left.session.sim().paddle(Side::Left).position.y, 260.0);
+        assert_eq!(left.session.sim().paddle(Side::Right).position.y, -260.0);
+    }
+}
@@ -0,0 +1,503 @@
+use crate::ai::Difficulty;
+use crate::bounce::{self, Contact, PaddleBody, WallBody};
+use crate::config::PongConfig;
+use crate::math::Vec2;
+use crate::rally::{self, Serve, ServeStep};
+use crate::sim::{Ball, Side};
+
+/// A side of the four-player arena, each one has a paddle and a goal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Wall {
+    Left,
+    Right,
+    Bottom,
+    Top
+}
+
+impl Wall {
+    /// In the order of the players, and of the serves.
+    pub const ALL: [Wall; 4] = [Wall::Left, Wall::Top, Wall::Right, Wall::Bottom];
+
+    /// Index in [`PartySim::paddles`] and [`PartyInputs`].
+    pub fn index(self) -> usize {
+        match self {
+            Wall::Left => 0,
+            Wall::Top => 1,
+            Wall::Right => 2,
+            Wall::Bottom => 3
+        }
+    }
+
+    /// Unit vector from the wall toward the center of the arena.
+    pub fn normal(self) -> Vec2 {
+        match self {
+            Wall::Left => Vec2::new(1.0, 0.0),
+            Wall::Right => Vec2::new(-1.0, 0.0),
+            Wall::Bottom => Vec2::new(0.0, 1.0),
+            Wall::Top => Vec2::new(0.0, -1.0)
+        }
+    }
+
+    /// Direction a positive axis moves the paddle: up on the left and right
+    /// walls, right on the top and bottom ones.
+    pub fn tangent(self) -> Vec2 {
+        if self.is_vertical() { Vec2::new(0.0, 1.0) } else { Vec2::new(1.0, 0.0) }
+    }
+
+    /// The left and right walls, their paddles move along y.
+    pub fn is_vertical(self) -> bool {
+        matches!(self, Wall::Left | Wall::Right)
+    }
+
+    fn next(self) -> Wall {
+        Wall::ALL[(self.index() + 1) % Wall::ALL.len()]
+    }
+}
+
+impl From<Side> for Wall {
+    fn from(side: Side) -> Wall {
+        match side {
+            Side::Left => Wall::Left,
+            Side::Right => Wall::Right
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartyPaddle {
+    pub wall: Wall,
+    /// Center of the paddle.
+    pub position: Vec2,
+    /// Speed along the wall tangent during the last step, for the spin.
+    pub velocity: f32
+}
+
+/// Paddle axes for one step, between -1 and 1 along [`Wall::tangent`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PartyInputs {
+    pub axes: [f32; 4]
+}
+
+impl PartyInputs {
+    pub fn get(&self, wall: Wall) -> f32 {
+        self.axes[wall.index()]
+    }
+
+    pub fn set(&mut self, wall: Wall, axis: f32) {
+        self.axes[wall.index()] = axis;
+    }
+}
+
+/// What happened during a step of a four-player match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartyEvent {
+    /// The ball left the center.
+    Serve,
+    /// The ball bounced on the wall of a player knocked out.
+    WallBounce,
+    PaddleHit(Wall),
+    /// The ball went through the goal of `conceder`, the last player who
+    /// hit it scores unless it is the conceder.
+    Goal { conceder: Wall, scorer: Option<Wall> },
+    /// This player has no lives left, their wall is closed.
+    Eliminated(Wall),
+    /// Last player standing, nothing moves until [`PartySim::restart`].
+    MatchOver(Wall)
+}
+
+/// Positions to draw, between two steps of the simulation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PartyFrame {
+    pub ball: Vec2,
+    /// In the order of [`Wall::index`].
+    pub paddles: [Vec2; 4]
+}
+
+impl PartyFrame {
+    pub fn paddle(&self, wall: Wall) -> Vec2 {
+        self.paddles[wall.index()]
+    }
+}
+
+/// Four-player Pong, a paddle and a goal on every wall of the arena.
+///
+/// Every goal costs a life to the player who let it in, and gives a point
+/// to the one who hit the ball last. A player without lives is knocked out
+/// and their wall closes, the last one standing wins. The top and bottom
+/// paddles are the side paddles turned a quarter: `paddle_height` along the
+/// wall and `paddle_width` across.
+///
+/// It is kept apart from [`PongSim`](crate::PongSim) rather than making
+/// [`Side`] four-valued: lives, knock-outs and closing walls share nothing
+/// with the score, power-ups and replays of a two-player match, and would
+/// change every front-end reading `Side`. What both play the same way, the
+/// ball physics, the serve, the paddle moves, the goals and the frame
+/// blending, lives in `bounce` and `rally` so that they do not drift apart.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartySim {
+    pub config: PongConfig,
+    pub ball: Ball,
+    pub paddles: [PartyPaddle; 4],
+    pub lives: [u32; 4],
+    pub points: [u32; 4],
+    last_hit: Option<Wall>,
+    /// The next ball is served toward this player.
+    receiver: Wall,
+    serve: Serve,
+    winner: Option<Wall>
+}
+
+impl PartySim {
+    pub fn new(config: PongConfig) -> PartySim {
+        let paddle = |wall: Wall| PartyPaddle { wall, position: paddle_base(&config, wall), velocity: 0.0 };
+        let mut ball = Ball { position: Vec2::ZERO, velocity: Vec2::ZERO };
+        let mut sim = PartySim {
+            serve: Serve::stop(&mut ball),
+            ball,
+            paddles: [paddle(Wall::Left), paddle(Wall::Top), paddle(Wall::Right), paddle(Wall::Bottom)],
+            lives: [config.lives.max(1); 4],
+            points: [0; 4],
+            last_hit: None,
+            receiver: Wall::Right,
+            winner: None,
+            config
+        };
+        sim.reset_ball();
+
+        sim
+    }
+
+    /// Start a new match with the same config.
+    pub fn restart(&mut self) {
+        *self = PartySim::new(self.config.clone());
+    }
+
+    pub fn winner(&self) -> Option<Wall> {
+        self.winner
+    }
+
+    pub fn paddle(&self, wall: Wall) -> &PartyPaddle {
+        &self.paddles[wall.index()]
+    }
+
+    /// Whether the player of this wall is still in the match.
+    pub fn is_alive(&self, wall: Wall) -> bool {
+        self.lives[wall.index()] > 0
+    }
+
+    /// Blend `previous`, the state before the last step, with this one.
+    pub fn frame(&self, previous: &PartySim, alpha: f32) -> PartyFrame {
+        // the ball jumps back to the center after a goal
+        let ball = rally::blend(previous.ball.position, self.ball.position, alpha, previous.lives != self.lives);
+
+        let mut paddles = [Vec2::ZERO; 4];
+        for (index, paddle) in paddles.iter_mut().enumerate() {
+            *paddle = rally::blend(previous.paddles[index].position, self.paddles[index].position, alpha, false);
+        }
+        PartyFrame { ball, paddles }
+    }
+
+    /// Seconds left before the serve, to show a countdown.
+    pub fn serve_countdown(&self) -> Option<f32> {
+        self.serve.countdown()
+    }
+
+    /// The axis a computer player gives to the paddle of `wall`: it follows the
+    /// ball along the wall, at the speed of the difficulty.
+    pub fn follow_ball(&self, wall: Wall, difficulty: Difficulty) -> f32 {
+        let tangent = wall.tangent();
+        let offset = (self.ball.position - self.paddle(wall).position).dot(tangent);
+        // slow down over the last tenth of a second of travel
+        let brake = (self.config.paddle_speed * 0.1).max(f32::EPSILON);
+        (offset / brake).clamp(-1.0, 1.0) * difficulty.max_speed
+    }
+
+    /// Advance the match by `dt` seconds.
+    pub fn step(&mut self, dt: f32, inputs: PartyInputs) -> Vec<PartyEvent> {
+        let mut events = Vec::new();
+        if self.winner.is_some() {
+            return events;
+        }
+
+        self.move_paddles(dt, inputs);
+
+        match self.serve.update(&mut self.ball, dt) {
+            ServeStep::Waiting => return events,
+            ServeStep::Served => events.push(PartyEvent::Serve),
+            ServeStep::InPlay => {}
+        }
+
+        self.move_ball(dt, &mut events);
+
+        if let Some(conceder) = self.conceder() {
+            self.goal(conceder, &mut events);
+        }
+
+        events
+    }
+
+    fn move_paddles(&mut self, dt: f32, inputs: PartyInputs) {
+        let config = &self.config;
+        for paddle in self.paddles.iter_mut() {
+            let wall = paddle.wall;
+            let limit = paddle_travel(config, wall);
+            let tangent = wall.tangent();
+
+            let (moved, velocity) = rally::slide(config, paddle.position.dot(tangent), inputs.get(wall), dt, limit);
+            paddle.position = paddle_base(config, wall) + tangent * moved;
+            paddle.velocity = velocity;
+        }
+    }
+
+    /// The two-player sweep, with the closed walls of the players knocked out
+    /// in place of the top and bottom walls.
+    fn move_ball(&mut self, dt: f32, events: &mut Vec<PartyEvent>) {
+        let config = &self.config;
+        let alive: Vec<&PartyPaddle> = self.paddles.iter().filter(|paddle| self.lives[paddle.wall.index()] > 0).collect();
+        let bodies: Vec<PaddleBody> = alive.iter()
+            .map(|paddle| PaddleBody {
+                position: paddle.position,
+                normal: paddle.wall.normal(),
+                tangent: paddle.wall.tangent(),
+                velocity: paddle.velocity,
+                length: config.paddle_height
+            })
+            .collect();
+        let walls: Vec<WallBody> = Wall::ALL.iter()
+            .filter(|wall| self.lives[wall.index()] == 0)
+            .map(|&wall| wall_body(config, wall))
+            .collect();
+
+        let last_hit = &mut self.last_hit;
+        bounce::move_ball(config, &mut self.ball, dt, &bodies, &walls, |contact| match contact {
+            Contact::Paddle(index) => {
+                *last_hit = Some(alive[index].wall);
+                events.push(PartyEvent::PaddleHit(alive[index].wall));
+            }
+            Contact::Wall => events.push(PartyEvent::WallBounce)
+        });
+    }
+
+    /// The player whose goal the ball went through.
+    fn conceder(&self) -> Option<Wall> {
+        Wall::ALL.iter().copied()
+            .find(|&wall| self.is_alive(wall) && wall_body(&self.config, wall).is_crossed(&self.config, &self.ball))
+    }
+
+    fn goal(&mut self, conceder: Wall, events: &mut Vec<PartyEvent>) {
+        let scorer = self.last_hit.filter(|&scorer| scorer != conceder);
+        if let Some(scorer) = scorer {
+            self.points[scorer.index()] += 1;
+        }
+        self.lives[conceder.index()] -= 1;
+        events.push(PartyEvent::Goal { conceder, scorer });
+
+        if !self.is_alive(conceder) {
+            events.push(PartyEvent::Eliminated(conceder));
+        }
+
+        let mut alive = Wall::ALL.iter().copied().filter(|&wall| self.is_alive(wall));
+        if let (Some(winner), None) = (alive.next(), alive.next()) {
+            self.winner = Some(winner);
+            self.serve = Serve::stop(&mut self.ball);
+            events.push(PartyEvent::MatchOver(winner));
+            return;
+        }
+
+        // the serves go around the arena, skipping the players knocked out
+        self.receiver = self.receiver.next();
+        while !self.is_alive(self.receiver) {
+            self.receiver = self.receiver.next();
+        }
+        self.reset_ball();
+    }
+
+    /// Put the ball back at the center, to be served toward the receiver.
+    fn reset_ball(&mut self) {
+        let velocity = self.config.ball_velocity;
+        let velocity = -self.receiver.normal() * velocity.x.abs() + self.receiver.tangent() * velocity.y;
+
+        self.last_hit = None;
+        self.serve = Serve::new(&self.config, &mut self.ball, velocity);
+    }
+}
+
+/// Distance from the center of the arena to a wall.
+fn wall_distance(config: &PongConfig, wall: Wall) -> f32 {
+    if wall.is_vertical() { config.arena_width / 2.0 } else { config.arena_height / 2.0 }
+}
+
+/// The wall of a player knocked out, and the goal of the others.
+fn wall_body(config: &PongConfig, wall: Wall) -> WallBody {
+    WallBody { normal: wall.normal(), distance: wall_distance(config, wall) }
+}
+
+/// Center of the paddle of `wall` when it is in the middle of its wall.
+fn paddle_base(config: &PongConfig, wall: Wall) -> Vec2 {
+    -wall.normal() * (wall_distance(config, wall) - config.paddle_width / 2.0)
+}
+
+/// How far a paddle goes from the middle of its wall, it stays out of the
+/// corners so that two paddles never overlap.
+fn paddle_travel(config: &PongConfig, wall: Wall) -> f32 {
+    let length = if wall.is_vertical() { config.arena_height } else { config.arena_width };
+    ((length - config.paddle_height) / 2.0 - config.paddle_width).max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    fn sim() -> PartySim {
+        PartySim::new(PongConfig { lives: 2, paddle_spin: 0.0, ..PongConfig::default() })
+    }
+
+    fn inputs(wall: Wall, axis: f32) -> PartyInputs {
+        let mut inputs = PartyInputs::default();
+        inputs.set(wall, axis);
+        inputs
+    }
+
+    /// Send the ball straight through the goal of `conceder`, after a hit of `scorer`.
+    fn goal(sim: &mut PartySim, conceder: Wall, scorer: Option<Wall>) -> Vec<PartyEvent> {
+        sim.last_hit = scorer;
+        let paddle = sim.paddle(conceder).position;
+        // past the end of the paddle, along the wall
+        let aside = conceder.tangent() * (sim.config.paddle_height + 30.0);
+        sim.ball.position = paddle + aside + conceder.normal() * 20.0;
+        sim.ball.velocity = -conceder.normal() * 600.0;
+        sim.step(0.1, PartyInputs::default())
+    }
+
+    #[test]
+    fn top_and_bottom_paddles_move_along_x_out_of_the_corners() {
+        // the paddles move during the serve countdown
+        let mut sim = PartySim::new(PongConfig { serve_delay: 100.0, ..PongConfig::default() });
+        assert_eq!(sim.paddle(Wall::Top).position, Vec2::new(0.0, 290.0));
+        assert_eq!(sim.paddle(Wall::Bottom).position, Vec2::new(0.0, -290.0));
+
+        for _ in 0..1000 {
+            sim.step(DT, PartyInputs { axes: [1.0, 1.0, -1.0, -1.0] });
+        }
+        // the arena is 800 wide, the paddles 80 long and 20 thick
+        assert_eq!(sim.paddle(Wall::Top).position, Vec2::new(340.0, 290.0));
+        assert_eq!(sim.paddle(Wall::Bottom).position, Vec2::new(-340.0, -290.0));
+        assert_eq!(sim.paddle(Wall::Left).position, Vec2::new(-390.0, 240.0));
+        assert_eq!(sim.paddle(Wall::Right).position, Vec2::new(390.0, -240.0));
+    }
+
+    #[test]
+    fn ball_bounces_on_the_top_paddle() {
+        let mut sim = sim();
+        sim.ball.position = Vec2::new(0.0, 260.0);
+        sim.ball.velocity = Vec2::new(0.0, 300.0);
+
+        let events = sim.step(0.1, PartyInputs::default());
+        assert_eq!(events, vec![PartyEvent::PaddleHit(Wall::Top)]);
+        assert_eq!(sim.ball.velocity, Vec2::new(0.0, -300.0 * sim.config.ball_acceleration));
+        assert!(sim.ball.position.y <= 270.0);
+
+        // off center, toward the end of the paddle it goes
+        sim.ball.position = Vec2::new(25.0, 260.0);
+        sim.ball.velocity = Vec2::new(0.0, 300.0);
+        sim.step(0.1, inputs(Wall::Top, 0.0));
+        let velocity = sim.ball.velocity;
+        assert!((velocity.x.atan2(-velocity.y).to_degrees() - 30.0).abs() < 1e-3, "{:?}", velocity);
+    }
+
+    #[test]
+    fn goal_costs_a_life_and_scores_for_the_last_hitter() {
+        let mut sim = sim();
+        let events = goal(&mut sim, Wall::Bottom, Some(Wall::Left));
+        assert_eq!(events, vec![PartyEvent::Goal { conceder: Wall::Bottom, scorer: Some(Wall::Left) }]);
+        assert_eq!(sim.lives, [2, 2, 2, 1]);
+        assert_eq!(sim.points, [1, 0, 0, 0]);
+        assert_eq!(sim.ball.position, Vec2::ZERO);
+
+        // no point for putting the ball in your own goal
+        let events = goal(&mut sim, Wall::Left, Some(Wall::Left));
+        assert_eq!(events, vec![PartyEvent::Goal { conceder: Wall::Left, scorer: None }]);
+        assert_eq!(sim.points, [1, 0, 0, 0]);
+    }
+
+    #[test]
+    fn knocked_out_walls_close() {
+        let mut sim = sim();
+        goal(&mut sim, Wall::Top, None);
+        let events = goal(&mut sim, Wall::Top, Some(Wall::Right));
+        assert!(events.contains(&PartyEvent::Eliminated(Wall::Top)), "{:?}", events);
+        assert!(!sim.is_alive(Wall::Top));
+
+        // the ball now bounces where the goal was
+        sim.ball.position = Vec2::new(200.0, 250.0);
+        sim.ball.velocity = Vec2::new(0.0, 600.0);
+        let events = sim.step(0.1, PartyInputs::default());
+        assert_eq!(events, vec![PartyEvent::WallBounce]);
+        assert_eq!(sim.ball.velocity, Vec2::new(0.0, -600.0));
+        assert!(sim.ball.position.y <= 290.0);
+    }
+
+    #[test]
+    fn serves_go_around_the_players_left() {
+        let mut sim = sim();
+        assert!(sim.ball.velocity.x > 0.0);
+
+        goal(&mut sim, Wall::Right, None);
+        goal(&mut sim, Wall::Right, None);
+        // after the right player, toward the bottom, then the left one
+        assert_eq!(sim.receiver, Wall::Left);
+        assert!(sim.ball.velocity.x < 0.0);
+        goal(&mut sim, Wall::Left, None);
+        assert_eq!(sim.receiver, Wall::Top);
+        goal(&mut sim, Wall::Left, None);
+        assert_eq!(sim.receiver, Wall::Bottom);
+        assert!(sim.ball.velocity.y < 0.0);
+    }
+
+    #[test]
+    fn last_player_standing_wins() {
+        let mut sim = sim();
+        for &wall in [Wall::Left, Wall::Top, Wall::Right].iter() {
+            goal(&mut sim, wall, Some(Wall::Bottom));
+            let events = goal(&mut sim, wall, Some(Wall::Bottom));
+            assert!(events.contains(&PartyEvent::Eliminated(wall)));
+        }
+        assert_eq!(sim.winner(), Some(Wall::Bottom));
+        assert_eq!(sim.points, [0, 0, 0, 6]);
+
+        let frozen = sim.clone();
+        assert!(sim.step(1.0, PartyInputs { axes: [1.0; 4] }).is_empty());
+        assert_eq!(sim, frozen);
+
+        sim.restart();
+        assert_eq!(sim.winner(), None);
+        assert_eq!(sim.lives, [2; 4]);
+    }
+
+    #[test]
+    fn ball_never_leaves_through_a_closed_wall() {
+        let mut rng = Rng::new(7);
+        let mut sim = PartySim::new(PongConfig { lives: 3, ball_acceleration: 1.1, ..PongConfig::default() });
+        for _ in 0..50_000 {
+            let mut inputs = PartyInputs::default();
+            for &wall in Wall::ALL.iter() {
+                inputs.set(wall, sim.follow_ball(wall, Difficulty::NORMAL) * rng.range(0.0, 1.2));
+            }
+            for event in sim.step(DT, inputs) {
+                if let PartyEvent::Goal { conceder, .. } = event {
+                    assert!(sim.lives[conceder.index()] < 3);
+                }
+            }
+            for &wall in Wall::ALL.iter().filter(|&&wall| !sim.is_alive(wall)) {
+                let limit = wall_distance(&sim.config, wall) - sim.config.ball_radius;
+                assert!(sim.ball.position.dot(-wall.normal()) <= limit + 1e-3, "{:?}", sim.ball);
+            }
+            if sim.winner().is_some() {
+                sim.restart();
+            }
+        }
+    }
+}
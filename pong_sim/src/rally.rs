@@ -0,0 +1,78 @@
+use crate::config::PongConfig;
+use crate::math::Vec2;
+use crate::sim::Ball;
+
+/// The ball waiting at the center before a serve, shared by the two and
+/// four-player matches so that they serve the same way.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Serve {
+    /// Seconds left before the ball leaves, 0 once it is in play.
+    timer: f32,
+    velocity: Vec2
+}
+
+/// What a step of the serve leaves to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ServeStep {
+    /// The ball still waits, nothing else moves.
+    Waiting,
+    /// The ball left the center during this step.
+    Served,
+    InPlay
+}
+
+impl Serve {
+    /// Put the ball back at the center, to leave with `velocity` after the
+    /// serve delay.
+    pub fn new(config: &PongConfig, ball: &mut Ball, velocity: Vec2) -> Serve {
+        ball.position = Vec2::ZERO;
+        if config.serve_delay > 0.0 {
+            ball.velocity = Vec2::ZERO;
+            Serve { timer: config.serve_delay, velocity }
+        }
+        else {
+            ball.velocity = velocity;
+            Serve { timer: 0.0, velocity }
+        }
+    }
+
+    /// The ball at rest at the center for good, once the match is over.
+    pub fn stop(ball: &mut Ball) -> Serve {
+        ball.position = Vec2::ZERO;
+        ball.velocity = Vec2::ZERO;
+        Serve { timer: 0.0, velocity: Vec2::ZERO }
+    }
+
+    /// Seconds left before the serve.
+    pub fn countdown(&self) -> Option<f32> {
+        if self.timer > 0.0 { Some(self.timer) } else { None }
+    }
+
+    /// Count down by `dt` seconds, sending the ball once the delay is over.
+    pub fn update(&mut self, ball: &mut Ball, dt: f32) -> ServeStep {
+        if self.timer <= 0.0 {
+            return ServeStep::InPlay;
+        }
+        self.timer -= dt;
+        if self.timer > 0.0 {
+            return ServeStep::Waiting;
+        }
+        ball.velocity = self.velocity;
+        ServeStep::Served
+    }
+}
+
+/// Move a paddle `along` its wall for `dt` seconds by `axis` of the paddle
+/// speed, staying within `limit` of the middle. Gives the new place along the
+/// wall and the speed it moved at, for the spin.
+pub(crate) fn slide(config: &PongConfig, along: f32, axis: f32, dt: f32, limit: f32) -> (f32, f32) {
+    let moved = (along + axis.clamp(-1.0, 1.0) * config.paddle_speed * dt).clamp(-limit, limit);
+    let velocity = if dt > 0.0 { (moved - along) / dt } else { 0.0 };
+    (moved, velocity)
+}
+
+/// A position between the last two steps, or the last one when it jumped in
+/// between, like a ball put back at the center after a goal.
+pub(crate) fn blend(previous: Vec2, current: Vec2, alpha: f32, jumped: bool) -> Vec2 {
+    if jumped { current } else { previous.lerp(current, alpha) }
+}
@@ -0,0 +1,117 @@
+use crate::math::Vec2;
+use crate::sim::{PongSim, Side};
+
+/// Grayscale image of a match, one byte per pixel, rows from the top. The
+/// arena fills the whole image, whatever its aspect ratio.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pixels {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<u8>
+}
+
+/// Shade of the things drawn, the arena is black.
+const PADDLE_SHADE: u8 = 255;
+const BALL_SHADE: u8 = 255;
+const POWER_UP_SHADE: u8 = 128;
+
+impl Pixels {
+    pub fn new(width: usize, height: usize) -> Pixels {
+        Pixels { width, height, data: vec![0; width * height] }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.data[y * self.width + x]
+    }
+
+    /// Draw the paddles, the balls and the power-ups of `sim`, without the
+    /// score.
+    pub fn render(&mut self, sim: &PongSim) {
+        for pixel in self.data.iter_mut() {
+            *pixel = 0;
+        }
+
+        let config = &sim.config;
+        for power_up in sim.power_ups.iter() {
+            let half_size = Vec2::new(config.power_up_radius, config.power_up_radius);
+            self.fill(sim, power_up.position, half_size, POWER_UP_SHADE);
+        }
+        for side in [Side::Left, Side::Right].iter() {
+            let half_size = Vec2::new(config.paddle_width / 2.0, sim.paddle_height(*side) / 2.0);
+            self.fill(sim, sim.paddle(*side).position, half_size, PADDLE_SHADE);
+        }
+        for ball in sim.balls() {
+            let half_size = Vec2::new(config.ball_radius, config.ball_radius);
+            self.fill(sim, ball.position, half_size, BALL_SHADE);
+        }
+    }
+
+    /// A rectangle of the arena, at least one pixel so that a small ball
+    /// does not vanish.
+    fn fill(&mut self, sim: &PongSim, center: Vec2, half_size: Vec2, shade: u8) {
+        let config = &sim.config;
+        if self.width == 0 || self.height == 0 || config.arena_width <= 0.0 || config.arena_height <= 0.0 {
+            return;
+        }
+        let scale_x = self.width as f32 / config.arena_width;
+        let scale_y = self.height as f32 / config.arena_height;
+        let to_pixel_x = |x: f32| (x + config.arena_width / 2.0) * scale_x;
+        let to_pixel_y = |y: f32| (config.arena_height / 2.0 - y) * scale_y;
+
+        let clamp_x = |x: f32| (x.max(0.0) as usize).min(self.width - 1);
+        let clamp_y = |y: f32| (y.max(0.0) as usize).min(self.height - 1);
+        let left = to_pixel_x(center.x - half_size.x);
+        let top = to_pixel_y(center.y + half_size.y);
+        if left >= self.width as f32 || top >= self.height as f32 {
+            return;
+        }
+        let right = to_pixel_x(center.x + half_size.x);
+        let bottom = to_pixel_y(center.y - half_size.y);
+        if right < 0.0 || bottom < 0.0 {
+            return;
+        }
+
+        let (left, right) = (clamp_x(left), clamp_x(right.ceil() - 1.0).max(clamp_x(left)));
+        let (top, bottom) = (clamp_y(top), clamp_y(bottom.ceil() - 1.0).max(clamp_y(top)));
+        for y in top..=bottom {
+            let row = y * self.width;
+            for pixel in self.data[row + left..=row + right].iter_mut() {
+                *pixel = shade;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PongConfig;
+
+    #[test]
+    fn paddles_and_ball_are_drawn_where_they_are() {
+        let mut sim = PongSim::new(PongConfig::default());
+        sim.ball.position = Vec2::new(200.0, 150.0);
+        let mut pixels = Pixels::new(80, 60);
+        pixels.render(&sim);
+
+        // the arena is 800 by 600, ten units per pixel
+        assert_eq!(pixels.get(60, 15), BALL_SHADE);
+        assert_eq!(pixels.get(0, 30), PADDLE_SHADE);
+        assert_eq!(pixels.get(79, 30), PADDLE_SHADE);
+        assert_eq!(pixels.get(40, 30), 0);
+        assert_eq!(pixels.get(0, 10), 0);
+
+        // a ball of 2 by 2 pixels and two paddles of 2 by 8, no other pixel set
+        let lit = pixels.data.iter().filter(|pixel| **pixel != 0).count();
+        assert_eq!(lit, 4 + 2 * 2 * 8);
+    }
+
+    #[test]
+    fn a_tiny_ball_still_shows() {
+        let mut sim = PongSim::new(PongConfig::default());
+        sim.ball.position = Vec2::new(0.0, 0.0);
+        let mut pixels = Pixels::new(8, 6);
+        pixels.render(&sim);
+        assert_eq!(pixels.get(4, 3), BALL_SHADE);
+    }
+}
@@ -0,0 +1,189 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::PongConfig;
+use crate::sim::{Ball, Inputs, PongSim, Score};
+
+/// Consecutive steps with the same inputs, keyboards rarely change.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct InputRun {
+    pub steps: u32,
+    pub left: f32,
+    pub right: f32
+}
+
+/// Everything needed to play a match again: the simulation is deterministic,
+/// so its config, the seed of the power-ups and of the computer players and
+/// the inputs of every step give back the same scores and ball trajectory.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Replay {
+    pub config: PongConfig,
+    pub seed: u64,
+    /// Duration of a step in seconds.
+    pub step: f32,
+    pub inputs: Vec<InputRun>,
+    /// State at the end of the recording, to check the replay.
+    pub score: Score,
+    pub ball: Ball
+}
+
+impl Replay {
+    /// An empty recording of a match starting now.
+    pub fn new(config: PongConfig, seed: u64, step: f32) -> Replay {
+        let sim = PongSim::with_seed(config.clone(), seed);
+        Replay { config, seed, step, inputs: Vec::new(), score: sim.score, ball: sim.ball }
+    }
+
+    /// Inputs of the next step, call it with the ones given to `PongSim::step`.
+    pub fn record(&mut self, inputs: Inputs) {
+        match self.inputs.last_mut() {
+            Some(run) if run.left == inputs.left && run.right == inputs.right => run.steps += 1,
+            _ => self.inputs.push(InputRun { steps: 1, left: inputs.left, right: inputs.right })
+        }
+    }
+
+    /// Remember where the recorded match is, before saving it.
+    pub fn finish(&mut self, sim: &PongSim) {
+        self.score = sim.score.clone();
+        self.ball = sim.ball.clone();
+    }
+
+    pub fn steps(&self) -> u32 {
+        self.inputs.iter().map(|run| run.steps).sum()
+    }
+
+    /// The inputs of every step, in order.
+    pub fn iter(&self) -> ReplayInputs {
+        ReplayInputs { runs: self.inputs.clone(), run: 0, step: 0 }
+    }
+
+    /// Play the whole replay without rendering.
+    pub fn play(&self) -> PongSim {
+        let mut sim = PongSim::with_seed(self.config.clone(), self.seed);
+        for inputs in self.iter() {
+            sim.step(self.step, inputs);
+        }
+        sim
+    }
+
+    /// Play the replay and check that it ends as recorded.
+    pub fn verify(&self) -> Result<PongSim, String> {
+        let sim = self.play();
+        if sim.score != self.score || sim.ball != self.ball {
+            return Err(format!(
+                "The replay ended with {:?} and {:?}, it was recorded with {:?} and {:?}",
+                sim.score, sim.ball, self.score, self.ball
+            ));
+        }
+        Ok(sim)
+    }
+
+    pub fn from_ron(text: &str) -> Result<Replay, String> {
+        ron::de::from_str(text).map_err(|error| format!("Invalid Pong replay: {}", error))
+    }
+
+    /// One input run per line.
+    pub fn to_ron(&self) -> Result<String, String> {
+        let pretty = ron::ser::PrettyConfig::default().with_depth_limit(2);
+        ron::ser::to_string_pretty(self, pretty)
+            .map_err(|error| format!("Can not write the Pong replay: {}", error))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Replay, String> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|error| format!("Can not read {}: {}", path.display(), error))?;
+
+        Replay::from_ron(&text).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        fs::write(path, self.to_ron()?).map_err(|error| format!("Can not write {}: {}", path.display(), error))
+    }
+}
+
+/// Inputs of a replay step by step, to feed a running game.
+#[derive(Debug, Clone)]
+pub struct ReplayInputs {
+    runs: Vec<InputRun>,
+    run: usize,
+    /// Steps already given from the current run.
+    step: u32
+}
+
+impl Iterator for ReplayInputs {
+    type Item = Inputs;
+
+    fn next(&mut self) -> Option<Inputs> {
+        let run = self.runs.get(self.run)?;
+        let inputs = Inputs { left: run.left, right: run.right };
+        self.step += 1;
+        if self.step >= run.steps {
+            self.run += 1;
+            self.step = 0;
+        }
+        Some(inputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::{AiController, Difficulty};
+    use crate::sim::Side;
+
+    const DT: f32 = 1.0 / 120.0;
+
+    /// A short match between two computer players.
+    fn record(seed: u64) -> Replay {
+        let config = PongConfig { ball_acceleration: 1.1, points_to_win: 3, win_by: 1, ..PongConfig::default() };
+        let mut sim = PongSim::with_seed(config.clone(), seed);
+        let mut replay = Replay::new(config, seed, DT);
+        let mut left = AiController::new(Side::Left, Difficulty::EASY, seed);
+        let mut right = AiController::new(Side::Right, Difficulty::HARD, seed + 1);
+
+        while sim.winner().is_none() {
+            let inputs = Inputs { left: left.update(&sim, DT), right: right.update(&sim, DT) };
+            replay.record(inputs);
+            sim.step(DT, inputs);
+        }
+        replay.finish(&sim);
+        replay
+    }
+
+    #[test]
+    fn inputs_are_grouped_in_runs() {
+        let mut replay = Replay::new(PongConfig::default(), 0, DT);
+        let up = Inputs { left: 1.0, right: 0.0 };
+        for inputs in [up, up, up, Inputs::default(), up].iter() {
+            replay.record(*inputs);
+        }
+
+        assert_eq!(replay.inputs.len(), 3);
+        assert_eq!(replay.steps(), 5);
+        assert_eq!(replay.iter().collect::<Vec<_>>(), vec![up, up, up, Inputs::default(), up]);
+    }
+
+    #[test]
+    fn replay_reproduces_the_match() {
+        let replay = record(3);
+        assert!(replay.score.left == 3 || replay.score.right == 3);
+
+        let text = replay.to_ron().unwrap();
+        let loaded = Replay::from_ron(&text).unwrap();
+        assert_eq!(loaded, replay);
+        let winner = if replay.score.left == 3 { Side::Left } else { Side::Right };
+        assert_eq!(loaded.verify().unwrap().winner(), Some(winner));
+    }
+
+    #[test]
+    fn tampered_replay_is_detected() {
+        let mut replay = record(4);
+        let run = replay.inputs.iter_mut().find(|run| run.left != 0.0).unwrap();
+        run.left = -run.left;
+        assert!(replay.verify().is_err());
+    }
+}
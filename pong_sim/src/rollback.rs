@@ -0,0 +1,445 @@
+use std::collections::VecDeque;
+use std::convert::TryInto;
+
+use crate::config::PongConfig;
+use crate::sim::{Event, Inputs, PongSim, Side};
+use crate::timestep::FixedTimestep;
+
+/// A paddle axis as sent over the network. Both peers simulate the quantized
+/// value, a float that went through the wire could differ from the local one.
+pub type NetInput = i8;
+
+pub fn quantize(axis: f32) -> NetInput {
+    (axis.clamp(-1.0, 1.0) * 127.0).round() as NetInput
+}
+
+pub fn dequantize(input: NetInput) -> f32 {
+    (input as f32 / 127.0).max(-1.0)
+}
+
+/// Inputs sent in one message at most, older unacknowledged ones go first.
+const MAX_INPUTS_PER_MESSAGE: usize = 64;
+
+/// Frames of advantage over the remote peer tolerated before waiting for it.
+const TIME_SYNC_TOLERANCE: i32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RollbackSettings {
+    /// Frames between reading a local input and simulating it, it hides part
+    /// of the latency and makes rollbacks shorter.
+    pub input_delay: u32,
+    /// Frames simulated past the last remote input before waiting for it.
+    pub max_prediction: u32,
+    /// Duration of a frame in seconds, the same on both peers.
+    pub step: f32
+}
+
+impl Default for RollbackSettings {
+    fn default() -> RollbackSettings {
+        RollbackSettings { input_delay: 2, max_prediction: 8, step: 1.0 / FixedTimestep::DEFAULT_RATE }
+    }
+}
+
+/// Sent to the remote peer every frame. Inputs are sent again until they are
+/// acknowledged, so losing a message costs nothing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    /// Frame the sender is about to simulate.
+    pub frame: u32,
+    /// How many frames the sender thinks it is ahead of us.
+    pub advantage: i32,
+    /// Number of our inputs the sender has, they are not sent again.
+    pub ack: u32,
+    /// Frame of the first input.
+    pub start: u32,
+    pub inputs: Vec<NetInput>
+}
+
+impl Message {
+    const HEADER: usize = 17;
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Message::HEADER + self.inputs.len());
+        bytes.extend_from_slice(&self.frame.to_le_bytes());
+        bytes.extend_from_slice(&self.advantage.to_le_bytes());
+        bytes.extend_from_slice(&self.ack.to_le_bytes());
+        bytes.extend_from_slice(&self.start.to_le_bytes());
+        bytes.push(self.inputs.len() as u8);
+        bytes.extend(self.inputs.iter().map(|&input| input as u8));
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Message, String> {
+        if bytes.len() < Message::HEADER || bytes.len() != Message::HEADER + bytes[16] as usize {
+            return Err(format!("Invalid message of {} bytes", bytes.len()));
+        }
+        let word = |at: usize| -> [u8; 4] { bytes[at..at + 4].try_into().unwrap() };
+
+        Ok(Message {
+            frame: u32::from_le_bytes(word(0)),
+            advantage: i32::from_le_bytes(word(4)),
+            ack: u32::from_le_bytes(word(8)),
+            start: u32::from_le_bytes(word(12)),
+            inputs: bytes[Message::HEADER..].iter().map(|&byte| byte as NetInput).collect()
+        })
+    }
+}
+
+/// One peer of a two players match with GGPO style rollback.
+///
+/// The local input is delayed by a few frames and the remote one is predicted
+/// to be the last one received. When a remote input arrives and differs from
+/// the prediction, the simulation goes back to the saved state of that frame
+/// and simulates again up to the current frame. The transport is left to the
+/// caller: send [`RollbackSession::message`] and feed what comes back to
+/// [`RollbackSession::receive`].
+#[derive(Debug, Clone)]
+pub struct RollbackSession {
+    local: Side,
+    settings: RollbackSettings,
+    sim: PongSim,
+    /// Next frame to simulate.
+    frame: u32,
+    /// States at the start of the frames `states_start..frame`.
+    states: VecDeque<PongSim>,
+    states_start: u32,
+    /// Local inputs from the frame `local_start`, they are kept until the
+    /// remote peer has them. The first `input_delay` ones are neutral.
+    local_inputs: VecDeque<NetInput>,
+    local_start: u32,
+    /// Confirmed remote inputs of the frames `states_start..confirmed`.
+    remote_inputs: VecDeque<NetInput>,
+    confirmed: u32,
+    /// The last confirmed remote input, the prediction of the next ones.
+    remote_last: NetInput,
+    /// Remote input used to simulate the frames `states_start..frame`,
+    /// predicted or confirmed.
+    remote_used: VecDeque<NetInput>,
+    /// First frame simulated with a wrong prediction.
+    rollback_from: Option<u32>,
+    /// Last frame the remote peer told about, `None` until it is heard of.
+    remote_frame: Option<u32>,
+    remote_advantage: i32,
+    remote_ack: u32,
+    resimulated: u64
+}
+
+impl RollbackSession {
+    pub fn new(config: PongConfig, local: Side, settings: RollbackSettings) -> RollbackSession {
+        RollbackSession {
+            local,
+            settings,
+            sim: PongSim::new(config),
+            frame: 0,
+            states: VecDeque::new(),
+            states_start: 0,
+            local_inputs: vec![0; settings.input_delay as usize].into(),
+            local_start: 0,
+            remote_inputs: VecDeque::new(),
+            confirmed: 0,
+            remote_last: 0,
+            remote_used: VecDeque::new(),
+            rollback_from: None,
+            remote_frame: None,
+            remote_advantage: 0,
+            remote_ack: 0,
+            resimulated: 0
+        }
+    }
+
+    pub fn local(&self) -> Side {
+        self.local
+    }
+
+    pub fn settings(&self) -> RollbackSettings {
+        self.settings
+    }
+
+    /// The simulation at the current frame, possibly built on predictions.
+    pub fn sim(&self) -> &PongSim {
+        &self.sim
+    }
+
+    /// The simulation at the last confirmed frame, no rollback can change it.
+    pub fn confirmed_sim(&mut self) -> &PongSim {
+        self.rollback();
+        let confirmed = self.confirmed_frame();
+        if confirmed >= self.frame {
+            return &self.sim;
+        }
+        &self.states[(confirmed - self.states_start) as usize]
+    }
+
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Frames before this one have both inputs known and will not change.
+    pub fn confirmed_frame(&self) -> u32 {
+        self.confirmed
+    }
+
+    /// Number of local inputs the remote peer has.
+    pub fn remote_ack(&self) -> u32 {
+        self.remote_ack
+    }
+
+    /// Frames simulated again since the start, to monitor the rollbacks.
+    pub fn resimulated(&self) -> u64 {
+        self.resimulated
+    }
+
+    /// How many frames we are ahead of the remote peer, as far as we know.
+    pub fn frame_advantage(&self) -> i32 {
+        match self.remote_frame {
+            Some(remote_frame) => self.frame as i32 - remote_frame as i32,
+            None => 0
+        }
+    }
+
+    /// Simulate the next frame with the local paddle axis. Nothing happens,
+    /// and the input is not used, when the remote peer is too far behind.
+    /// The events are the ones of the new frame, resimulated frames are silent.
+    pub fn advance(&mut self, local_axis: f32) -> Option<Vec<Event>> {
+        self.rollback();
+        if self.frame >= self.confirmed_frame() + self.settings.max_prediction || self.should_wait() {
+            return None;
+        }
+
+        self.local_inputs.push_back(quantize(local_axis));
+        self.states.push_back(self.sim.clone());
+        let events = self.step_frame();
+        self.frame += 1;
+        self.trim();
+
+        Some(events)
+    }
+
+    /// The message to send to the remote peer, every frame even when waiting.
+    pub fn message(&self) -> Message {
+        let start = (self.remote_ack.max(self.local_start) - self.local_start) as usize;
+        let start = start.min(self.local_inputs.len());
+        let end = self.local_inputs.len().min(start + MAX_INPUTS_PER_MESSAGE);
+
+        Message {
+            frame: self.frame,
+            advantage: self.frame_advantage(),
+            ack: self.confirmed_frame(),
+            start: self.local_start + start as u32,
+            inputs: self.local_inputs.range(start..end).copied().collect()
+        }
+    }
+
+    /// Take the remote inputs and acknowledgement of a message, in any order
+    /// and even duplicated. Mispredictions are corrected on the next advance.
+    pub fn receive(&mut self, message: &Message) {
+        self.remote_ack = self.remote_ack.max(message.ack);
+        let newer = match self.remote_frame {
+            Some(frame) => message.frame >= frame,
+            None => true
+        };
+        if newer {
+            self.remote_frame = Some(message.frame);
+            self.remote_advantage = message.advantage;
+        }
+
+        for (frame, &input) in (message.start..).zip(message.inputs.iter()) {
+            if frame != self.confirmed_frame() {
+                continue;
+            }
+            if frame < self.frame && self.remote_used[(frame - self.states_start) as usize] != input {
+                self.rollback_from.get_or_insert(frame);
+            }
+            self.remote_inputs.push_back(input);
+            self.remote_last = input;
+            self.confirmed += 1;
+        }
+    }
+
+    /// Go back to the first mispredicted frame and simulate again up to the
+    /// current one, returns the number of frames simulated again.
+    pub fn rollback(&mut self) -> u32 {
+        let start = match self.rollback_from.take() {
+            Some(start) => start,
+            None => return 0
+        };
+        let end = self.frame;
+
+        let index = (start - self.states_start) as usize;
+        self.sim = self.states[index].clone();
+        self.states.truncate(index);
+        for frame in start..end {
+            self.frame = frame;
+            self.states.push_back(self.sim.clone());
+            self.step_frame();
+        }
+        self.frame = end;
+        self.trim();
+
+        self.resimulated += (end - start) as u64;
+        end - start
+    }
+
+    /// Wait a frame when we are further ahead of the remote peer than it is of
+    /// us, the latency counts the same on both sides.
+    fn should_wait(&self) -> bool {
+        (self.frame_advantage() - self.remote_advantage) / 2 > TIME_SYNC_TOLERANCE
+    }
+
+    fn step_frame(&mut self) -> Vec<Event> {
+        let index = (self.frame - self.states_start) as usize;
+        let remote = match self.remote_inputs.get(index) {
+            Some(&input) => input,
+            None => self.remote_last
+        };
+        self.remote_used.truncate(index);
+        self.remote_used.push_back(remote);
+
+        let mut inputs = Inputs::default();
+        inputs.set(self.local, dequantize(self.local_inputs[(self.frame - self.local_start) as usize]));
+        inputs.set(self.local.opponent(), dequantize(remote));
+        self.sim.step(self.settings.step, inputs)
+    }
+
+    /// Forget the states and inputs that can not be rolled back to anymore,
+    /// and the local inputs the remote peer has.
+    fn trim(&mut self) {
+        let keep_from = self.confirmed_frame().min(self.frame);
+        while self.states_start < keep_from {
+            self.states.pop_front();
+            self.remote_inputs.pop_front();
+            self.remote_used.pop_front();
+            self.states_start += 1;
+        }
+
+        let keep_from = self.remote_ack.min(self.states_start);
+        while self.local_start < keep_from {
+            self.local_inputs.pop_front();
+            self.local_start += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+
+    /// Inputs changing every quarter of a second, the same for any peer.
+    fn scripted(side: Side, frame: u32) -> f32 {
+        let seed = (frame / 30) as u64 * 2 + side as u64;
+        [-1.0, 0.0, 1.0][(Rng::new(seed).next_u64() % 3) as usize]
+    }
+
+    fn reference(frames: u32) -> PongSim {
+        let settings = RollbackSettings::default();
+        let mut sim = PongSim::new(PongConfig::default());
+        for frame in 0..frames {
+            let delayed = |side| match frame.checked_sub(settings.input_delay) {
+                Some(frame) => scripted(side, frame),
+                None => 0.0
+            };
+            sim.step(settings.step, Inputs { left: delayed(Side::Left), right: delayed(Side::Right) });
+        }
+        sim
+    }
+
+    fn session(side: Side) -> RollbackSession {
+        RollbackSession::new(PongConfig::default(), side, RollbackSettings::default())
+    }
+
+    #[test]
+    fn message_round_trip() {
+        let message = Message { frame: 1234, advantage: -3, ack: 1200, start: 1190, inputs: vec![-127, 0, 127, 5] };
+        assert_eq!(Message::decode(&message.encode()), Ok(message));
+
+        assert!(Message::decode(&[1, 2, 3]).is_err());
+        let mut truncated = Message { frame: 0, advantage: 0, ack: 0, start: 0, inputs: vec![1, 2] }.encode();
+        truncated.pop();
+        assert!(Message::decode(&truncated).is_err());
+    }
+
+    #[test]
+    fn quantized_axis() {
+        assert_eq!(dequantize(quantize(1.0)), 1.0);
+        assert_eq!(dequantize(quantize(-1.0)), -1.0);
+        assert_eq!(dequantize(quantize(0.0)), 0.0);
+        assert_eq!(quantize(5.0), 127);
+        assert_eq!(dequantize(-128), -1.0);
+    }
+
+    #[test]
+    fn waits_for_the_remote_inputs() {
+        let mut left = session(Side::Left);
+        let max_prediction = left.settings().max_prediction;
+        for _ in 0..max_prediction {
+            assert!(left.advance(0.0).is_some());
+        }
+        assert_eq!(left.advance(0.0), None);
+        assert_eq!(left.frame(), max_prediction);
+    }
+
+    #[test]
+    fn misprediction_is_rolled_back() {
+        let mut left = session(Side::Left);
+        for _ in 0..6 {
+            left.advance(0.0).unwrap();
+        }
+        // left predicted a still right paddle, and now learns it moved
+        let inputs = vec![0, 0, 127, 127, 127, 127];
+        left.receive(&Message { frame: 4, advantage: -2, ack: 0, start: 0, inputs });
+        assert_eq!(left.rollback(), 4);
+        assert_eq!(left.confirmed_frame(), 6);
+        assert_eq!(left.frame(), 6);
+
+        let mut expected = PongSim::new(PongConfig::default());
+        for frame in 0..6 {
+            let right = if frame >= 2 { 1.0 } else { 0.0 };
+            expected.step(left.settings().step, Inputs { left: 0.0, right });
+        }
+        assert_eq!(left.sim(), &expected);
+        assert_eq!(left.confirmed_sim(), &expected);
+    }
+
+    #[test]
+    fn peers_agree_over_a_lossy_link() {
+        const FRAMES: u32 = 3000;
+        // messages in flight for a few frames, some lost and some reordered
+        let mut rng = Rng::new(7);
+        let mut in_flight: Vec<(u32, Side, Message)> = Vec::new();
+        let mut peers = [session(Side::Left), session(Side::Right)];
+
+        let mut tick = 0;
+        while peers.iter().any(|peer| peer.frame() < FRAMES || peer.confirmed_frame() < FRAMES) {
+            for peer in peers.iter_mut() {
+                let side = peer.local();
+                if peer.frame() < FRAMES {
+                    peer.advance(scripted(side, peer.frame()));
+                }
+                else {
+                    peer.rollback();
+                }
+                if rng.next_f32() > 0.25 {
+                    let arrival = tick + 2 + (rng.next_u64() % 6) as u32;
+                    in_flight.push((arrival, side.opponent(), peer.message()));
+                }
+            }
+            for (_, side, message) in in_flight.iter().filter(|(arrival, ..)| *arrival == tick) {
+                peers[*side as usize].receive(message);
+            }
+            in_flight.retain(|(arrival, ..)| *arrival > tick);
+            tick += 1;
+
+            // only the frames that can still be rolled back or resent are kept
+            for peer in peers.iter() {
+                let lengths = [peer.states.len(), peer.local_inputs.len(), peer.remote_inputs.len(), peer.remote_used.len()];
+                assert!(lengths.iter().all(|&length| length <= 2 * MAX_INPUTS_PER_MESSAGE), "{:?}", lengths);
+            }
+            assert!(tick < 10 * FRAMES, "the peers never caught up");
+        }
+
+        assert!(peers[0].resimulated() > 0);
+        assert_eq!(peers[0].sim(), peers[1].sim());
+        assert_eq!(peers[0].sim(), &reference(FRAMES));
+    }
+}
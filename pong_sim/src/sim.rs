@@ -0,0 +1,1110 @@
+use serde::{Deserialize, Serialize};
+
+use crate::bounce::{self, Contact, PaddleBody, WallBody};
+use crate::config::{PongConfig, ServeRule};
+use crate::effects::Rgba;
+use crate::math::Vec2;
+use crate::rally::{self, Serve, ServeStep};
+use crate::rng::Rng;
+
+/// Balls in play at most, with the extra balls of a multi-ball.
+const MAX_BALLS: usize = 6;
+/// Power-ups waiting in the arena at most.
+const MAX_POWER_UPS: usize = 2;
+/// Angle between the balls of a multi-ball, in radians.
+const MULTI_BALL_SPREAD: f32 = 0.35;
+const GROW_FACTOR: f32 = 1.5;
+const SHRINK_FACTOR: f32 = 0.6;
+/// Speed of the balls during a slow-mo.
+const SLOW_MO_FACTOR: f32 = 0.5;
+/// Turn of a curve ball, in radians per second.
+const CURVE_RATE: f32 = 1.2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum Side {
+    Left,
+    Right
+}
+
+impl Side {
+    pub fn opponent(self) -> Side {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left
+        }
+    }
+
+    /// Index in [`PongSim::paddles`] and [`Frame::paddles`].
+    pub fn index(self) -> usize {
+        match self {
+            Side::Left => 0,
+            Side::Right => 1
+        }
+    }
+
+    /// -1 on the left, 1 on the right.
+    pub fn sign(self) -> f32 {
+        match self {
+            Side::Left => -1.0,
+            Side::Right => 1.0
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Ball {
+    pub position: Vec2,
+    pub velocity: Vec2
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Paddle {
+    pub side: Side,
+    /// Center of the paddle.
+    pub position: Vec2,
+    /// Vertical speed during the last step, for the spin it gives to the ball.
+    pub velocity: f32
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Score {
+    pub left: u32,
+    pub right: u32
+}
+
+impl Score {
+    pub fn get(&self, side: Side) -> u32 {
+        match side {
+            Side::Left => self.left,
+            Side::Right => self.right
+        }
+    }
+}
+
+/// Paddle axes for one step, between -1 (down) and 1 (up).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Inputs {
+    pub left: f32,
+    pub right: f32
+}
+
+impl Inputs {
+    pub fn get(&self, side: Side) -> f32 {
+        match side {
+            Side::Left => self.left,
+            Side::Right => self.right
+        }
+    }
+
+    pub fn set(&mut self, side: Side, axis: f32) {
+        match side {
+            Side::Left => self.left = axis,
+            Side::Right => self.right = axis
+        }
+    }
+}
+
+/// Something to catch with the ball when [`PongConfig::power_ups`] is set. The
+/// player who sent the ball gets it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PowerUpKind {
+    /// Two more balls, until the power-up wears off.
+    MultiBall,
+    /// A longer paddle.
+    Grow,
+    /// A shorter paddle for the opponent.
+    Shrink,
+    /// Every ball at half speed.
+    SlowMo,
+    /// The balls sent by this player bend along their way.
+    Curve
+}
+
+impl PowerUpKind {
+    pub const ALL: [PowerUpKind; 5] = [
+        PowerUpKind::MultiBall,
+        PowerUpKind::Grow,
+        PowerUpKind::Shrink,
+        PowerUpKind::SlowMo,
+        PowerUpKind::Curve
+    ];
+
+    /// Short label for the score boards of the front-ends.
+    pub fn name(self) -> &'static str {
+        match self {
+            PowerUpKind::MultiBall => "MULTI",
+            PowerUpKind::Grow => "GROW",
+            PowerUpKind::Shrink => "SHRINK",
+            PowerUpKind::SlowMo => "SLOW",
+            PowerUpKind::Curve => "CURVE"
+        }
+    }
+
+    /// Color the front-ends draw it in, and tint the paddle of the player who
+    /// caught it with.
+    pub fn color(self) -> Rgba {
+        match self {
+            PowerUpKind::MultiBall => [1.0, 0.84, 0.0, 1.0],
+            PowerUpKind::Grow => [0.0, 1.0, 0.0, 1.0],
+            PowerUpKind::Shrink => [1.0, 0.0, 0.0, 1.0],
+            PowerUpKind::SlowMo => [0.0, 1.0, 1.0, 1.0],
+            PowerUpKind::Curve => [1.0, 0.75, 0.8, 1.0]
+        }
+    }
+}
+
+/// A power-up waiting in the arena.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PowerUp {
+    pub kind: PowerUpKind,
+    pub position: Vec2
+}
+
+/// A power-up caught by a player, until it wears off.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Effect {
+    pub kind: PowerUpKind,
+    /// The player who caught it.
+    pub side: Side,
+    /// Seconds left.
+    pub remaining: f32
+}
+
+/// What happened during a step, for the front-ends to play sounds, update the
+/// score board, ...
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// The ball left the center.
+    Serve,
+    /// The ball bounced on the top or bottom wall.
+    WallBounce,
+    /// The ball bounced on the paddle of this side.
+    PaddleHit(Side),
+    /// The ball left the arena, this side scores.
+    Goal(Side),
+    /// This side won the match, nothing moves until [`PongSim::restart`].
+    MatchOver(Side),
+    /// A ball sent by this side caught a power-up.
+    PowerUp(PowerUpKind, Side),
+    /// The power-up caught by this side wore off.
+    PowerUpOver(PowerUpKind, Side)
+}
+
+/// Positions to draw, between two steps of the simulation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub ball: Vec2,
+    /// The balls of a multi-ball, in the order of [`PongSim::extra_balls`].
+    pub extra_balls: Vec<Vec2>,
+    /// Left paddle first.
+    pub paddles: [Vec2; 2]
+}
+
+impl Frame {
+    pub fn paddle(&self, side: Side) -> Vec2 {
+        match side {
+            Side::Left => self.paddles[0],
+            Side::Right => self.paddles[1]
+        }
+    }
+}
+
+/// The whole state of a Pong match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PongSim {
+    pub config: PongConfig,
+    /// The ball served, a multi-ball adds the others. When it leaves the
+    /// arena an extra ball takes its place.
+    pub ball: Ball,
+    pub extra_balls: Vec<Ball>,
+    pub paddles: [Paddle; 2],
+    pub score: Score,
+    pub power_ups: Vec<PowerUp>,
+    pub effects: Vec<Effect>,
+    /// The ball is served away from this side.
+    server: Side,
+    serve: Serve,
+    winner: Option<Side>,
+    /// Seconds before the next power-up appears.
+    power_up_timer: f32,
+    /// Where and which power-ups appear.
+    rng: Rng
+}
+
+impl PongSim {
+    pub fn new(config: PongConfig) -> PongSim {
+        PongSim::with_seed(config, 0)
+    }
+
+    /// A match whose power-ups come from `seed`, the same seed gives the
+    /// same match with the same inputs.
+    pub fn with_seed(config: PongConfig, seed: u64) -> PongSim {
+        let paddle_x = (config.arena_width - config.paddle_width) / 2.0;
+        let mut ball = Ball { position: Vec2::ZERO, velocity: Vec2::ZERO };
+        let mut sim = PongSim {
+            serve: Serve::stop(&mut ball),
+            ball,
+            extra_balls: Vec::new(),
+            paddles: [
+                Paddle { side: Side::Left, position: Vec2::new(-paddle_x, 0.0), velocity: 0.0 },
+                Paddle { side: Side::Right, position: Vec2::new(paddle_x, 0.0), velocity: 0.0 }
+            ],
+            score: Score::default(),
+            power_ups: Vec::new(),
+            effects: Vec::new(),
+            server: Side::Left,
+            winner: None,
+            power_up_timer: config.power_up_interval,
+            rng: Rng::new(seed),
+            config
+        };
+        sim.reset_ball();
+
+        sim
+    }
+
+    /// Change the tuning in the middle of a match, the score and the ball
+    /// are kept, the paddles move to their new place.
+    pub fn set_config(&mut self, config: PongConfig) {
+        let paddle_x = (config.arena_width - config.paddle_width) / 2.0;
+        self.config = config;
+        for side in [Side::Left, Side::Right].iter() {
+            let limit = self.paddle_limit(*side);
+            let paddle = &mut self.paddles[side.index()];
+            paddle.position.x = side.sign() * paddle_x;
+            paddle.position.y = paddle.position.y.clamp(-limit, limit);
+        }
+    }
+
+    /// Start a new match with the same config, and new power-ups.
+    pub fn restart(&mut self) {
+        let seed = self.rng.next_u64();
+        *self = PongSim::with_seed(self.config.clone(), seed);
+    }
+
+    /// The side that won the match, once it is over.
+    pub fn winner(&self) -> Option<Side> {
+        self.winner
+    }
+
+    /// The side the next ball is served away from.
+    pub fn server(&self) -> Side {
+        self.server
+    }
+
+    pub fn paddle(&self, side: Side) -> &Paddle {
+        &self.paddles[side.index()]
+    }
+
+    /// Length of the paddle of a side, changed by the grow and shrink power-ups.
+    pub fn paddle_height(&self, side: Side) -> f32 {
+        let mut height = self.config.paddle_height;
+        if self.has_effect(PowerUpKind::Grow, side) {
+            height *= GROW_FACTOR;
+        }
+        if self.has_effect(PowerUpKind::Shrink, side.opponent()) {
+            height *= SHRINK_FACTOR;
+        }
+        height
+    }
+
+    /// Whether `side` caught a power-up of this kind that did not wear off yet.
+    pub fn has_effect(&self, kind: PowerUpKind, side: Side) -> bool {
+        self.effects.iter().any(|effect| effect.kind == kind && effect.side == side)
+    }
+
+    /// The ball served and the extra balls.
+    pub fn balls(&self) -> impl Iterator<Item = &Ball> {
+        std::iter::once(&self.ball).chain(self.extra_balls.iter())
+    }
+
+    /// How far the center of a paddle goes from the middle.
+    fn paddle_limit(&self, side: Side) -> f32 {
+        ((self.config.arena_height - self.paddle_height(side)) / 2.0).max(0.0)
+    }
+
+    /// Blend `previous`, the state before the last step, with this one.
+    /// `alpha` comes from [`FixedTimestep::alpha`](crate::FixedTimestep::alpha).
+    pub fn frame(&self, previous: &PongSim, alpha: f32) -> Frame {
+        // the ball jumps back to the center after a goal, do not draw it
+        // crossing the whole arena
+        let scored = previous.score != self.score;
+        let ball = rally::blend(previous.ball.position, self.ball.position, alpha, scored);
+
+        // extra balls come and go, they are only blended when they are the same
+        let same_balls = previous.extra_balls.len() == self.extra_balls.len() && !scored;
+        let extra_balls = self.extra_balls.iter().enumerate()
+            .map(|(index, ball)| match previous.extra_balls.get(index) {
+                Some(previous) => rally::blend(previous.position, ball.position, alpha, !same_balls),
+                None => ball.position
+            })
+            .collect();
+
+        let paddle = |index: usize| rally::blend(previous.paddles[index].position, self.paddles[index].position, alpha, false);
+        Frame { ball, extra_balls, paddles: [paddle(0), paddle(1)] }
+    }
+
+    /// Whether the ball is waiting at the center to be served.
+    pub fn is_serving(&self) -> bool {
+        self.serve.countdown().is_some()
+    }
+
+    /// Seconds left before the serve, to show a countdown.
+    pub fn serve_countdown(&self) -> Option<f32> {
+        self.serve.countdown()
+    }
+
+    /// Advance the match by `dt` seconds.
+    pub fn step(&mut self, dt: f32, inputs: Inputs) -> Vec<Event> {
+        let mut events = Vec::new();
+        if self.winner.is_some() {
+            return events;
+        }
+
+        self.move_paddles(dt, inputs);
+        self.update_effects(dt, &mut events);
+
+        match self.serve.update(&mut self.ball, dt) {
+            ServeStep::Waiting => return events,
+            ServeStep::Served => events.push(Event::Serve),
+            ServeStep::InPlay => {}
+        }
+
+        self.spawn_power_ups(dt);
+        self.move_balls(dt, &mut events);
+        self.catch_power_ups(&mut events);
+
+        events
+    }
+
+    fn move_paddles(&mut self, dt: f32, inputs: Inputs) {
+        let limits = [self.paddle_limit(Side::Left), self.paddle_limit(Side::Right)];
+
+        for paddle in self.paddles.iter_mut() {
+            let limit = limits[paddle.side.index()];
+            let (y, velocity) = rally::slide(&self.config, paddle.position.y, inputs.get(paddle.side), dt, limit);
+            paddle.position.y = y;
+            paddle.velocity = velocity;
+        }
+    }
+
+    /// Count down the power-ups caught, the extra balls go with the last multi-ball.
+    fn update_effects(&mut self, dt: f32, events: &mut Vec<Event>) {
+        if self.effects.is_empty() {
+            return;
+        }
+        for effect in self.effects.iter_mut() {
+            effect.remaining -= dt;
+            if effect.remaining <= 0.0 {
+                events.push(Event::PowerUpOver(effect.kind, effect.side));
+            }
+        }
+        self.effects.retain(|effect| effect.remaining > 0.0);
+
+        if !self.effects.iter().any(|effect| effect.kind == PowerUpKind::MultiBall) {
+            self.extra_balls.clear();
+        }
+        // a paddle shrinking back may be past the end of the arena
+        for side in [Side::Left, Side::Right].iter() {
+            let limit = self.paddle_limit(*side);
+            let paddle = &mut self.paddles[side.index()];
+            paddle.position.y = paddle.position.y.clamp(-limit, limit);
+        }
+    }
+
+    fn spawn_power_ups(&mut self, dt: f32) {
+        if !self.config.power_ups {
+            return;
+        }
+        self.power_up_timer -= dt;
+        if self.power_up_timer > 0.0 {
+            return;
+        }
+        self.power_up_timer = self.config.power_up_interval;
+        if self.power_ups.len() >= MAX_POWER_UPS {
+            return;
+        }
+
+        // in the middle half of the arena, away from the paddles
+        let config = &self.config;
+        let x = config.arena_width / 4.0;
+        let y = (config.arena_height / 2.0 - config.power_up_radius * 2.0).max(0.0);
+        let position = Vec2::new(self.rng.range(-x, x), self.rng.range(-y, y));
+        let kind = PowerUpKind::ALL[(self.rng.next_u64() % PowerUpKind::ALL.len() as u64) as usize];
+        self.power_ups.push(PowerUp { kind, position });
+    }
+
+    /// Move every ball, each one leaving the arena is a point. The rally ends
+    /// with the last ball.
+    fn move_balls(&mut self, dt: f32, events: &mut Vec<Event>) {
+        let dt = if self.effects.iter().any(|effect| effect.kind == PowerUpKind::SlowMo) {
+            dt * SLOW_MO_FACTOR
+        }
+        else {
+            dt
+        };
+        let heights = [self.paddle_height(Side::Left), self.paddle_height(Side::Right)];
+        let curves = [
+            self.has_effect(PowerUpKind::Curve, Side::Left),
+            self.has_effect(PowerUpKind::Curve, Side::Right)
+        ];
+        let mover = BallMover { config: &self.config, paddles: &self.paddles, heights, curves };
+
+        let mut scorers = Vec::new();
+        let main_scorer = mover.move_ball(&mut self.ball, dt, events);
+        scorers.extend(main_scorer);
+        self.extra_balls.retain_mut(|ball| match mover.move_ball(ball, dt, events) {
+            Some(scorer) => {
+                scorers.push(scorer);
+                false
+            }
+            None => true
+        });
+
+        for scorer in scorers.iter() {
+            match scorer {
+                Side::Left => self.score.left += 1,
+                Side::Right => self.score.right += 1
+            }
+            events.push(Event::Goal(*scorer));
+
+            if self.has_won(*scorer) {
+                self.winner = Some(*scorer);
+                self.serve = Serve::stop(&mut self.ball);
+                self.extra_balls.clear();
+                events.push(Event::MatchOver(*scorer));
+                return;
+            }
+        }
+
+        if let Some(scorer) = main_scorer {
+            if self.extra_balls.is_empty() {
+                self.server = match self.config.serve {
+                    ServeRule::Conceder => scorer.opponent(),
+                    ServeRule::Alternate => self.server.opponent()
+                };
+                self.reset_ball();
+            }
+            else {
+                self.ball = self.extra_balls.remove(0);
+            }
+        }
+    }
+
+    /// The balls touching a power-up take it for the player who sent them.
+    fn catch_power_ups(&mut self, events: &mut Vec<Event>) {
+        if self.power_ups.is_empty() {
+            return;
+        }
+        let reach = self.config.power_up_radius + self.config.ball_radius;
+        let mut caught = Vec::new();
+        for ball in self.balls() {
+            let side = if ball.velocity.x > 0.0 { Side::Left } else { Side::Right };
+            for (index, power_up) in self.power_ups.iter().enumerate() {
+                let near = (ball.position - power_up.position).length_squared() < reach * reach;
+                if near && !caught.iter().any(|&(caught, _, _)| caught == index) {
+                    caught.push((index, side, ball.clone()));
+                }
+            }
+        }
+        caught.sort_by_key(|&(index, _, _)| std::cmp::Reverse(index));
+
+        for (index, side, ball) in caught {
+            let kind = self.power_ups.remove(index).kind;
+            events.push(Event::PowerUp(kind, side));
+
+            let duration = self.config.power_up_duration;
+            match self.effects.iter_mut().find(|effect| effect.kind == kind && effect.side == side) {
+                Some(effect) => effect.remaining = duration,
+                None => self.effects.push(Effect { kind, side, remaining: duration })
+            }
+            if kind == PowerUpKind::MultiBall {
+                for &angle in [MULTI_BALL_SPREAD, -MULTI_BALL_SPREAD].iter() {
+                    if self.extra_balls.len() + 1 < MAX_BALLS {
+                        let velocity = ball.velocity.rotated(angle);
+                        self.extra_balls.push(Ball { position: ball.position, velocity });
+                    }
+                }
+            }
+        }
+    }
+
+    /// First to `points_to_win`, with a lead of at least `win_by`.
+    fn has_won(&self, side: Side) -> bool {
+        let points = self.score.get(side);
+        let other = self.score.get(side.opponent());
+
+        self.config.points_to_win > 0
+            && points >= self.config.points_to_win
+            && points >= other + self.config.win_by
+    }
+
+    /// Put the ball back at the center, to be served away from the server.
+    fn reset_ball(&mut self) {
+        let velocity = self.config.ball_velocity;
+        let velocity = Vec2::new(velocity.x.abs() * self.server.opponent().sign(), velocity.y);
+        self.serve = Serve::new(&self.config, &mut self.ball, velocity);
+    }
+}
+
+/// Moves one ball against the paddles and the walls.
+struct BallMover<'a> {
+    config: &'a PongConfig,
+    paddles: &'a [Paddle; 2],
+    /// Length of each paddle, with the power-ups.
+    heights: [f32; 2],
+    /// The balls sent by these sides curve.
+    curves: [bool; 2]
+}
+
+impl BallMover<'_> {
+    /// Move the ball by `dt` seconds, the side scoring when it leaves the arena.
+    fn move_ball(&self, ball: &mut Ball, dt: f32, events: &mut Vec<Event>) -> Option<Side> {
+        let config = self.config;
+
+        // sent by the right paddle when it goes left
+        let sender = if ball.velocity.x < 0.0 { Side::Right } else { Side::Left };
+        if self.curves[sender.index()] {
+            curve(config, ball, sender, dt);
+        }
+
+        let paddles = [self.body(Side::Left), self.body(Side::Right)];
+        let walls = [
+            WallBody { normal: Vec2::new(0.0, -1.0), distance: config.arena_height / 2.0 },
+            WallBody { normal: Vec2::new(0.0, 1.0), distance: config.arena_height / 2.0 }
+        ];
+        bounce::move_ball(config, ball, dt, &paddles, &walls, |contact| match contact {
+            Contact::Paddle(index) => events.push(Event::PaddleHit(self.paddles[index].side)),
+            Contact::Wall => events.push(Event::WallBounce)
+        });
+
+        // ball leaving the arena through a goal, the other side scores
+        [Side::Left, Side::Right].iter()
+            .find(|side| self.goal(**side).is_crossed(config, ball))
+            .map(|side| side.opponent())
+    }
+
+    /// The goal behind the paddle of `side`.
+    fn goal(&self, side: Side) -> WallBody {
+        WallBody { normal: Vec2::new(-side.sign(), 0.0), distance: self.config.arena_width / 2.0 }
+    }
+
+    fn body(&self, side: Side) -> PaddleBody {
+        let paddle = &self.paddles[side.index()];
+        PaddleBody {
+            position: paddle.position,
+            normal: Vec2::new(-side.sign(), 0.0),
+            tangent: Vec2::new(0.0, 1.0),
+            velocity: paddle.velocity,
+            length: self.heights[side.index()]
+        }
+    }
+}
+
+/// Turn the velocity of a ball sent by `sender`, without going steeper than
+/// the bounces off a paddle end.
+fn curve(config: &PongConfig, ball: &mut Ball, sender: Side, dt: f32) {
+    let turned = ball.velocity.rotated(-sender.sign() * CURVE_RATE * dt);
+    let max_angle = config.max_bounce_angle.to_radians();
+    if turned.y.abs().atan2(turned.x.abs()) <= max_angle && turned.x * ball.velocity.x > 0.0 {
+        ball.velocity = turned;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    fn sim() -> PongSim {
+        PongSim::new(PongConfig::default())
+    }
+
+    #[test]
+    fn paddles_stay_in_the_arena() {
+        let mut sim = sim();
+        for _ in 0..1000 {
+            sim.step(DT, Inputs { left: 1.0, right: -1.0 });
+        }
+
+        let limit = (sim.config.arena_height - sim.config.paddle_height) / 2.0;
+        assert_eq!(sim.paddle(Side::Left).position.y, limit);
+        assert_eq!(sim.paddle(Side::Right).position.y, -limit);
+    }
+
+    #[test]
+    fn ball_bounces_on_walls() {
+        let mut sim = sim();
+        sim.ball.position = Vec2::new(0.0, 285.0);
+        sim.ball.velocity = Vec2::new(0.0, 600.0);
+
+        let events = sim.step(0.1, Inputs::default());
+        assert_eq!(events, vec![Event::WallBounce]);
+        assert_eq!(sim.ball.velocity, Vec2::new(0.0, -600.0));
+        assert!(sim.ball.position.y <= 290.0);
+    }
+
+    #[test]
+    fn ball_bounces_on_paddles() {
+        let mut sim = sim();
+        sim.ball.position = Vec2::new(-360.0, 0.0);
+        sim.ball.velocity = Vec2::new(-300.0, 0.0);
+
+        let events = sim.step(0.1, Inputs::default());
+        assert_eq!(events, vec![Event::PaddleHit(Side::Left)]);
+        let speed = 300.0 * sim.config.ball_acceleration;
+        assert_eq!(sim.ball.velocity, Vec2::new(speed, 0.0));
+        assert!(sim.ball.position.x >= -370.0);
+
+        // going away from the paddle, no second bounce
+        let events = sim.step(DT, Inputs::default());
+        assert!(events.is_empty());
+        assert_eq!(sim.ball.velocity, Vec2::new(speed, 0.0));
+    }
+
+    #[test]
+    fn very_fast_balls_do_not_go_through_paddles() {
+        // the ball starts 200 units away from the paddle and moves from 200 to
+        // 800 units in a single step, more than the width of a paddle
+        for &speed in [12_000.0, 24_000.0, 48_000.0].iter() {
+            for &side in [Side::Left, Side::Right].iter() {
+                for i in 0..8 {
+                    let mut sim = sim();
+                    let target = sim.paddle(side).position + Vec2::new(0.0, i as f32 * 10.0 - 35.0);
+                    let direction = target - Vec2::new(side.opponent().sign() * 300.0, 0.0);
+                    sim.ball.position = target - direction * (200.0 / direction.length());
+                    sim.ball.velocity = direction * (speed / direction.length());
+
+                    let events = sim.step(DT, Inputs::default());
+                    assert!(events.contains(&Event::PaddleHit(side)), "{} at {}: {:?}", speed, i, events);
+                    assert!(!events.iter().any(|event| matches!(event, Event::Goal(_))), "{} at {}: {:?}", speed, i, events);
+                    assert!(sim.ball.velocity.x * side.sign() < 0.0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fast_ball_keeps_the_rest_of_its_motion() {
+        let mut sim = sim();
+        sim.ball.position = Vec2::new(-300.0, 0.0);
+        sim.ball.velocity = Vec2::new(-6_000.0, 0.0);
+
+        // 100 units in the step: 70 to the paddle face at -370, 30 back
+        let events = sim.step(1.0 / 60.0, Inputs::default());
+        assert_eq!(events, vec![Event::PaddleHit(Side::Left)]);
+        assert_eq!(sim.ball.velocity, Vec2::new(6_000.0, 0.0));
+        assert!((sim.ball.position.x + 340.0).abs() < 1e-2, "{:?}", sim.ball.position);
+    }
+
+    #[test]
+    fn fast_ball_bounces_on_a_wall_then_a_paddle_in_one_step() {
+        let mut sim = sim();
+        sim.ball.position = Vec2::new(0.0, 200.0);
+        sim.ball.velocity = Vec2::new(-12_000.0, 12_000.0);
+
+        // up to the wall at y = 290, then down to the paddle face at x = -370
+        let events = sim.step(1.0 / 30.0, Inputs::default());
+        assert_eq!(events, vec![Event::WallBounce, Event::PaddleHit(Side::Left)]);
+        assert!(sim.ball.velocity.x > 0.0);
+        assert!(sim.ball.position.x > -370.0 && sim.ball.position.y < 290.0);
+    }
+
+    #[test]
+    fn ball_falling_on_top_of_a_paddle_bounces_up() {
+        let mut sim = sim();
+        let paddle = sim.paddle(Side::Left).position;
+        sim.ball.position = paddle + Vec2::new(0.0, 100.0);
+        sim.ball.velocity = Vec2::new(0.0, -6_000.0);
+
+        let events = sim.step(1.0 / 60.0, Inputs::default());
+        assert_eq!(events, vec![Event::PaddleHit(Side::Left)]);
+        assert_eq!(sim.ball.velocity, Vec2::new(0.0, 6_000.0));
+        assert!(sim.ball.position.y >= paddle.y + 50.0);
+    }
+
+    fn hit_left_paddle(sim: &mut PongSim, height: f32, inputs: Inputs) -> Vec2 {
+        hit_left_paddle_at(sim, height, inputs, 300.0)
+    }
+
+    fn hit_left_paddle_at(sim: &mut PongSim, height: f32, inputs: Inputs, speed: f32) -> Vec2 {
+        let paddle = sim.paddle(Side::Left).position;
+        sim.ball.position = Vec2::new(-365.0, paddle.y + height);
+        sim.ball.velocity = Vec2::new(-speed, 0.0);
+        let events = sim.step(0.02, inputs);
+        assert!(events.contains(&Event::PaddleHit(Side::Left)), "{:?}", events);
+        sim.ball.velocity
+    }
+
+    #[test]
+    fn bounce_angle_depends_on_where_the_ball_hits() {
+        let mut sim = PongSim::new(PongConfig { paddle_spin: 0.0, ..PongConfig::default() });
+        let speed = 300.0 * sim.config.ball_acceleration;
+
+        let center = hit_left_paddle(&mut sim, 0.0, Inputs::default());
+        assert_eq!(center, Vec2::new(speed, 0.0));
+
+        // near the end of the paddle, the round ball hits the corner, the
+        // end itself with the ball radius would give the maximum angle
+        let top = hit_left_paddle_at(&mut sim, 45.0, Inputs::default(), 500.0);
+        assert!((top.y.atan2(top.x).to_degrees() - 54.0).abs() < 1e-3, "{:?}", top);
+        assert!((top.length() - 500.0 * sim.config.ball_acceleration).abs() < 1e-3);
+
+        let bottom = hit_left_paddle(&mut sim, -25.0, Inputs::default());
+        assert!((bottom.y.atan2(bottom.x).to_degrees() + 30.0).abs() < 1e-3, "{:?}", bottom);
+    }
+
+    #[test]
+    fn moving_paddle_adds_spin() {
+        let mut sim = sim();
+        let still = hit_left_paddle(&mut sim, 0.0, Inputs::default());
+        let mut sim = PongSim::new(PongConfig::default());
+        let up = hit_left_paddle(&mut sim, 0.0, Inputs { left: 1.0, right: 0.0 });
+
+        assert_eq!(still.y, 0.0);
+        assert!(up.y > 0.0 && up.x > 0.0);
+        assert!((up.length() - 300.0 * sim.config.ball_acceleration).abs() < 1e-3);
+
+        // the spin can not push the ball past the maximum angle
+        let mut sim = PongSim::new(PongConfig { paddle_spin: 100.0, ..PongConfig::default() });
+        let up = hit_left_paddle(&mut sim, 0.0, Inputs { left: 1.0, right: 0.0 });
+        assert!((up.y.atan2(up.x).to_degrees() - 60.0).abs() < 1e-3, "{:?}", up);
+    }
+
+    #[test]
+    fn speed_rises_per_rally_up_to_the_cap() {
+        let mut sim = PongSim::new(PongConfig {
+            ball_acceleration: 1.5,
+            max_ball_speed: 500.0,
+            ..PongConfig::default()
+        });
+
+        assert_eq!(hit_left_paddle(&mut sim, 0.0, Inputs::default()).length(), 450.0);
+        assert_eq!(hit_left_paddle_at(&mut sim, 0.0, Inputs::default(), 450.0).length(), 500.0);
+        // already faster than the cap, kept as is
+        assert_eq!(hit_left_paddle_at(&mut sim, 0.0, Inputs::default(), 800.0).length(), 800.0);
+
+        // a goal starts a new rally at the serve speed
+        sim.ball.position = Vec2::new(405.0, 250.0);
+        sim.step(0.1, Inputs::default());
+        assert_eq!(sim.ball.velocity.length(), sim.config.ball_velocity.length());
+    }
+
+    #[test]
+    fn rallies_get_faster_by_default() {
+        let mut sim = sim();
+        let mut speeds = Vec::new();
+        for _ in 0..60 * 60 {
+            // both paddles follow the ball, the rally never ends
+            let follow = |side: Side| (sim.ball.position.y - sim.paddle(side).position.y).clamp(-1.0, 1.0);
+            let inputs = Inputs { left: follow(Side::Left), right: follow(Side::Right) };
+            for event in sim.step(DT, inputs) {
+                assert!(!matches!(event, Event::Goal(_)), "{:?}", event);
+                if let Event::PaddleHit(_) = event {
+                    speeds.push(sim.ball.velocity.length());
+                }
+            }
+        }
+
+        assert!(speeds.len() >= 5, "{:?}", speeds);
+        assert!(speeds[0] > sim.config.ball_velocity.length());
+        assert!(speeds.windows(2).all(|pair| pair[1] >= pair[0]), "{:?}", speeds);
+        assert!(speeds[speeds.len() - 1] > speeds[0]);
+        assert!(speeds.iter().all(|speed| *speed <= sim.config.max_ball_speed + 1e-3));
+    }
+
+    #[test]
+    fn missed_ball_scores_and_serves_toward_the_scorer() {
+        let mut sim = sim();
+        sim.ball.position = Vec2::new(405.0, 250.0);
+        sim.ball.velocity = Vec2::new(300.0, 0.0);
+
+        let events = sim.step(0.1, Inputs::default());
+        assert_eq!(events, vec![Event::Goal(Side::Left)]);
+        assert_eq!(sim.score, Score { left: 1, right: 0 });
+        assert_eq!(sim.ball.position, Vec2::ZERO);
+        assert!(sim.ball.velocity.x < 0.0);
+    }
+
+    #[test]
+    fn serve_waits_for_the_delay() {
+        let mut sim = PongSim::new(PongConfig { serve_delay: 1.0, ..PongConfig::default() });
+        assert!(sim.is_serving());
+
+        let events = sim.step(0.5, Inputs::default());
+        assert!(events.is_empty());
+        assert_eq!(sim.ball.position, Vec2::ZERO);
+
+        let events = sim.step(0.5, Inputs::default());
+        assert_eq!(events, vec![Event::Serve]);
+        assert!(!sim.is_serving());
+        assert_ne!(sim.ball.position, Vec2::ZERO);
+    }
+
+    #[test]
+    fn frame_interpolates_but_does_not_lerp_goals() {
+        let mut sim = sim();
+        sim.ball.position = Vec2::new(0.0, 0.0);
+        sim.ball.velocity = Vec2::new(120.0, 0.0);
+        let previous = sim.clone();
+        sim.step(0.5, Inputs { left: 1.0, right: 0.0 });
+
+        let frame = sim.frame(&previous, 0.25);
+        assert_eq!(frame.ball, Vec2::new(15.0, 0.0));
+        assert_eq!(frame.paddles[0].y, sim.config.paddle_speed * 0.5 * 0.25);
+
+        sim.ball.position = Vec2::new(405.0, 250.0);
+        let previous = sim.clone();
+        assert_eq!(sim.step(0.5, Inputs::default()), vec![Event::Goal(Side::Left)]);
+        assert_eq!(sim.frame(&previous, 0.5).ball, sim.ball.position);
+    }
+
+    fn goal(sim: &mut PongSim, scorer: Side) -> Vec<Event> {
+        sim.ball.position = Vec2::new(-scorer.sign() * 405.0, 250.0);
+        sim.ball.velocity = Vec2::new(-scorer.sign() * 300.0, 0.0);
+        sim.step(0.1, Inputs::default())
+    }
+
+    #[test]
+    fn conceder_serves() {
+        let mut sim = sim();
+        assert_eq!(sim.server(), Side::Left);
+
+        goal(&mut sim, Side::Left);
+        assert_eq!(sim.server(), Side::Right);
+        assert!(sim.ball.velocity.x < 0.0);
+        goal(&mut sim, Side::Left);
+        assert_eq!(sim.server(), Side::Right);
+        goal(&mut sim, Side::Right);
+        assert_eq!(sim.server(), Side::Left);
+        assert!(sim.ball.velocity.x > 0.0);
+    }
+
+    #[test]
+    fn serve_alternates() {
+        let mut sim = PongSim::new(PongConfig { serve: ServeRule::Alternate, ..PongConfig::default() });
+
+        let mut servers = vec![sim.server()];
+        for _ in 0..3 {
+            goal(&mut sim, Side::Left);
+            servers.push(sim.server());
+        }
+        assert_eq!(servers, vec![Side::Left, Side::Right, Side::Left, Side::Right]);
+    }
+
+    #[test]
+    fn countdown_before_each_serve() {
+        let mut sim = PongSim::new(PongConfig { serve_delay: 3.0, ..PongConfig::default() });
+        assert_eq!(sim.serve_countdown(), Some(3.0));
+
+        sim.step(1.0, Inputs::default());
+        assert_eq!(sim.serve_countdown(), Some(2.0));
+        sim.step(2.0, Inputs::default());
+        assert_eq!(sim.serve_countdown(), None);
+
+        goal(&mut sim, Side::Right);
+        assert_eq!(sim.serve_countdown(), Some(3.0));
+        assert_eq!(sim.ball.velocity, Vec2::ZERO);
+    }
+
+    #[test]
+    fn first_to_five_win_by_two() {
+        let mut sim = PongSim::new(PongConfig { points_to_win: 5, win_by: 2, ..PongConfig::default() });
+
+        for _ in 0..4 {
+            goal(&mut sim, Side::Left);
+            goal(&mut sim, Side::Right);
+        }
+        // 4 - 4, then 4 - 5 and 6 - 5 are not enough
+        assert_eq!(goal(&mut sim, Side::Right), vec![Event::Goal(Side::Right)]);
+        assert_eq!(sim.winner(), None);
+        goal(&mut sim, Side::Left);
+        goal(&mut sim, Side::Left);
+        assert_eq!(sim.score, Score { left: 6, right: 5 });
+        assert_eq!(sim.winner(), None);
+
+        assert_eq!(goal(&mut sim, Side::Left), vec![Event::Goal(Side::Left), Event::MatchOver(Side::Left)]);
+        assert_eq!(sim.winner(), Some(Side::Left));
+
+        // nothing moves once the match is over
+        let frozen = sim.clone();
+        assert!(sim.step(1.0, Inputs { left: 1.0, right: 1.0 }).is_empty());
+        assert_eq!(sim, frozen);
+
+        sim.restart();
+        assert_eq!(sim.winner(), None);
+        assert_eq!(sim.score, Score::default());
+    }
+
+    #[test]
+    fn endless_match() {
+        let mut sim = PongSim::new(PongConfig { points_to_win: 0, ..PongConfig::default() });
+        for _ in 0..50 {
+            goal(&mut sim, Side::Left);
+        }
+        assert_eq!(sim.winner(), None);
+        assert_eq!(sim.score.left, 50);
+    }
+
+    #[test]
+    fn set_config_keeps_the_match() {
+        let mut sim = sim();
+        goal(&mut sim, Side::Left);
+        for _ in 0..100 {
+            sim.step(DT, Inputs { left: 1.0, right: -1.0 });
+        }
+
+        sim.set_config(PongConfig { arena_width: 400.0, paddle_height: 500.0, ..PongConfig::default() });
+        assert_eq!(sim.score, Score { left: 1, right: 0 });
+        assert_eq!(sim.paddle(Side::Left).position, Vec2::new(-190.0, 50.0));
+        assert_eq!(sim.paddle(Side::Right).position, Vec2::new(190.0, -50.0));
+    }
+
+    #[test]
+    fn step_is_deterministic() {
+        let inputs = |i: usize| Inputs { left: (i % 7) as f32 / 3.0 - 1.0, right: (i % 5) as f32 / 2.0 - 1.0 };
+        let mut a = sim();
+        let mut b = sim();
+        for i in 0..10_000 {
+            assert_eq!(a.step(DT, inputs(i)), b.step(DT, inputs(i)));
+        }
+        assert_eq!(a, b);
+    }
+
+    fn power_ups() -> PongSim {
+        PongSim::with_seed(PongConfig { power_ups: true, ..PongConfig::default() }, 7)
+    }
+
+    /// A power-up right in front of the ball, sent by the left paddle.
+    fn catch(sim: &mut PongSim, kind: PowerUpKind) -> Vec<Event> {
+        sim.power_ups.push(PowerUp { kind, position: Vec2::new(20.0, 0.0) });
+        sim.ball.position = Vec2::ZERO;
+        sim.ball.velocity = Vec2::new(300.0, 0.0);
+        sim.step(0.1, Inputs::default())
+    }
+
+    #[test]
+    fn power_ups_appear_only_when_enabled() {
+        let mut sim = sim();
+        let mut enabled = power_ups();
+        for _ in 0..(10.0 / DT) as usize {
+            sim.step(DT, Inputs::default());
+            enabled.step(DT, Inputs::default());
+        }
+        assert!(sim.power_ups.is_empty());
+        assert!(!enabled.power_ups.is_empty());
+        for power_up in enabled.power_ups.iter() {
+            assert!(power_up.position.x.abs() <= 200.0 && power_up.position.y.abs() <= 270.0, "{:?}", power_up);
+        }
+    }
+
+    #[test]
+    fn ball_catches_power_ups_for_its_sender() {
+        let mut sim = power_ups();
+        let events = catch(&mut sim, PowerUpKind::Grow);
+        assert_eq!(events, vec![Event::PowerUp(PowerUpKind::Grow, Side::Left)]);
+        assert!(sim.power_ups.is_empty());
+        assert_eq!(sim.paddle_height(Side::Left), 120.0);
+        assert_eq!(sim.paddle_height(Side::Right), 80.0);
+
+        catch(&mut sim, PowerUpKind::Shrink);
+        assert_eq!(sim.paddle_height(Side::Right), 48.0);
+    }
+
+    #[test]
+    fn effects_wear_off() {
+        let mut sim = power_ups();
+        sim.config.power_up_duration = 1.0;
+        catch(&mut sim, PowerUpKind::SlowMo);
+        assert!(sim.has_effect(PowerUpKind::SlowMo, Side::Left));
+
+        // caught again, the duration starts over
+        sim.step(0.5, Inputs::default());
+        catch(&mut sim, PowerUpKind::SlowMo);
+        assert_eq!(sim.effects.len(), 1);
+
+        let events = sim.step(0.95, Inputs::default());
+        assert!(!events.contains(&Event::PowerUpOver(PowerUpKind::SlowMo, Side::Left)));
+        let events = sim.step(0.1, Inputs::default());
+        assert!(events.contains(&Event::PowerUpOver(PowerUpKind::SlowMo, Side::Left)));
+        assert!(sim.effects.is_empty());
+    }
+
+    #[test]
+    fn slow_mo_slows_every_ball() {
+        let mut sim = power_ups();
+        catch(&mut sim, PowerUpKind::SlowMo);
+        let position = sim.ball.position;
+        sim.step(0.1, Inputs::default());
+        assert_eq!(sim.ball.position.x - position.x, 15.0);
+    }
+
+    #[test]
+    fn curve_bends_the_balls_of_its_player() {
+        let mut sim = power_ups();
+        catch(&mut sim, PowerUpKind::Curve);
+        sim.step(0.1, Inputs::default());
+        assert!(sim.ball.velocity.y > 0.0);
+        assert!((sim.ball.velocity.length() - 300.0).abs() < 1e-3);
+
+        // the balls sent back by the right paddle go straight
+        sim.ball.velocity = Vec2::new(-300.0, 0.0);
+        sim.step(0.1, Inputs::default());
+        assert_eq!(sim.ball.velocity, Vec2::new(-300.0, 0.0));
+    }
+
+    #[test]
+    fn every_ball_of_a_multi_ball_scores() {
+        let mut sim = power_ups();
+        catch(&mut sim, PowerUpKind::MultiBall);
+        assert_eq!(sim.extra_balls.len(), 2);
+        assert_eq!(sim.frame(&sim.clone(), 0.5).extra_balls.len(), 2);
+
+        // the first extra ball takes the place of the one scoring, the rally
+        // goes on
+        let extra = sim.extra_balls[0].velocity;
+        assert_eq!(goal(&mut sim, Side::Left), vec![Event::Goal(Side::Left)]);
+        assert_eq!(sim.ball.velocity, extra);
+        assert_eq!(sim.extra_balls.len(), 1);
+
+        goal(&mut sim, Side::Left);
+        assert!(sim.extra_balls.is_empty());
+        assert_eq!(sim.score, Score { left: 2, right: 0 });
+
+        // the last ball ends the rally
+        goal(&mut sim, Side::Right);
+        assert_eq!(sim.ball.position, Vec2::ZERO);
+        assert_eq!(sim.score, Score { left: 2, right: 1 });
+    }
+
+    #[test]
+    fn extra_balls_go_with_the_multi_ball() {
+        let mut sim = power_ups();
+        sim.config.power_up_duration = 0.5;
+        catch(&mut sim, PowerUpKind::MultiBall);
+        assert_eq!(sim.extra_balls.len(), 2);
+
+        let events = sim.step(0.5, Inputs::default());
+        assert!(events.contains(&Event::PowerUpOver(PowerUpKind::MultiBall, Side::Left)));
+        assert!(sim.extra_balls.is_empty());
+    }
+
+    #[test]
+    fn power_ups_are_deterministic() {
+        let run = |seed| {
+            let mut sim = PongSim::with_seed(PongConfig { power_ups: true, power_up_interval: 1.0, ..PongConfig::default() }, seed);
+            let mut events = Vec::new();
+            for i in 0..3000 {
+                let axis = ((i / 40) % 3) as f32 - 1.0;
+                events.extend(sim.step(DT, Inputs { left: axis, right: -axis }));
+            }
+            (sim, events)
+        };
+
+        let (first, events) = run(3);
+        assert_eq!(run(3), (first.clone(), events.clone()));
+        assert_ne!(run(4).0.power_ups, first.power_ups);
+        assert!(!first.power_ups.is_empty() || events.iter().any(|event| matches!(event, Event::PowerUp(_, _))));
+    }
+}
@@ -0,0 +1,270 @@
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::sim::{Event, PongSim, Score, Side};
+
+/// Matches kept in the [`History`], the oldest are dropped.
+pub const MAX_MATCHES: usize = 100;
+/// Length of the high score table.
+pub const MAX_HIGH_SCORES: usize = 10;
+
+/// Statistics of one match, fed with the events of every step.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MatchStats {
+    /// Paddle hits of each point played.
+    pub rallies: Vec<u32>,
+    pub longest_rally: u32,
+    /// Fastest ball of the match, in units per second.
+    pub top_speed: f32,
+    /// Points of the left then of the right player.
+    pub points: [u32; 2],
+    /// Paddle hits of the point being played.
+    #[serde(skip)]
+    rally: u32
+}
+
+impl MatchStats {
+    /// Account for a step of `sim` and the events it gave.
+    pub fn record(&mut self, sim: &PongSim, events: &[Event]) {
+        for event in events {
+            match event {
+                Event::Serve => self.rally = 0,
+                Event::PaddleHit(_) => {
+                    self.rally += 1;
+                    self.longest_rally = self.longest_rally.max(self.rally);
+                }
+                Event::Goal(side) => {
+                    self.points[side.index()] += 1;
+                    self.rallies.push(self.rally);
+                    self.rally = 0;
+                }
+                _ => {}
+            }
+        }
+        for ball in sim.balls() {
+            self.top_speed = self.top_speed.max(ball.velocity.length());
+        }
+    }
+
+    pub fn average_rally(&self) -> f32 {
+        if self.rallies.is_empty() {
+            return 0.0;
+        }
+        self.rallies.iter().sum::<u32>() as f32 / self.rallies.len() as f32
+    }
+}
+
+/// A match played to the end.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct MatchRecord {
+    /// Seconds since the Unix epoch when it ended.
+    pub time: u64,
+    pub winner: Side,
+    pub score: Score,
+    pub stats: MatchStats
+}
+
+impl MatchRecord {
+    /// The match of `sim` once won, `None` before.
+    pub fn new(sim: &PongSim, stats: MatchStats) -> Option<MatchRecord> {
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+        sim.winner().map(|winner| MatchRecord { time, winner, score: sim.score.clone(), stats })
+    }
+
+    /// Ranked by the longest rally, then by the top speed.
+    fn beats(&self, other: &MatchRecord) -> bool {
+        (self.stats.longest_rally, self.stats.top_speed) > (other.stats.longest_rally, other.stats.top_speed)
+    }
+}
+
+/// The last matches played and the best ones, saved in the user data dir so
+/// that they outlive the game.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct History {
+    /// Oldest first.
+    pub matches: Vec<MatchRecord>,
+    /// Best first, see [`History::add`].
+    pub high_scores: Vec<MatchRecord>
+}
+
+impl History {
+    /// Keep a finished match, gives its rank in the high scores when it made it.
+    pub fn add(&mut self, record: MatchRecord) -> Option<usize> {
+        let rank = self.high_scores.iter().position(|high_score| record.beats(high_score))
+            .unwrap_or(self.high_scores.len());
+        if rank < MAX_HIGH_SCORES {
+            self.high_scores.insert(rank, record.clone());
+            self.high_scores.truncate(MAX_HIGH_SCORES);
+        }
+
+        self.matches.push(record);
+        if self.matches.len() > MAX_MATCHES {
+            self.matches.remove(0);
+        }
+        if rank < MAX_HIGH_SCORES { Some(rank) } else { None }
+    }
+
+    pub fn wins(&self, side: Side) -> usize {
+        self.matches.iter().filter(|record| record.winner == side).count()
+    }
+
+    /// The stats screen of the front-ends, one line per text.
+    pub fn lines(&self) -> Vec<String> {
+        let side = |side: Side| match side {
+            Side::Left => "LEFT",
+            Side::Right => "RIGHT"
+        };
+        let mut lines = vec![format!(
+            "{} MATCHES  LEFT WINS {}  RIGHT WINS {}",
+            self.matches.len(), self.wins(Side::Left), self.wins(Side::Right)
+        )];
+
+        if let Some(last) = self.matches.last() {
+            lines.push(format!(
+                "LAST MATCH {} WON {} - {}  AVERAGE RALLY {:.1}  TOP SPEED {:.0}",
+                side(last.winner), last.score.left, last.score.right,
+                last.stats.average_rally(), last.stats.top_speed
+            ));
+        }
+
+        lines.push("HIGH SCORES".to_string());
+        if self.high_scores.is_empty() {
+            lines.push("no match played yet".to_string());
+        }
+        for (rank, record) in self.high_scores.iter().enumerate() {
+            lines.push(format!(
+                "{:>2}. RALLY {:>3}  SPEED {:>4.0}  {} WON {} - {}",
+                rank + 1, record.stats.longest_rally, record.stats.top_speed,
+                side(record.winner), record.score.left, record.score.right
+            ));
+        }
+        lines
+    }
+
+    pub fn from_ron(text: &str) -> Result<History, String> {
+        ron::de::from_str(text).map_err(|error| format!("Invalid Pong history: {}", error))
+    }
+
+    pub fn to_ron(&self) -> Result<String, String> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| format!("Can not write the Pong history: {}", error))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<History, String> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|error| format!("Can not read {}: {}", path.display(), error))?;
+
+        History::from_ron(&text).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)
+                .map_err(|error| format!("Can not create {}: {}", directory.display(), error))?;
+        }
+        fs::write(path, self.to_ron()?).map_err(|error| format!("Can not write {}: {}", path.display(), error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PongConfig;
+    use crate::sim::Inputs;
+
+    fn record(longest_rally: u32, top_speed: f32) -> MatchRecord {
+        MatchRecord {
+            time: 0,
+            winner: Side::Left,
+            score: Score { left: 11, right: 3 },
+            stats: MatchStats { longest_rally, top_speed, ..MatchStats::default() }
+        }
+    }
+
+    #[test]
+    fn stats_of_a_match() {
+        let config = PongConfig { points_to_win: 3, win_by: 1, ..PongConfig::default() };
+        let mut sim = PongSim::new(config);
+        let mut stats = MatchStats::default();
+        // the left paddle follows the ball, the right one stays still
+        let mut steps = 0;
+        while sim.winner().is_none() && steps < 100_000 {
+            let inputs = Inputs { left: ((sim.ball.position.y - sim.paddles[0].position.y) * 0.1).clamp(-1.0, 1.0), right: 0.0 };
+            let events = sim.step(1.0 / 60.0, inputs);
+            stats.record(&sim, &events);
+            steps += 1;
+        }
+
+        assert_eq!(sim.winner(), Some(Side::Left));
+        assert_eq!(stats.points, [sim.score.left, sim.score.right]);
+        assert_eq!(stats.rallies.len() as u32, sim.score.left + sim.score.right);
+        assert_eq!(stats.longest_rally, *stats.rallies.iter().max().unwrap());
+        assert!(stats.longest_rally >= 1);
+        assert!(stats.top_speed >= sim.config.ball_velocity.length());
+
+        let record = MatchRecord::new(&sim, stats).unwrap();
+        assert_eq!(record.winner, Side::Left);
+    }
+
+    #[test]
+    fn rallies_count_the_hits_of_each_point() {
+        let sim = PongSim::new(PongConfig::default());
+        let mut stats = MatchStats::default();
+        let hit = Event::PaddleHit(Side::Left);
+        stats.record(&sim, &[Event::Serve, hit, hit, hit, Event::Goal(Side::Left)]);
+        stats.record(&sim, &[Event::Serve, hit, Event::Goal(Side::Right)]);
+
+        assert_eq!(stats.rallies, vec![3, 1]);
+        assert_eq!(stats.longest_rally, 3);
+        assert_eq!(stats.points, [1, 1]);
+        assert_eq!(stats.average_rally(), 2.0);
+    }
+
+    #[test]
+    fn high_scores_keep_the_best() {
+        let mut history = History::default();
+        assert_eq!(history.add(record(5, 300.0)), Some(0));
+        assert_eq!(history.add(record(9, 300.0)), Some(0));
+        assert_eq!(history.add(record(5, 400.0)), Some(1));
+        for _ in 0..MAX_HIGH_SCORES {
+            history.add(record(7, 0.0));
+        }
+        assert_eq!(history.add(record(1, 0.0)), None);
+
+        let best: Vec<u32> = history.high_scores.iter().map(|record| record.stats.longest_rally).collect();
+        assert_eq!(best.len(), MAX_HIGH_SCORES);
+        assert_eq!(best[0], 9);
+        assert!(best.iter().all(|rally| *rally >= 7));
+        assert_eq!(history.matches.len(), MAX_HIGH_SCORES + 4);
+
+        for _ in 0..MAX_MATCHES {
+            history.add(record(0, 0.0));
+        }
+        assert_eq!(history.matches.len(), MAX_MATCHES);
+        assert_eq!(history.wins(Side::Left), MAX_MATCHES);
+    }
+
+    #[test]
+    fn history_saved_and_loaded() {
+        let mut history = History::default();
+        history.add(MatchRecord {
+            stats: MatchStats { rallies: vec![2, 4], points: [11, 1], ..record(4, 512.5).stats },
+            ..record(4, 512.5)
+        });
+        let path = std::env::temp_dir().join(format!("pong_sim_history_{}.ron", std::process::id()));
+        history.save(&path).unwrap();
+        assert_eq!(History::load(&path), Ok(history.clone()));
+        std::fs::remove_file(&path).unwrap();
+
+        let lines = history.lines();
+        assert_eq!(lines[0], "1 MATCHES  LEFT WINS 1  RIGHT WINS 0");
+        assert!(lines[3].contains("RALLY   4"), "{:?}", lines);
+    }
+}
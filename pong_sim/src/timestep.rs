@@ -0,0 +1,96 @@
+/// Accumulates frame time and tells how many fixed steps to run, so that the
+/// simulation advances the same way whatever the frame rate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixedTimestep {
+    step: f32,
+    max_steps: u32,
+    accumulator: f32
+}
+
+impl FixedTimestep {
+    /// Simulation rate used by the front-ends.
+    pub const DEFAULT_RATE: f32 = 120.0;
+
+    /// Steps run in one frame at most by default.
+    pub const DEFAULT_MAX_STEPS: u32 = 8;
+
+    /// At most `max_steps` are run per frame, the rest of a long frame (a
+    /// window drag, a breakpoint, ...) is dropped instead of catching up forever.
+    pub fn new(step: f32, max_steps: u32) -> FixedTimestep {
+        FixedTimestep { step, max_steps, accumulator: 0.0 }
+    }
+
+    pub fn from_rate(rate: f32) -> FixedTimestep {
+        FixedTimestep::new(1.0 / rate, FixedTimestep::DEFAULT_MAX_STEPS)
+    }
+
+    /// Duration of one step, in seconds.
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    /// Add the duration of a frame and return the number of steps to run.
+    pub fn advance(&mut self, dt: f32) -> u32 {
+        self.accumulator += dt.max(0.0);
+
+        let mut steps = 0;
+        while self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            steps += 1;
+            if steps == self.max_steps {
+                self.accumulator %= self.step;
+                break;
+            }
+        }
+
+        steps
+    }
+
+    /// How far the frame is between the last step and the next one, between 0
+    /// and 1. Render `previous.lerp(current, alpha)` to hide the steps.
+    pub fn alpha(&self) -> f32 {
+        self.accumulator / self.step
+    }
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        FixedTimestep::from_rate(FixedTimestep::DEFAULT_RATE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_do_not_depend_on_the_frame_rate() {
+        for &fps in [30.0, 59.94, 60.0, 144.0, 240.0, 1000.0].iter() {
+            let mut timestep = FixedTimestep::new(1.0 / 120.0, 100);
+            let frames = (fps * 10.0) as usize;
+            let steps: u32 = (0..frames).map(|_| timestep.advance(1.0 / fps)).sum();
+
+            // ten seconds, give or take the step in progress
+            let expected = (frames as f32 / fps * 120.0) as u32;
+            assert!(steps + 1 >= expected && steps <= expected, "{} fps: {} steps", fps, steps);
+            assert!((0.0..1.0).contains(&timestep.alpha()));
+        }
+    }
+
+    #[test]
+    fn alpha_is_the_time_left_in_the_accumulator() {
+        let mut timestep = FixedTimestep::new(0.01, 8);
+        assert_eq!(timestep.advance(0.025), 2);
+        assert!((timestep.alpha() - 0.5).abs() < 1e-3);
+        assert_eq!(timestep.advance(0.005), 1);
+        assert!(timestep.alpha() < 1e-3);
+    }
+
+    #[test]
+    fn long_frames_are_capped() {
+        let mut timestep = FixedTimestep::new(0.01, 8);
+        assert_eq!(timestep.advance(10.0), 8);
+        assert!(timestep.alpha() < 1.0);
+        assert_eq!(timestep.advance(0.0), 0);
+    }
+}
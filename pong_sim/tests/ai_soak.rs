@@ -0,0 +1,79 @@
+//! Computer against computer for long matches, checking that nothing escapes
+//! the arena and that the difficulties rank as expected.
+
+use pong_sim::{AiController, Difficulty, Event, Inputs, PongConfig, PongSim, Score, Side};
+
+const DT: f32 = 1.0 / 120.0;
+
+fn soak(left: Difficulty, right: Difficulty, seed: u64, seconds: f32) -> Score {
+    let mut sim = PongSim::new(PongConfig { points_to_win: 0, ..PongConfig::default() });
+    let mut left = AiController::new(Side::Left, left, seed);
+    let mut right = AiController::new(Side::Right, right, seed.wrapping_add(1));
+
+    let half_width = sim.config.arena_width / 2.0 + sim.config.ball_radius;
+    let top = sim.config.arena_height / 2.0 - sim.config.ball_radius;
+    let paddle_limit = (sim.config.arena_height - sim.config.paddle_height) / 2.0;
+
+    for _ in 0..(seconds / DT) as usize {
+        let inputs = Inputs { left: left.update(&sim, DT), right: right.update(&sim, DT) };
+        assert!(inputs.left.abs() <= 1.0 && inputs.right.abs() <= 1.0, "{:?}", inputs);
+
+        let events = sim.step(DT, inputs);
+
+        let ball = sim.ball.position;
+        assert!(ball.x.is_finite() && ball.y.is_finite(), "{:?}", sim.ball);
+        assert!(ball.y.abs() <= top + 1e-3, "{:?}", sim.ball);
+        if !events.iter().any(|event| matches!(event, Event::Goal(_))) {
+            assert!(ball.x.abs() <= half_width + 1e-3, "{:?}", sim.ball);
+        }
+        for paddle in sim.paddles.iter() {
+            assert!(paddle.position.y.abs() <= paddle_limit, "{:?}", paddle);
+        }
+    }
+
+    sim.score
+}
+
+#[test]
+fn every_pairing_runs_ten_minutes() {
+    let difficulties = [Difficulty::EASY, Difficulty::NORMAL, Difficulty::HARD];
+    for (i, &left) in difficulties.iter().enumerate() {
+        for (j, &right) in difficulties.iter().enumerate() {
+            // a few matches, normal against hard is close enough for one
+            // match to go either way
+            let mut score = Score::default();
+            for k in 0..3 {
+                let match_score = soak(left, right, ((i * 3 + j) * 3 + k) as u64, 600.0);
+                score.left += match_score.left;
+                score.right += match_score.right;
+            }
+
+            // the list goes from easy to hard
+            if i > j {
+                assert!(score.left >= score.right, "{:?} vs {:?}: {:?}", left, right, score);
+            }
+            if i < j {
+                assert!(score.left <= score.right, "{:?} vs {:?}: {:?}", left, right, score);
+            }
+        }
+    }
+}
+
+#[test]
+fn harder_beats_easier() {
+    let mut hard = 0;
+    let mut easy = 0;
+    for seed in 0..4 {
+        let score = soak(Difficulty::HARD, Difficulty::EASY, seed, 300.0);
+        hard += score.left;
+        easy += score.right;
+    }
+    assert!(hard > easy * 2, "hard {} easy {}", hard, easy);
+}
+
+#[test]
+fn soak_is_deterministic() {
+    let a = soak(Difficulty::NORMAL, Difficulty::EASY, 9, 120.0);
+    let b = soak(Difficulty::NORMAL, Difficulty::EASY, 9, 120.0);
+    assert_eq!(a, b);
+}
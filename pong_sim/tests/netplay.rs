@@ -0,0 +1,112 @@
+//! Two processes playing a networked match over loopback, through a transport
+//! adding latency, jitter and packet loss. The test binary runs itself a
+//! second time as the remote peer.
+
+use std::env;
+use std::io::{BufRead, BufReader};
+use std::net::{SocketAddr, UdpSocket};
+use std::process::{Command, Stdio};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use pong_sim::{
+    Inputs, LossyTransport, Peer, PongConfig, PongSim, Rng, RollbackSession, RollbackSettings, Side, UdpTransport
+};
+
+const FRAMES: u32 = 1200;
+const PEER_ENV: &str = "PONG_NETPLAY_PEER";
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Inputs changing every quarter of a second, the same in both processes.
+fn scripted(side: Side, frame: u32) -> f32 {
+    let seed = (frame / 30) as u64 * 2 + side as u64;
+    [-1.0, 0.0, 1.0][(Rng::new(seed).next_u64() % 3) as usize]
+}
+
+fn reference() -> PongSim {
+    let settings = RollbackSettings::default();
+    let mut sim = PongSim::new(PongConfig::default());
+    for frame in 0..FRAMES {
+        let delayed = |side| match frame.checked_sub(settings.input_delay) {
+            Some(frame) => scripted(side, frame),
+            None => 0.0
+        };
+        sim.step(settings.step, Inputs { left: delayed(Side::Left), right: delayed(Side::Right) });
+    }
+    sim
+}
+
+fn play(side: Side, socket: UdpSocket, remote: SocketAddr) -> PongSim {
+    let transport = UdpTransport::new(socket, remote).unwrap();
+    let transport = LossyTransport::new(
+        transport, Duration::from_millis(15), Duration::from_millis(10), 0.2, side as u64
+    );
+    let session = RollbackSession::new(PongConfig::default(), side, RollbackSettings::default());
+    let mut peer = Peer::new(session, transport);
+    let start = Instant::now();
+
+    while peer.session.frame() < FRAMES {
+        let frame = peer.session.frame();
+        peer.update(scripted(side, frame));
+        sleep(Duration::from_millis(1));
+        assert!(start.elapsed() < TIMEOUT, "stuck at frame {}", frame);
+    }
+
+    // keep talking until both peers have every input, a bit longer for the
+    // last acknowledgements still in flight
+    let mut synchronized: Option<Instant> = None;
+    loop {
+        peer.poll();
+        peer.send();
+        if peer.is_synchronized(FRAMES)
+            && synchronized.get_or_insert_with(Instant::now).elapsed() > Duration::from_millis(200)
+        {
+            break;
+        }
+        sleep(Duration::from_millis(1));
+        assert!(start.elapsed() < TIMEOUT, "never synchronized");
+    }
+
+    assert!(peer.session.resimulated() > 0);
+    peer.session.sim().clone()
+}
+
+/// The remote half of `two_processes_over_loopback`, it does nothing when
+/// the test binary is run normally.
+#[test]
+fn peer_process() {
+    let remote = match env::var(PEER_ENV) {
+        Ok(remote) => remote.parse().unwrap(),
+        Err(_) => return
+    };
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    println!("PEER_ADDRESS {}", socket.local_addr().unwrap());
+
+    let sim = play(Side::Right, socket, remote);
+    println!("PEER_RESULT {:?}", sim);
+}
+
+#[test]
+fn two_processes_over_loopback() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let mut child = Command::new(env::current_exe().unwrap())
+        .args(["peer_process", "--exact", "--nocapture", "--test-threads=1"])
+        .env(PEER_ENV, socket.local_addr().unwrap().to_string())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines().map(Result::unwrap);
+    let mut value_of = |key: &str| {
+        // the test harness writes the name of the test on the same line
+        lines.find_map(|line| line.find(key).map(|at| line[at + key.len()..].to_string()))
+            .unwrap_or_else(|| panic!("the peer process did not print {}", key))
+    };
+
+    let remote = value_of("PEER_ADDRESS ").parse().unwrap();
+    let sim = play(Side::Left, socket, remote);
+    let remote_sim = value_of("PEER_RESULT ");
+    assert!(child.wait().unwrap().success());
+
+    assert_eq!(remote_sim, format!("{:?}", sim));
+    assert_eq!(sim, reference());
+}
@@ -0,0 +1,35 @@
+//! Recorded matches played again without rendering, a change of the rules
+//! that alters a match shows up here. Record new ones with
+//! `cargo run --example record` or from a front-end.
+
+use std::fs;
+use std::path::Path;
+
+use pong_sim::{Replay, Score};
+
+fn play(name: &str) -> Score {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("replays").join(name);
+    let replay = Replay::load(path).unwrap();
+    replay.verify().unwrap().score
+}
+
+#[test]
+fn easy_vs_hard() {
+    assert_eq!(play("easy_vs_hard.ron"), Score { left: 0, right: 3 });
+}
+
+#[test]
+fn normal_vs_normal() {
+    assert_eq!(play("normal_vs_normal.ron"), Score { left: 3, right: 1 });
+}
+
+#[test]
+fn every_replay_ends_as_recorded() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("replays");
+    for entry in fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+        if let Err(error) = Replay::load(&path).and_then(|replay| replay.verify()) {
+            panic!("{}: {}", path.display(), error);
+        }
+    }
+}
@@ -0,0 +1,43 @@
+//! Neighbours of every boid, with the grid and checking every other boid.
+//! The area grows with the count to keep the density of 2000 boids in the
+//! 800x600 arena.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use spatial_grid::{brute_force, scatter, SpatialGrid};
+
+const RADIUS: f32 = 50.0;
+
+fn neighbours(c: &mut Criterion) {
+    let mut group = c.benchmark_group("neighbours");
+    group.sample_size(10);
+    for count in [2_000, 20_000].iter() {
+        let scale = (*count as f32 / 2000.0).sqrt();
+        let size = [800.0 * scale, 600.0 * scale];
+        let positions = scatter(*count, size, 42);
+
+        group.bench_with_input(BenchmarkId::new("grid", count), &positions, |b, positions| {
+            let mut grid = SpatialGrid::centered(size[0], size[1], RADIUS);
+            b.iter(|| {
+                grid.rebuild(positions.iter().copied());
+                let mut found = 0;
+                for position in positions.iter() {
+                    grid.query(*position, RADIUS, |_, _, _| found += 1);
+                }
+                found
+            });
+        });
+        group.bench_with_input(BenchmarkId::new("brute_force", count), &positions, |b, positions| {
+            b.iter(|| {
+                let mut found = 0;
+                for position in positions.iter() {
+                    brute_force(positions, size, *position, RADIUS, |_, _, _| found += 1);
+                }
+                found
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, neighbours);
+criterion_main!(benches);
@@ -0,0 +1,332 @@
+//! Uniform grid for the neighbour queries of the boids.
+//!
+//! The amethyst and bevy boids rebuild a [`PayloadGrid`] from the positions,
+//! entities and velocities every step, then ask it for the boids around each
+//! one. The area wraps around like the screen: a boid at the left edge sees
+//! the ones at the right edge, and the offsets given go the short way.
+
+/// Positions bucketed in cells of the area, rebuilt at once from all the
+/// positions. The buffers are kept between rebuilds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpatialGrid {
+    /// Corner of the area with the smallest coordinates.
+    min: [f32; 2],
+    size: [f32; 2],
+    columns: usize,
+    rows: usize,
+    /// Size of a cell, at least the one asked for since the cells fill the area.
+    cell: [f32; 2],
+    /// Where each cell starts in `entries`, one more than the cells.
+    starts: Vec<usize>,
+    /// Indices of the positions, sorted by cell.
+    entries: Vec<usize>,
+    positions: Vec<[f32; 2]>,
+    /// Cell of each position, kept to sort them.
+    cells: Vec<usize>
+}
+
+impl SpatialGrid {
+    /// An empty grid over the area from `min` of `size`, with cells of at
+    /// least `cell_size`. Queries are fastest with a cell size close to the
+    /// largest radius asked.
+    pub fn new(min: [f32; 2], size: [f32; 2], cell_size: f32) -> SpatialGrid {
+        let count = |length: f32| ((length / cell_size).floor() as usize).max(1);
+        let (columns, rows) = (count(size[0]), count(size[1]));
+        SpatialGrid {
+            min,
+            size,
+            columns,
+            rows,
+            cell: [size[0] / columns as f32, size[1] / rows as f32],
+            starts: vec![0; columns * rows + 1],
+            entries: Vec::new(),
+            positions: Vec::new(),
+            cells: Vec::new()
+        }
+    }
+
+    /// An empty grid over the area centered on the origin, as in the boids.
+    pub fn centered(width: f32, height: f32, cell_size: f32) -> SpatialGrid {
+        SpatialGrid::new([-width * 0.5, -height * 0.5], [width, height], cell_size)
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub fn positions(&self) -> &[[f32; 2]] {
+        &self.positions
+    }
+
+    /// Replace the positions, their index is the one given to the queries.
+    /// The ones out of the area are wrapped into it.
+    pub fn rebuild<I: IntoIterator<Item = [f32; 2]>>(&mut self, positions: I) {
+        self.positions.clear();
+        self.positions.extend(positions);
+
+        // counting sort by cell
+        self.cells.clear();
+        self.starts.iter_mut().for_each(|start| *start = 0);
+        for index in 0..self.positions.len() {
+            let (column, row) = self.cell_of(self.positions[index]);
+            let cell = row * self.columns + column;
+            self.cells.push(cell);
+            self.starts[cell + 1] += 1;
+        }
+        for cell in 0..self.columns * self.rows {
+            self.starts[cell + 1] += self.starts[cell];
+        }
+
+        self.entries.clear();
+        self.entries.resize(self.positions.len(), 0);
+        // the starts are moved forward while filling, and back afterwards
+        for (index, cell) in self.cells.iter().enumerate() {
+            self.entries[self.starts[*cell]] = index;
+            self.starts[*cell] += 1;
+        }
+        for cell in (1..=self.columns * self.rows).rev() {
+            self.starts[cell] = self.starts[cell - 1];
+        }
+        self.starts[0] = 0;
+    }
+
+    /// Call `f` with the index, the offset from `center` and the squared
+    /// distance of every position closer than `radius`, the position at
+    /// `center` included. Each position is given once, even when the radius
+    /// is larger than the area.
+    pub fn query<F: FnMut(usize, [f32; 2], f32)>(&self, center: [f32; 2], radius: f32, mut f: F) {
+        let (column, row) = self.cell_of(center);
+        let radius_sq = radius * radius;
+        let columns = around(column, (radius / self.cell[0]).ceil() as usize, self.columns);
+        let rows = around(row, (radius / self.cell[1]).ceil() as usize, self.rows);
+        for row in rows {
+            for column in columns.clone() {
+                let cell = row * self.columns + column;
+                for index in &self.entries[self.starts[cell]..self.starts[cell + 1]] {
+                    let offset = self.offset(center, self.positions[*index]);
+                    let distance_sq = offset[0] * offset[0] + offset[1] * offset[1];
+                    if distance_sq < radius_sq {
+                        f(*index, offset, distance_sq);
+                    }
+                }
+            }
+        }
+    }
+
+    /// From `from` to `to` the short way around the area.
+    pub fn offset(&self, from: [f32; 2], to: [f32; 2]) -> [f32; 2] {
+        wrapped_offset(from, to, self.size)
+    }
+
+    fn cell_of(&self, position: [f32; 2]) -> (usize, usize) {
+        let index = |axis: usize, count: usize| {
+            let local = (position[axis] - self.min[axis]).rem_euclid(self.size[axis]);
+            ((local / self.cell[axis]) as usize).min(count - 1)
+        };
+        (index(0, self.columns), index(1, self.rows))
+    }
+}
+
+/// The cells up to `reach` away from `cell` on an axis of `count` cells that
+/// wraps around, each once.
+fn around(cell: usize, reach: usize, count: usize) -> impl Iterator<Item = usize> + Clone {
+    let (first, length) = if 2 * reach + 1 >= count { (0, count) } else { (cell + count - reach, 2 * reach + 1) };
+    (0..length).map(move |step| (first + step) % count)
+}
+
+/// A [`SpatialGrid`] over an area centered on the origin, with a payload for
+/// each position, like the entity and the velocity of a boid. The grid is
+/// made again when the area or the cell size changes, the buffers are kept
+/// between rebuilds otherwise.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PayloadGrid<T> {
+    grid: SpatialGrid,
+    /// Of each position of the grid.
+    payloads: Vec<T>,
+    /// Area and cell size the grid was made for.
+    area: [f32; 2],
+    cell_size: f32
+}
+
+impl<T> Default for PayloadGrid<T> {
+    fn default() -> Self {
+        PayloadGrid {
+            grid: SpatialGrid::centered(1.0, 1.0, 1.0),
+            payloads: Vec::new(),
+            area: [1.0, 1.0],
+            cell_size: 1.0
+        }
+    }
+}
+
+impl<T> PayloadGrid<T> {
+    pub fn grid(&self) -> &SpatialGrid {
+        &self.grid
+    }
+
+    pub fn payloads(&self) -> &[T] {
+        &self.payloads
+    }
+
+    /// Replace the positions and their payloads, over an area of `area`
+    /// centered on the origin with cells of at least `cell_size`.
+    pub fn rebuild<I: IntoIterator<Item = ([f32; 2], T)>>(&mut self, area: [f32; 2], cell_size: f32, items: I) {
+        if area != self.area || cell_size != self.cell_size {
+            self.grid = SpatialGrid::centered(area[0], area[1], cell_size);
+            self.area = area;
+            self.cell_size = cell_size;
+        }
+
+        let payloads = &mut self.payloads;
+        payloads.clear();
+        self.grid.rebuild(items.into_iter().map(|(position, payload)| {
+            payloads.push(payload);
+            position
+        }));
+    }
+
+    /// Like [`SpatialGrid::query`], with the payload instead of the index.
+    pub fn query<F: FnMut(&T, [f32; 2], f32)>(&self, center: [f32; 2], radius: f32, mut f: F) {
+        let payloads = &self.payloads;
+        self.grid.query(center, radius, |index, offset, distance_sq| f(&payloads[index], offset, distance_sq));
+    }
+}
+
+/// `count` points scattered over the area of `size` centered on the origin,
+/// the same ones for the same seed. SplitMix64, for the tests and the
+/// benchmarks.
+pub fn scatter(count: usize, size: [f32; 2], mut seed: u64) -> Vec<[f32; 2]> {
+    let mut next = move || {
+        seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        ((z ^ (z >> 31)) >> 40) as f32 / (1u64 << 24) as f32
+    };
+    (0..count).map(|_| [(next() - 0.5) * size[0], (next() - 0.5) * size[1]]).collect()
+}
+
+/// From `from` to `to` the short way around an area of `size`.
+pub fn wrapped_offset(from: [f32; 2], to: [f32; 2], size: [f32; 2]) -> [f32; 2] {
+    let wrap = |axis: usize| {
+        let delta = to[axis] - from[axis];
+        delta - size[axis] * (delta / size[axis]).round()
+    };
+    [wrap(0), wrap(1)]
+}
+
+/// The same query as [`SpatialGrid::query`] checking every position, what
+/// the boids did before the grid.
+pub fn brute_force<F: FnMut(usize, [f32; 2], f32)>(
+    positions: &[[f32; 2]],
+    size: [f32; 2],
+    center: [f32; 2],
+    radius: f32,
+    mut f: F
+) {
+    let radius_sq = radius * radius;
+    for (index, position) in positions.iter().enumerate() {
+        let offset = wrapped_offset(center, *position, size);
+        let distance_sq = offset[0] * offset[0] + offset[1] * offset[1];
+        if distance_sq < radius_sq {
+            f(index, offset, distance_sq);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn neighbours(grid: &SpatialGrid, center: [f32; 2], radius: f32) -> Vec<usize> {
+        let mut found = Vec::new();
+        grid.query(center, radius, |index, _, _| found.push(index));
+        found.sort_unstable();
+        found
+    }
+
+    #[test]
+    fn finds_the_same_as_brute_force() {
+        let size = [800.0, 600.0];
+        let positions = scatter(2000, size, 7);
+        let mut grid = SpatialGrid::centered(size[0], size[1], 50.0);
+        grid.rebuild(positions.iter().copied());
+        assert_eq!(grid.len(), 2000);
+
+        for radius in [20.0, 50.0, 120.0, 1000.0].iter() {
+            for center in positions.iter().take(100) {
+                let mut expected = Vec::new();
+                brute_force(&positions, size, *center, *radius, |index, _, _| expected.push(index));
+                assert_eq!(neighbours(&grid, *center, *radius), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn sees_across_the_edges() {
+        let mut grid = SpatialGrid::centered(800.0, 600.0, 50.0);
+        grid.rebuild(vec![[-395.0, 0.0], [395.0, 0.0], [0.0, 295.0], [0.0, -295.0], [0.0, 0.0]]);
+
+        let mut offsets = Vec::new();
+        grid.query([-395.0, 0.0], 20.0, |index, offset, _| offsets.push((index, offset)));
+        offsets.sort_by_key(|(index, _)| *index);
+        assert_eq!(offsets, vec![(0, [0.0, 0.0]), (1, [-10.0, 0.0])]);
+
+        assert_eq!(neighbours(&grid, [0.0, 298.0], 20.0), vec![2, 3]);
+        assert_eq!(neighbours(&grid, [0.0, 0.0], 20.0), vec![4]);
+    }
+
+    #[test]
+    fn wraps_positions_out_of_the_area() {
+        let mut grid = SpatialGrid::centered(100.0, 100.0, 10.0);
+        grid.rebuild(vec![[55.0, 0.0], [-160.0, 0.0]]);
+        assert_eq!(neighbours(&grid, [-45.0, 0.0], 1.0), vec![0]);
+        assert_eq!(neighbours(&grid, [40.0, 0.0], 1.0), vec![1]);
+    }
+
+    #[test]
+    fn rebuilds_from_scratch() {
+        let mut grid = SpatialGrid::centered(100.0, 100.0, 10.0);
+        grid.rebuild(scatter(50, [100.0, 100.0], 1));
+        grid.rebuild(vec![[0.0, 0.0]]);
+        assert_eq!(grid.len(), 1);
+        assert_eq!(neighbours(&grid, [0.0, 0.0], 200.0), vec![0]);
+
+        grid.rebuild(Vec::new());
+        assert!(grid.is_empty());
+        assert!(neighbours(&grid, [0.0, 0.0], 200.0).is_empty());
+    }
+
+    #[test]
+    fn small_areas_have_one_cell() {
+        let mut grid = SpatialGrid::centered(10.0, 10.0, 50.0);
+        grid.rebuild(vec![[-4.0, -4.0], [4.0, 4.0]]);
+        assert_eq!(neighbours(&grid, [0.0, 0.0], 100.0), vec![0, 1]);
+    }
+
+    #[test]
+    fn payloads_follow_their_position() {
+        let mut grid = PayloadGrid::default();
+        grid.rebuild([100.0, 100.0], 10.0, vec![([40.0, 0.0], 'a'), ([-45.0, 0.0], 'b'), ([0.0, 0.0], 'c')]);
+        let mut found = Vec::new();
+        grid.query([45.0, 0.0], 11.0, |payload, offset, _| found.push((*payload, offset)));
+        found.sort_by_key(|(payload, _)| *payload);
+        assert_eq!(found, vec![('a', [-5.0, 0.0]), ('b', [10.0, 0.0])]);
+    }
+
+    #[test]
+    fn payload_grids_follow_the_area() {
+        let mut grid = PayloadGrid::default();
+        grid.rebuild([100.0, 100.0], 10.0, vec![([45.0, 0.0], 0)]);
+        assert_eq!(grid.payloads(), &[0]);
+        assert_eq!(grid.grid().offset([-45.0, 0.0], [45.0, 0.0]), [-10.0, 0.0]);
+
+        grid.rebuild([200.0, 100.0], 10.0, vec![([45.0, 0.0], 1), ([-45.0, 0.0], 2)]);
+        assert_eq!(grid.payloads(), &[1, 2]);
+        assert_eq!(grid.grid().offset([-45.0, 0.0], [45.0, 0.0]), [90.0, 0.0]);
+    }
+}